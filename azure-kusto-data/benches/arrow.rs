@@ -0,0 +1,109 @@
+use arrow::record_batch::RecordBatch;
+use azure_kusto_data::arrow::{convert_column, decode_primary_results};
+use azure_kusto_data::models::{TableKind, V2QueryResult};
+use azure_kusto_data::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Builds a non progressive V2 response with a single primary result of the given number of rows.
+fn response_body(rows: usize) -> Vec<u8> {
+    let rows: Vec<Value> = (0..rows)
+        .map(|i| {
+            json!([
+                i,
+                format!("value {i}"),
+                i % 2 == 0,
+                i as f64 / 3.0,
+                "2023-01-01T12:34:56.789Z",
+                "1.02:03:04.0050000"
+            ])
+        })
+        .collect();
+    let frames = json!([
+        {"FrameType": "DataSetHeader", "IsProgressive": false, "Version": "v2.0"},
+        {
+            "FrameType": "DataTable",
+            "TableId": 0,
+            "TableName": "PrimaryResult",
+            "TableKind": "PrimaryResult",
+            "Columns": [
+                {"ColumnName": "long_col", "ColumnType": "long"},
+                {"ColumnName": "string_col", "ColumnType": "string"},
+                {"ColumnName": "bool_col", "ColumnType": "bool"},
+                {"ColumnName": "real_col", "ColumnType": "real"},
+                {"ColumnName": "datetime_col", "ColumnType": "datetime"},
+                {"ColumnName": "timespan_col", "ColumnType": "timespan"}
+            ],
+            "Rows": rows
+        },
+        {"FrameType": "DataSetCompletion", "HasErrors": false, "Cancelled": false}
+    ]);
+    serde_json::to_vec(&frames).unwrap()
+}
+
+/// The conversion through per column `serde_json::Value`s.
+fn transpose_columns(body: &[u8]) -> Vec<RecordBatch> {
+    let frames: Vec<V2QueryResult> = serde_json::from_slice(body).unwrap();
+    frames
+        .into_iter()
+        .filter_map(|frame| match frame {
+            V2QueryResult::DataTable(table) if table.table_kind == TableKind::PrimaryResult => {
+                Some(table)
+            }
+            _ => None,
+        })
+        .map(|table| {
+            let mut columns = vec![Vec::with_capacity(table.rows.len()); table.columns.len()];
+            for row in table.rows {
+                if let Value::Array(values) = row {
+                    for (i, value) in values.into_iter().enumerate() {
+                        columns[i].push(value);
+                    }
+                }
+            }
+            let (fields, arrays): (Vec<_>, Vec<_>) = columns
+                .into_iter()
+                .zip(&table.columns)
+                .map(|(data, column)| convert_column(data, column).unwrap())
+                .unzip();
+            RecordBatch::try_new(Arc::new(arrow::datatypes::Schema::new(fields)), arrays).unwrap()
+        })
+        .collect()
+}
+
+/// The conversion of parsed frames into batches.
+fn parsed_frames(body: &[u8]) -> Vec<RecordBatch> {
+    KustoResponseDataSetV2 {
+        results: serde_json::from_slice(body).unwrap(),
+        ..Default::default()
+    }
+    .into_record_batches()
+    .collect::<Result<_, _>>()
+    .unwrap()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("arrow");
+    for rows in [1_000, 100_000] {
+        let body = response_body(rows);
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("transpose columns", rows),
+            &body,
+            |b, body| b.iter(|| transpose_columns(body)),
+        );
+        group.bench_with_input(BenchmarkId::new("parsed frames", rows), &body, |b, body| {
+            b.iter(|| parsed_frames(body))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("columnar decode", rows),
+            &body,
+            |b, body| b.iter(|| decode_primary_results(body).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -131,7 +131,10 @@ async fn non_progressive(args: &Args, client: &KustoClient) {
     }
 
     // Print the primary tables
-    let primary_results = response.into_primary_results().collect::<Vec<_>>();
+    let primary_results = response
+        .into_primary_results()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
     println!("primary results: {:#?}", primary_results);
 }
 
@@ -170,7 +173,7 @@ async fn to_struct(args: &Args, client: &KustoClient) -> Result<(), Box<dyn Erro
     let results = response
         .into_primary_results()
         .next()
-        .ok_or_else(|| "Expected to get a primary result, but got none".to_string())?;
+        .ok_or_else(|| "Expected to get a primary result, but got none".to_string())??;
 
     let rows = results.rows;
 
@@ -1,19 +1,38 @@
+//! Conversion of query results into `arrow` [RecordBatch]es.
+
 use std::convert::TryInto;
+use std::io::Write;
+use std::num::NonZeroUsize;
 
+use std::collections::{HashMap, VecDeque};
 use std::str::FromStr;
 use std::sync::Arc;
 
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type};
 use arrow_array::{
-    ArrayRef, BooleanArray, DurationNanosecondArray, Float64Array, Int32Array, Int64Array,
-    RecordBatch, StringArray, TimestampNanosecondArray,
+    ArrayRef, BooleanArray, Decimal256Array, DurationNanosecondArray, FixedSizeBinaryArray,
+    Float64Array, Int32Array, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
 };
-use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use arrow_buffer::i256;
+use arrow_cast::cast::{cast_with_options, CastOptions};
+use arrow_json::writer::record_batches_to_json_rows;
+use arrow_json::LineDelimitedWriter;
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow_select::concat::concat_batches;
 use azure_core::error::{ErrorKind, ResultExt};
-use serde_json::Value;
+use futures::{stream, Stream, StreamExt};
+use serde_json::{Map, Value};
+use time::{Duration, OffsetDateTime};
+
+mod decoder;
 
-use crate::error::Result;
+pub use decoder::{decode_primary_results, RecordBatchDecoder};
+pub(crate) use decoder::{BatchMode, FramesDecoder};
+
+use crate::error::{Error, Result};
 use crate::models::ColumnType;
-use crate::models::{Column, DataTable};
+use crate::models::{Column, DataTable, TableV1};
 use crate::types::{KustoDateTime, KustoDuration};
 
 fn convert_array_string(values: Vec<Value>) -> Result<ArrayRef> {
@@ -82,69 +101,560 @@ fn convert_array_i64(values: Vec<Value>) -> Result<ArrayRef> {
     Ok(Arc::new(Int64Array::from(ints)))
 }
 
-pub fn convert_column(data: Vec<Value>, column: &Column) -> Result<(Field, ArrayRef)> {
-    let column_name = &column.column_name;
+/// Precision of the Arrow `Decimal256` type Kusto decimals are converted to,
+/// holding the 29 integer digits of the largest Kusto decimals along with [DECIMAL_SCALE] fractional digits.
+pub const DECIMAL_PRECISION: u8 = 76;
+/// Scale of the Arrow `Decimal256` type Kusto decimals are converted to, the largest scale of Kusto decimals.
+/// Decimals with more fractional digits, e.g. in scientific notation, are rounded half away from zero.
+pub const DECIMAL_SCALE: i8 = 28;
+
+/// Key of the field metadata holding the name of an Arrow extension type.
+pub(crate) const EXTENSION_NAME_KEY: &str = "ARROW:extension:name";
+
+fn extension_metadata(name: &str) -> HashMap<String, String> {
+    HashMap::from([(EXTENSION_NAME_KEY.to_string(), name.to_string())])
+}
+
+fn convert_array_dynamic(values: Vec<Value>) -> Result<ArrayRef> {
+    let strings = values
+        .into_iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            v => serde_json::to_string(&v).map(Some),
+        })
+        .collect::<std::result::Result<Vec<Option<String>>, _>>()?;
+    Ok(Arc::new(StringArray::from(strings)))
+}
+
+fn convert_array_guid(values: Vec<Value>) -> Result<ArrayRef> {
+    let guids = values
+        .into_iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            Value::String(s) => uuid::Uuid::parse_str(&s)
+                .map(|u| Some(u.into_bytes()))
+                .map_err(|e| Error::ConversionError(format!("invalid guid {s}: {e}"))),
+            v => Err(Error::ConversionError(format!("invalid guid {v}"))),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let array = FixedSizeBinaryArray::try_from_sparse_iter_with_size(guids.into_iter(), 16)
+        .context(ErrorKind::DataConversion, "Failed to create guid array")?;
+    Ok(Arc::new(array))
+}
+
+/// Parses a decimal string, optionally in scientific notation, into an integer scaled by [DECIMAL_SCALE].
+pub(crate) fn parse_decimal(value: &str) -> Option<i256> {
+    let (mantissa, exponent) = match value.find(['e', 'E']) {
+        Some(i) => (&value[..i], value[i + 1..].parse::<i32>().ok()?),
+        None => (value, 0),
+    };
+    let (negative, mantissa) = match mantissa.strip_prefix('-') {
+        Some(m) => (true, m),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    // The digits, and the power of ten to multiply them by to get the scaled value.
+    let digits = integer.chars().chain(fraction.chars());
+    let shift = i32::from(DECIMAL_SCALE)
+        .checked_add(exponent)?
+        .checked_sub(i32::try_from(fraction.len()).ok()?)?;
+
+    let ten = i256::from_i128(10);
+    let mut scaled = i256::ZERO;
+    let mut round_up = false;
+    let kept = i32::try_from(integer.len() + fraction.len())
+        .ok()?
+        .checked_add(shift.min(0))?;
+    for (i, digit) in digits.enumerate() {
+        let digit = digit.to_digit(10)?;
+        match i32::try_from(i).ok()?.cmp(&kept) {
+            std::cmp::Ordering::Less => {
+                scaled = scaled
+                    .checked_mul(ten)?
+                    .checked_add(i256::from_i128(digit.into()))?
+            }
+            std::cmp::Ordering::Equal => round_up = digit >= 5,
+            std::cmp::Ordering::Greater => break,
+        }
+    }
+    if shift > 0 && scaled != i256::ZERO {
+        scaled = scaled.checked_mul(ten.checked_pow(shift.unsigned_abs())?)?;
+    }
+    if round_up {
+        scaled = scaled.checked_add(i256::ONE)?;
+    }
+    Some(if negative {
+        scaled.wrapping_neg()
+    } else {
+        scaled
+    })
+}
+
+fn convert_array_decimal(values: Vec<Value>) -> Result<ArrayRef> {
+    let decimals = values
+        .into_iter()
+        .map(|v| {
+            let s = match v {
+                Value::Null => return Ok(None),
+                Value::String(s) => s,
+                Value::Number(n) => n.to_string(),
+                v => return Err(Error::ConversionError(format!("invalid decimal {v}"))),
+            };
+            parse_decimal(&s)
+                .map(Some)
+                .ok_or_else(|| Error::ConversionError(format!("invalid decimal {s}")))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let array = Decimal256Array::from(decimals)
+        .with_precision_and_scale(DECIMAL_PRECISION, DECIMAL_SCALE)
+        .context(ErrorKind::DataConversion, "Failed to create decimal array")?;
+    array
+        .validate_decimal_precision(DECIMAL_PRECISION)
+        .context(ErrorKind::DataConversion, "Decimal out of range")?;
+    Ok(Arc::new(array))
+}
+
+/// The Arrow type the values of a column of the given type are converted to.
+///
+/// Dynamic values are JSON text and guids are 16 bytes, marked as such by the extension metadata of [column_field].
+#[must_use]
+pub fn kusto_type_to_arrow(column_type: &ColumnType) -> DataType {
+    match column_type {
+        ColumnType::String | ColumnType::Dynamic => DataType::Utf8,
+        ColumnType::Bool => DataType::Boolean,
+        ColumnType::Int => DataType::Int32,
+        ColumnType::Long => DataType::Int64,
+        ColumnType::Real => DataType::Float64,
+        ColumnType::Datetime => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        ColumnType::Timespan => DataType::Duration(TimeUnit::Nanosecond),
+        ColumnType::Guid => DataType::FixedSizeBinary(16),
+        ColumnType::Decimal => DataType::Decimal256(DECIMAL_PRECISION, DECIMAL_SCALE),
+    }
+}
+
+/// The Kusto type that can hold the values of an Arrow type.
+///
+/// Nested types are mapped to dynamic columns, and dictionaries to the type of their values.
+/// Unsigned 64-bit integers may not fit in a long, and are mapped to decimal columns.
+/// Fails for types without an equivalent, such as binary or interval types.
+pub fn arrow_type_to_kusto(data_type: &DataType) -> Result<ColumnType> {
+    Ok(match data_type {
+        DataType::Boolean => ColumnType::Bool,
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::UInt8 | DataType::UInt16 => {
+            ColumnType::Int
+        }
+        DataType::Int64 | DataType::UInt32 => ColumnType::Long,
+        DataType::Float16 | DataType::Float32 | DataType::Float64 => ColumnType::Real,
+        DataType::Utf8 | DataType::LargeUtf8 => ColumnType::String,
+        DataType::Timestamp(_, _) | DataType::Date32 | DataType::Date64 => ColumnType::Datetime,
+        DataType::Duration(_) | DataType::Time32(_) | DataType::Time64(_) => ColumnType::Timespan,
+        DataType::FixedSizeBinary(16) => ColumnType::Guid,
+        DataType::UInt64 | DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => {
+            ColumnType::Decimal
+        }
+        DataType::List(_)
+        | DataType::LargeList(_)
+        | DataType::FixedSizeList(_, _)
+        | DataType::Struct(_)
+        | DataType::Map(_, _) => ColumnType::Dynamic,
+        DataType::Dictionary(_, value_type) => arrow_type_to_kusto(value_type)?,
+        data_type => {
+            return Err(Error::UnsupportedOperation(format!(
+                "Arrow type {data_type} has no Kusto equivalent"
+            )))
+        }
+    })
+}
+
+/// The Arrow field the values of a column are converted to.
+pub fn column_field(column: &Column) -> Field {
+    let field = Field::new(
+        &column.column_name,
+        kusto_type_to_arrow(&column.column_type),
+        true,
+    );
     match column.column_type {
-        ColumnType::String => convert_array_string(data)
-            .map(|data| (Field::new(column_name, DataType::Utf8, true), data)),
-        ColumnType::Bool => convert_array_bool(data)
-            .map(|data| (Field::new(column_name, DataType::Boolean, true), data)),
-        ColumnType::Int => convert_array_i32(data)
-            .map(|data| (Field::new(column_name, DataType::Int32, true), data)),
-        ColumnType::Long => convert_array_i64(data)
-            .map(|data| (Field::new(column_name, DataType::Int64, true), data)),
-        ColumnType::Real => convert_array_float(data)
-            .map(|data| (Field::new(column_name, DataType::Float64, true), data)),
-        ColumnType::Datetime => convert_array_datetime(data).map(|data| {
-            (
-                Field::new(
-                    column_name,
-                    DataType::Timestamp(TimeUnit::Nanosecond, None),
-                    true,
-                ),
-                data,
-            )
-        }),
-        ColumnType::Timespan => convert_array_timespan(data).map(|data| {
-            (
-                Field::new(column_name, DataType::Duration(TimeUnit::Nanosecond), true),
-                data,
-            )
-        }),
-        _ => todo!(),
+        ColumnType::Dynamic => field.with_metadata(extension_metadata("arrow.json")),
+        ColumnType::Guid => field.with_metadata(extension_metadata("arrow.uuid")),
+        _ => field,
     }
 }
 
-pub fn convert_table(table: DataTable) -> Result<RecordBatch> {
-    let mut buffer: Vec<Vec<Value>> = Vec::with_capacity(table.columns.len());
-    let mut fields: Vec<Field> = Vec::with_capacity(table.columns.len());
-    let mut columns: Vec<ArrayRef> = Vec::with_capacity(table.columns.len());
-
-    for _ in 0..table.columns.len() {
-        buffer.push(Vec::with_capacity(table.rows.len()));
-    }
-    table.rows.into_iter().for_each(|row| match row {
-        Value::Array(v) => {
-            v.into_iter().enumerate().for_each(|(i, v)| {
-                buffer[i].push(v);
-            });
+/// The column holding the values of an Arrow field. The reverse of [column_field].
+///
+/// Fields with the `arrow.json` extension are mapped to dynamic columns. See [arrow_type_to_kusto] for other fields.
+pub fn field_column(field: &Field) -> Result<Column> {
+    let column_type = match field.metadata().get(EXTENSION_NAME_KEY).map(String::as_str) {
+        Some("arrow.json") => ColumnType::Dynamic,
+        _ => arrow_type_to_kusto(field.data_type())?,
+    };
+    Ok(Column {
+        column_name: field.name().clone(),
+        column_type,
+    })
+}
+
+/// The columns holding the values of an Arrow schema, e.g. to create a table with
+/// [create_merge_table_command](crate::management::create_merge_table_command)
+/// and its ingestion mapping with [column_mappings](crate::ingestion_mapping::column_mappings).
+pub fn schema_columns(schema: &Schema) -> Result<Vec<Column>> {
+    schema.fields().iter().map(|f| field_column(f)).collect()
+}
+
+/// Converts the rows of a [RecordBatch] into JSON objects, as ingested with the `multijson` format.
+///
+/// The columns are typed as by [schema_columns], and their values formatted as Kusto parses them,
+/// e.g. `1.02:03:04.0000000` for timespans. Dynamic values are embedded as JSON, and null values are omitted.
+pub fn record_batch_json_rows(batch: &RecordBatch) -> Result<Vec<Map<String, Value>>> {
+    let schema = batch.schema();
+    let columns = schema_columns(&schema)?;
+    let mut rows = vec![Map::new(); batch.num_rows()];
+    for (column, array) in columns.iter().zip(batch.columns()) {
+        let values = json_values(array, &column.column_type)?;
+        for (row, value) in rows.iter_mut().zip(values) {
+            if let Some(value) = value {
+                row.insert(column.column_name.clone(), value);
+            }
         }
-        _ => unreachable!("Must be an array"),
+    }
+    Ok(rows)
+}
+
+/// Writes the rows of a [RecordBatch] as lines of JSON objects with a [LineDelimitedWriter], as ingested with the `multijson` format,
+/// returning the writer they were written to.
+///
+/// The values are formatted as by [record_batch_json_rows], after converting the columns into arrays that `arrow-json` writes as such.
+/// `arrow-json` can't embed JSON text, so batches with text columns of the `arrow.json` extension are converted with [record_batch_json_rows] instead.
+pub fn write_json_lines<W: Write>(batch: &RecordBatch, writer: W) -> Result<W> {
+    let mut writer = LineDelimitedWriter::new(writer);
+    match json_batch(batch)? {
+        Some(batch) => writer.write(&batch),
+        None => record_batch_json_rows(batch)?
+            .into_iter()
+            .try_for_each(|row| writer.write_row(&Value::Object(row))),
+    }
+    .and_then(|()| writer.finish())
+    .context(ErrorKind::DataConversion, "Failed to write JSON rows")?;
+    Ok(writer.into_inner())
+}
+
+/// Converts the columns of a batch into arrays that `arrow-json` writes as Kusto parses them,
+/// or [None] if a column holds JSON text, which `arrow-json` can't embed.
+fn json_batch(batch: &RecordBatch) -> Result<Option<RecordBatch>> {
+    let schema = batch.schema();
+    let mut fields = Vec::with_capacity(batch.num_columns());
+    let mut arrays = Vec::with_capacity(batch.num_columns());
+    for (field, array) in schema.fields().iter().zip(batch.columns()) {
+        let Some(array) = json_array(array, &field_column(field)?.column_type)? else {
+            return Ok(None);
+        };
+        fields.push(Field::new(field.name(), array.data_type().clone(), true));
+        arrays.push(array);
+    }
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .context(ErrorKind::DataConversion, "Failed to convert batch")?;
+    Ok(Some(batch))
+}
+
+/// Converts an array holding values of the given type into an array that `arrow-json` writes as Kusto parses it, see [json_batch].
+fn json_array(array: &ArrayRef, column_type: &ColumnType) -> Result<Option<ArrayRef>> {
+    Ok(Some(match column_type {
+        ColumnType::Bool => cast_array(array, &DataType::Boolean)?,
+        ColumnType::Int | ColumnType::Long => cast_array(array, &DataType::Int64)?,
+        // Non-finite reals have no JSON representation, so they are ingested as nulls
+        ColumnType::Real => Arc::new(
+            cast_array(array, &DataType::Float64)?
+                .as_primitive::<Float64Type>()
+                .iter()
+                .map(|v| v.filter(|v| v.is_finite()))
+                .collect::<Float64Array>(),
+        ),
+        ColumnType::String | ColumnType::Decimal => cast_array(array, &DataType::Utf8)?,
+        ColumnType::Datetime | ColumnType::Timespan | ColumnType::Guid => Arc::new(
+            json_values(array, column_type)?
+                .iter()
+                .map(|v| v.as_ref().and_then(Value::as_str))
+                .collect::<StringArray>(),
+        ),
+        ColumnType::Dynamic => match array.data_type() {
+            DataType::Dictionary(_, value_type) => {
+                return json_array(&cast_array(array, value_type)?, column_type)
+            }
+            // Text with the `arrow.json` extension
+            DataType::Utf8 | DataType::LargeUtf8 => return Ok(None),
+            _ => array.clone(),
+        },
+    }))
+}
+
+/// Casts an array, failing rather than producing nulls for values that do not fit.
+fn cast_array(array: &ArrayRef, data_type: &DataType) -> Result<ArrayRef> {
+    let options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    Ok(cast_with_options(array, data_type, &options).context(
+        ErrorKind::DataConversion,
+        format!("Failed to convert {} values", array.data_type()),
+    )?)
+}
+
+/// The nanoseconds since the epoch of datetimes, or in durations and times of day.
+fn nanoseconds(array: &ArrayRef, column_type: &ColumnType) -> Result<Vec<Option<i64>>> {
+    let unit = match (column_type, array.data_type()) {
+        (ColumnType::Datetime, _) => DataType::Timestamp(TimeUnit::Nanosecond, None),
+        (_, DataType::Time32(_) | DataType::Time64(_)) => DataType::Time64(TimeUnit::Nanosecond),
+        _ => DataType::Duration(TimeUnit::Nanosecond),
+    };
+    let nanoseconds = cast_array(&cast_array(array, &unit)?, &DataType::Int64)?;
+    Ok(nanoseconds.as_primitive::<Int64Type>().iter().collect())
+}
+
+/// Converts the values of an array holding values of the given type into JSON values, [None] for nulls.
+fn json_values(array: &ArrayRef, column_type: &ColumnType) -> Result<Vec<Option<Value>>> {
+    Ok(match column_type {
+        ColumnType::Bool => cast_array(array, &DataType::Boolean)?
+            .as_boolean()
+            .iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+        ColumnType::Int | ColumnType::Long => cast_array(array, &DataType::Int64)?
+            .as_primitive::<Int64Type>()
+            .iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+        // Non-finite reals have no JSON representation, so they are ingested as nulls
+        ColumnType::Real => cast_array(array, &DataType::Float64)?
+            .as_primitive::<Float64Type>()
+            .iter()
+            .map(|v| v.and_then(serde_json::Number::from_f64).map(Value::Number))
+            .collect(),
+        ColumnType::String | ColumnType::Decimal => cast_array(array, &DataType::Utf8)?
+            .as_string::<i32>()
+            .iter()
+            .map(|v| v.map(Value::from))
+            .collect(),
+        ColumnType::Datetime => nanoseconds(array, column_type)?
+            .into_iter()
+            .map(|v| {
+                v.map(|v| {
+                    OffsetDateTime::from_unix_timestamp_nanos(v.into())
+                        .map(|d| Value::from(KustoDateTime::from(d).to_string()))
+                        .context(ErrorKind::DataConversion, "Datetime out of range")
+                })
+                .transpose()
+            })
+            .collect::<azure_core::Result<_>>()?,
+        ColumnType::Timespan => nanoseconds(array, column_type)?
+            .into_iter()
+            .map(|v| {
+                v.map(|v| {
+                    KustoDuration::from(Duration::nanoseconds(v))
+                        .to_string()
+                        .into()
+                })
+            })
+            .collect(),
+        ColumnType::Guid => cast_array(array, &DataType::FixedSizeBinary(16))?
+            .as_fixed_size_binary()
+            .iter()
+            .map(|v| {
+                v.map(|v| {
+                    uuid::Uuid::from_slice(v)
+                        .map(|g| Value::from(g.to_string()))
+                        .context(ErrorKind::DataConversion, "Invalid guid")
+                })
+                .transpose()
+            })
+            .collect::<azure_core::Result<_>>()?,
+        ColumnType::Dynamic => match array.data_type() {
+            DataType::Dictionary(_, value_type) => {
+                return json_values(&cast_array(array, value_type)?, column_type)
+            }
+            // Text with the `arrow.json` extension
+            DataType::Utf8 | DataType::LargeUtf8 => cast_array(array, &DataType::Utf8)?
+                .as_string::<i32>()
+                .iter()
+                .map(|v| v.map(serde_json::from_str).transpose())
+                .collect::<std::result::Result<_, _>>()?,
+            _ => {
+                let field = Field::new("value", array.data_type().clone(), true);
+                let batch =
+                    RecordBatch::try_new(Arc::new(Schema::new(vec![field])), vec![array.clone()])
+                        .context(
+                        ErrorKind::DataConversion,
+                        "Failed to convert dynamic values",
+                    )?;
+                record_batches_to_json_rows(&[&batch])
+                    .context(
+                        ErrorKind::DataConversion,
+                        "Failed to convert dynamic values",
+                    )?
+                    .into_iter()
+                    .map(|mut row| row.remove("value"))
+                    .collect()
+            }
+        },
+    })
+}
+
+/// Converts the values of a single column into an Arrow array, along with its field.
+pub fn convert_column(data: Vec<Value>, column: &Column) -> Result<(Field, ArrayRef)> {
+    let array = match column.column_type {
+        ColumnType::String => convert_array_string(data),
+        ColumnType::Bool => convert_array_bool(data),
+        ColumnType::Int => convert_array_i32(data),
+        ColumnType::Long => convert_array_i64(data),
+        ColumnType::Real => convert_array_float(data),
+        ColumnType::Datetime => convert_array_datetime(data),
+        ColumnType::Timespan => convert_array_timespan(data),
+        ColumnType::Dynamic => convert_array_dynamic(data),
+        ColumnType::Guid => convert_array_guid(data),
+        ColumnType::Decimal => convert_array_decimal(data),
+    }?;
+    Ok((column_field(column), array))
+}
+
+/// Converts a table into a [RecordBatch].
+pub fn convert_table(table: DataTable) -> Result<RecordBatch> {
+    convert_rows(&table.columns, table.rows)
+}
+
+/// Converts a table of a V1 response into a [RecordBatch], typing its columns as [TableColumn](crate::models::TableColumn) does.
+pub fn convert_table_v1(table: TableV1) -> Result<RecordBatch> {
+    let columns: Vec<Column> = table.columns.into_iter().map(Column::from).collect();
+    convert_rows(&columns, table.rows.into_iter().map(Value::Array).collect())
+}
+
+/// Converts rows, each a JSON array of values in the order of the columns, into a [RecordBatch].
+pub fn convert_rows(table_columns: &[Column], rows: Vec<Value>) -> Result<RecordBatch> {
+    let mut decoder = RecordBatchDecoder::new(table_columns);
+    decoder.decode_values(rows)?;
+    decoder.flush()
+}
+
+/// Decodes the first primary result in the raw frames of a response into a stream of [RecordBatch]es,
+/// one per fragment or regrouped into batches of up to `max_rows` rows.
+///
+/// The frames are read up to the header of the primary result, so that its schema is returned along with the stream,
+/// even if the table has no rows. If the response has no primary result, the schema is empty.
+/// Errors reported by the `DataSetCompletion` frame are yielded as [Error::PartialQueryFailure] if `fail_on_errors` is set.
+/// The stream ends after the first error.
+pub(crate) async fn record_batch_stream(
+    frames: impl Stream<Item = Result<Vec<u8>>> + Unpin,
+    max_rows: Option<NonZeroUsize>,
+    fail_on_errors: bool,
+) -> Result<(SchemaRef, impl Stream<Item = Result<RecordBatch>>)> {
+    let mut state = BatchStreamState {
+        frames,
+        decoder: FramesDecoder::new(BatchMode::Fragments),
+        max_rows,
+        ready: VecDeque::new(),
+        carry: None,
+        fail_on_errors,
+        done: false,
+        error: None,
+    };
+    let schema = state.read_schema().await?;
+
+    let batches = stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        let batch = state.next_batch().await?;
+        let state = batch.is_ok().then_some(state);
+        Some((batch, state))
     });
+    Ok((schema, batches))
+}
 
-    buffer
-        .into_iter()
-        .zip(table.columns.into_iter())
-        .map(|(data, column)| convert_column(data, &column))
-        .try_for_each::<_, Result<()>>(|result| {
-            let (field, data) = result?;
-            fields.push(field);
-            columns.push(data);
-            Ok(())
-        })?;
+struct BatchStreamState<S> {
+    frames: S,
+    decoder: FramesDecoder,
+    max_rows: Option<NonZeroUsize>,
+    ready: VecDeque<RecordBatch>,
+    /// Rows that do not fill a batch of `max_rows` rows yet.
+    carry: Option<RecordBatch>,
+    fail_on_errors: bool,
+    /// Whether all frames were read.
+    done: bool,
+    /// An error found at the end of the response, yielded after the remaining batches.
+    error: Option<Error>,
+}
+
+impl<S: Stream<Item = Result<Vec<u8>>> + Unpin> BatchStreamState<S> {
+    /// Reads frames until the schema of the primary result is known, or all frames were read.
+    async fn read_schema(&mut self) -> Result<SchemaRef> {
+        loop {
+            if let Some(schema) = self.decoder.schema() {
+                return Ok(schema);
+            }
+            if self.done {
+                return Ok(Arc::new(Schema::empty()));
+            }
+            self.read_frame().await?;
+        }
+    }
 
-    Ok(RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
-        .context(ErrorKind::DataConversion, "Failed to create record batch")?)
+    async fn next_batch(&mut self) -> Option<Result<RecordBatch>> {
+        loop {
+            if let Some(batch) = self.ready.pop_front() {
+                return Some(Ok(batch));
+            }
+            if self.done {
+                return self.error.take().map(Err);
+            }
+            if let Err(e) = self.read_frame().await {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    /// Reads and decodes the next frame, noting the errors found at the end of the response once all frames were read.
+    async fn read_frame(&mut self) -> Result<()> {
+        match self.frames.next().await {
+            Some(frame) => self.decode(&frame?),
+            None => {
+                self.done = true;
+                self.ready.extend(self.carry.take());
+                self.error = self.decoder.finish().err().or_else(|| {
+                    self.decoder
+                        .take_errors()
+                        .filter(|_| self.fail_on_errors)
+                        .map(|errors| Error::PartialQueryFailure(errors, Default::default()))
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn decode(&mut self, frame: &[u8]) -> Result<()> {
+        self.decoder.decode_frame(frame)?;
+        for batch in self.decoder.take_batches() {
+            let Some(max_rows) = self.max_rows.map(NonZeroUsize::get) else {
+                self.ready.push_back(batch);
+                continue;
+            };
+            let mut batch = match self.carry.take() {
+                Some(carry) => concat_batches(&batch.schema(), [&carry, &batch])
+                    .context(ErrorKind::DataConversion, "Failed to concatenate batches")?,
+                None => batch,
+            };
+            while batch.num_rows() >= max_rows {
+                self.ready.push_back(batch.slice(0, max_rows));
+                batch = batch.slice(max_rows, batch.num_rows() - max_rows);
+            }
+            self.carry = (batch.num_rows() > 0).then_some(batch);
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -152,6 +662,7 @@ mod tests {
     use super::*;
     use crate::models::{TableKind, V2QueryResult};
     use crate::operations::query::KustoResponseDataSetV2;
+    use arrow_array::Array;
     use std::path::PathBuf;
 
     #[test]
@@ -207,7 +718,10 @@ mod tests {
         let data = std::fs::read_to_string(path).expect("Failed to read file");
         let tables: Vec<V2QueryResult> =
             serde_json::from_str(&data).expect("Failed to deserialize result table");
-        let response = KustoResponseDataSetV2 { results: tables };
+        let response = KustoResponseDataSetV2 {
+            results: tables,
+            ..Default::default()
+        };
         let record_batches = response
             .record_batches()
             .collect::<std::result::Result<Vec<_>, _>>()
@@ -216,4 +730,287 @@ mod tests {
         assert!(record_batches[0].num_columns() > 0);
         assert!(record_batches[0].num_rows() > 0);
     }
+
+    async fn stream_batches(
+        frames: &str,
+        max_rows: Option<NonZeroUsize>,
+        fail_on_errors: bool,
+    ) -> (SchemaRef, Vec<Result<usize>>) {
+        let frames: Vec<Value> = serde_json::from_str(frames).expect("Failed to parse");
+        let frames = stream::iter(
+            frames
+                .iter()
+                .map(|frame| Ok(serde_json::to_vec(frame).expect("Failed to serialize")))
+                .collect::<Vec<_>>(),
+        );
+        let (schema, batches) = record_batch_stream(frames, max_rows, fail_on_errors)
+            .await
+            .expect("Failed to read schema");
+        let sizes = batches.map(|batch| batch.map(|b| b.num_rows())).collect();
+        (schema, sizes.await)
+    }
+
+    #[tokio::test]
+    async fn record_batch_stream_chunks_rows() {
+        let frames = r#"[
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[1], [2], [3]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[4]]},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 4},
+            {"FrameType": "DataSetCompletion", "HasErrors": true, "Cancelled": false, "OneApiErrors": []}
+        ]"#;
+
+        let sizes = |results: Vec<Result<usize>>| {
+            results
+                .into_iter()
+                .collect::<Result<Vec<_>>>()
+                .expect("Failed")
+        };
+        let (_, results) = stream_batches(frames, None, false).await;
+        assert_eq!(sizes(results), vec![3, 1]);
+        let (_, results) = stream_batches(frames, NonZeroUsize::new(2), false).await;
+        assert_eq!(sizes(results), vec![2, 2]);
+
+        let (_, results) = stream_batches(frames, NonZeroUsize::new(3), true).await;
+        assert_eq!(results.len(), 3);
+        assert!(matches!(results[..2], [Ok(3), Ok(1)]));
+        assert!(matches!(results[2], Err(Error::PartialQueryFailure(..))));
+    }
+
+    #[tokio::test]
+    async fn record_batch_stream_has_schema_of_empty_table() {
+        let (schema, results) = stream_batches(
+            r#"[
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}]},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 0},
+            {"FrameType": "DataSetCompletion", "HasErrors": false, "Cancelled": false}
+        ]"#,
+            None,
+            true,
+        )
+        .await;
+
+        assert_eq!(
+            schema,
+            Arc::new(Schema::new(vec![Field::new("x", DataType::Int64, true)]))
+        );
+        assert!(results.is_empty());
+
+        let (schema, results) = stream_batches(
+            r#"[{"FrameType": "DataSetCompletion", "HasErrors": false, "Cancelled": false}]"#,
+            None,
+            true,
+        )
+        .await;
+        assert!(schema.fields().is_empty());
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn parse_decimals() {
+        let scale = 10_i128.pow(28);
+        let decimal = |v: i128| Some(i256::from_i128(v));
+        assert_eq!(parse_decimal("1.5"), decimal(15 * scale / 10));
+        assert_eq!(parse_decimal("-42"), decimal(-42 * scale));
+        assert_eq!(parse_decimal("1E-05"), decimal(scale / 100_000));
+        assert_eq!(parse_decimal("0.00000000000000000000000000015"), decimal(2));
+        assert_eq!(
+            parse_decimal("-0.00000000000000000000000000014"),
+            decimal(-1)
+        );
+        assert_eq!(
+            parse_decimal("79228162514264337593543950335"),
+            i256::from_string(&format!("79228162514264337593543950335{}", "0".repeat(28)))
+        );
+        assert_eq!(parse_decimal("1e-2147483648"), decimal(0));
+        assert_eq!(parse_decimal("1e2147483647"), None);
+        assert_eq!(parse_decimal("abc"), None);
+        assert_eq!(parse_decimal("."), None);
+        assert_eq!(parse_decimal("1e50"), None);
+    }
+
+    #[test]
+    fn convert_dynamic_guid_decimal() {
+        let columns = serde_json::from_str::<Vec<Column>>(
+            r#"[
+            {"ColumnName": "d", "ColumnType": "dynamic"},
+            {"ColumnName": "g", "ColumnType": "guid"},
+            {"ColumnName": "m", "ColumnType": "decimal"}
+        ]"#,
+        )
+        .expect("Failed to parse columns");
+        let rows = serde_json::json!([
+            [{"a": [1, 2]}, "74be27de-1e4e-49d9-b579-fe0b331d3642", "1.25"],
+            [null, null, null],
+            [null, null, "-79228162514264337593543950335"]
+        ]);
+
+        let batch = convert_rows(&columns, serde_json::from_value(rows).unwrap())
+            .expect("Failed to convert rows");
+
+        let dynamic = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(dynamic.value(0), r#"{"a":[1,2]}"#);
+        assert!(dynamic.is_null(1));
+        assert_eq!(
+            batch.schema().field(0).metadata()[EXTENSION_NAME_KEY],
+            "arrow.json"
+        );
+
+        let guids = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<FixedSizeBinaryArray>()
+            .unwrap();
+        assert_eq!(
+            guids.value(0),
+            uuid::Uuid::parse_str("74be27de-1e4e-49d9-b579-fe0b331d3642")
+                .unwrap()
+                .as_bytes()
+        );
+        assert!(guids.is_null(1));
+
+        let decimals = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Decimal256Array>()
+            .unwrap();
+        assert_eq!(
+            decimals.value_as_string(0),
+            "1.2500000000000000000000000000"
+        );
+        assert!(decimals.is_null(1));
+        assert_eq!(
+            decimals.value_as_string(2),
+            "-79228162514264337593543950335.0000000000000000000000000000"
+        );
+    }
+
+    #[test]
+    fn converts_batches_to_json_rows() {
+        let columns = serde_json::from_str::<Vec<Column>>(
+            r#"[
+            {"ColumnName": "b", "ColumnType": "bool"},
+            {"ColumnName": "i", "ColumnType": "int"},
+            {"ColumnName": "r", "ColumnType": "real"},
+            {"ColumnName": "s", "ColumnType": "string"},
+            {"ColumnName": "dt", "ColumnType": "datetime"},
+            {"ColumnName": "ts", "ColumnType": "timespan"},
+            {"ColumnName": "d", "ColumnType": "dynamic"},
+            {"ColumnName": "g", "ColumnType": "guid"},
+            {"ColumnName": "m", "ColumnType": "decimal"}
+        ]"#,
+        )
+        .expect("Failed to parse columns");
+        let row = serde_json::json!([
+            true,
+            42,
+            1.5,
+            "text",
+            "2024-01-02T03:04:05.5Z",
+            "1.02:03:04.5000000",
+            {"a": [1, 2]},
+            "74be27de-1e4e-49d9-b579-fe0b331d3642",
+            "1.25"
+        ]);
+        let nulls = Value::Array(vec![Value::Null; columns.len()]);
+        let batch =
+            convert_rows(&columns, vec![row.clone(), nulls]).expect("Failed to convert rows");
+
+        let rows = record_batch_json_rows(&batch).expect("Failed to convert batch");
+
+        let mut expected = Map::new();
+        for (column, value) in columns.iter().zip(row.as_array().unwrap()) {
+            expected.insert(column.column_name.clone(), value.clone());
+        }
+        expected.insert("m".to_string(), "1.2500000000000000000000000000".into());
+        assert_eq!(rows, vec![expected, Map::new()]);
+
+        let json_lines = |batch: &RecordBatch| {
+            let lines = write_json_lines(batch, Vec::new()).expect("Failed to write batch");
+            lines
+                .split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| serde_json::from_slice(line).expect("Failed to parse line"))
+                .collect::<Vec<Map<String, Value>>>()
+        };
+        assert_eq!(json_lines(&batch), rows);
+        // Without the dynamic column, as `arrow.json` text, the batch is written by `arrow-json` itself
+        let batch = batch.project(&[0, 1, 2, 3, 4, 5, 7, 8]).unwrap();
+        assert!(json_batch(&batch).unwrap().is_some());
+        assert_eq!(
+            json_lines(&batch),
+            record_batch_json_rows(&batch).expect("Failed to convert batch")
+        );
+    }
+
+    #[test]
+    fn maps_types_both_ways() {
+        let kusto_types = [
+            ColumnType::Bool,
+            ColumnType::Datetime,
+            ColumnType::Dynamic,
+            ColumnType::Guid,
+            ColumnType::Int,
+            ColumnType::Long,
+            ColumnType::Real,
+            ColumnType::String,
+            ColumnType::Timespan,
+            ColumnType::Decimal,
+        ];
+        for column_type in kusto_types {
+            let column = Column {
+                column_name: "a".to_string(),
+                column_type,
+            };
+            assert_eq!(
+                field_column(&column_field(&column)).expect("Failed to map field"),
+                column
+            );
+        }
+
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::UInt8, false),
+            Field::new("b b", DataType::Date32, true),
+            Field::new_list("c", Field::new("item", DataType::Int64, true), true),
+            Field::new_dictionary("d", DataType::Int32, DataType::Utf8, true),
+            Field::new("e", DataType::UInt64, true),
+        ]);
+        let columns = schema_columns(&schema).expect("Failed to map schema");
+        assert_eq!(
+            columns
+                .iter()
+                .map(|c| c.column_type.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                ColumnType::Int,
+                ColumnType::Datetime,
+                ColumnType::Dynamic,
+                ColumnType::String,
+                ColumnType::Decimal
+            ]
+        );
+        assert_eq!(
+            crate::management::create_merge_table_command("T", &columns)
+                .expect("Failed to render command")
+                .as_str(),
+            ".create-merge table T (a:int, ['b b']:datetime, c:dynamic, d:string, e:decimal)"
+        );
+        assert!(arrow_type_to_kusto(&DataType::Binary).is_err());
+    }
+
+    #[test]
+    fn convert_invalid_guid_fails() {
+        let column = Column {
+            column_name: "g".to_string(),
+            column_type: ColumnType::Guid,
+        };
+        assert!(matches!(
+            convert_column(vec![Value::from("not a guid")], &column),
+            Err(Error::ConversionError(_))
+        ));
+    }
 }
@@ -0,0 +1,900 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    BooleanBuilder, Decimal256Builder, DurationNanosecondBuilder, FixedSizeBinaryBuilder,
+    Float64Builder, Int32Builder, Int64Builder, StringBuilder, TimestampNanosecondBuilder,
+};
+use arrow_array::types::{Decimal256Type, DecimalType};
+use arrow_array::{ArrayRef, RecordBatch, RecordBatchOptions};
+use arrow_schema::{Schema, SchemaRef};
+use azure_core::error::{ErrorKind, ResultExt};
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{column_field, parse_decimal, DECIMAL_PRECISION, DECIMAL_SCALE};
+use crate::error::{Error, ProtocolError, Result};
+use crate::models::{Column, ColumnType, OneApiError, TableFragmentType, TableKind};
+use crate::types::{KustoDateTime, KustoDuration};
+
+/// An Arrow array builder for the values of a single column.
+enum ColumnBuilder {
+    String(StringBuilder),
+    Bool(BooleanBuilder),
+    Int(Int32Builder),
+    Long(Int64Builder),
+    Real(Float64Builder),
+    Datetime(TimestampNanosecondBuilder),
+    Timespan(DurationNanosecondBuilder),
+    Dynamic(StringBuilder),
+    Guid(FixedSizeBinaryBuilder),
+    Decimal(Decimal256Builder),
+}
+
+impl ColumnBuilder {
+    fn new(column_type: &ColumnType) -> Self {
+        match column_type {
+            ColumnType::String => Self::String(StringBuilder::new()),
+            ColumnType::Bool => Self::Bool(BooleanBuilder::new()),
+            ColumnType::Int => Self::Int(Int32Builder::new()),
+            ColumnType::Long => Self::Long(Int64Builder::new()),
+            ColumnType::Real => Self::Real(Float64Builder::new()),
+            ColumnType::Datetime => Self::Datetime(TimestampNanosecondBuilder::new()),
+            ColumnType::Timespan => Self::Timespan(DurationNanosecondBuilder::new()),
+            ColumnType::Dynamic => Self::Dynamic(StringBuilder::new()),
+            ColumnType::Guid => Self::Guid(FixedSizeBinaryBuilder::new(16)),
+            ColumnType::Decimal => Self::Decimal(
+                Decimal256Builder::new()
+                    .with_precision_and_scale(DECIMAL_PRECISION, DECIMAL_SCALE)
+                    .expect("Kusto decimal precision and scale are valid"),
+            ),
+        }
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            Self::String(b) | Self::Dynamic(b) => Arc::new(b.finish()),
+            Self::Bool(b) => Arc::new(b.finish()),
+            Self::Int(b) => Arc::new(b.finish()),
+            Self::Long(b) => Arc::new(b.finish()),
+            Self::Real(b) => Arc::new(b.finish()),
+            Self::Datetime(b) => Arc::new(b.finish()),
+            Self::Timespan(b) => Arc::new(b.finish()),
+            Self::Guid(b) => Arc::new(b.finish()),
+            Self::Decimal(b) => Arc::new(b.finish()),
+        }
+    }
+}
+
+/// Decodes rows of a V2 response straight into Arrow array builders, keyed by the [ColumnType] of each column,
+/// without building `serde_json::Value`s for them first.
+///
+/// Rows can be decoded over several calls, e.g. one per fragment of a progressive response,
+/// and are turned into a [RecordBatch] by [flush](Self::flush).
+pub struct RecordBatchDecoder {
+    schema: SchemaRef,
+    builders: Vec<ColumnBuilder>,
+    len: usize,
+}
+
+impl RecordBatchDecoder {
+    /// Creates a decoder for rows of the given columns.
+    #[must_use]
+    pub fn new(columns: &[Column]) -> Self {
+        Self {
+            schema: Arc::new(Schema::new(
+                columns.iter().map(column_field).collect::<Vec<_>>(),
+            )),
+            builders: columns
+                .iter()
+                .map(|c| ColumnBuilder::new(&c.column_type))
+                .collect(),
+            len: 0,
+        }
+    }
+
+    /// The schema of the batches created by the decoder.
+    #[must_use]
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    /// The number of rows decoded since the last flush.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no rows were decoded since the last flush.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Decodes a JSON array of rows, e.g. the `Rows` of a `DataTable` or `TableFragment` frame, returning the number of rows decoded.
+    ///
+    /// If the rows fail to decode, all rows decoded since the last flush are discarded.
+    pub fn decode_json(&mut self, rows: &[u8]) -> Result<usize> {
+        let mut deserializer = serde_json::Deserializer::from_slice(rows);
+        let count = RowsSeed(self).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(count)
+    }
+
+    /// Decodes rows that were already parsed, returning the number of rows decoded.
+    ///
+    /// If the rows fail to decode, all rows decoded since the last flush are discarded.
+    pub fn decode_values(&mut self, rows: Vec<Value>) -> Result<usize> {
+        Ok(RowsSeed(self).deserialize(Value::Array(rows))?)
+    }
+
+    /// Creates a [RecordBatch] of the rows decoded since the last flush.
+    pub fn flush(&mut self) -> Result<RecordBatch> {
+        let columns = self
+            .builders
+            .iter_mut()
+            .map(ColumnBuilder::finish)
+            .collect();
+        let options = RecordBatchOptions::new().with_row_count(Some(self.len));
+        self.len = 0;
+        Ok(
+            RecordBatch::try_new_with_options(self.schema.clone(), columns, &options)
+                .context(ErrorKind::DataConversion, "Failed to create record batch")?,
+        )
+    }
+
+    /// Discards the rows decoded since the last flush.
+    fn reset(&mut self) {
+        self.builders.iter_mut().for_each(|b| {
+            b.finish();
+        });
+        self.len = 0;
+    }
+}
+
+/// Visits a string value, or a number for columns whose values are usually encoded as strings.
+struct StrVisitor<F>(F);
+
+impl<'de, F: FnOnce(Option<&str>) -> std::result::Result<(), String>> Visitor<'de>
+    for StrVisitor<F>
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string or null")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<(), E> {
+        (self.0)(None).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<(), E> {
+        (self.0)(Some(v)).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<(), E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<(), E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<(), E> {
+        self.visit_str(&v.to_string())
+    }
+}
+
+/// Visits a real value, which the service encodes as a string for non-finite numbers.
+struct RealVisitor;
+
+impl<'de> Visitor<'de> for RealVisitor {
+    type Value = Option<f64>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a number or null")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> std::result::Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> std::result::Result<Self::Value, E> {
+        Ok(Some(v))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_i64<E: de::Error>(self, v: i64) -> std::result::Result<Self::Value, E> {
+        Ok(Some(v as f64))
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_u64<E: de::Error>(self, v: u64) -> std::result::Result<Self::Value, E> {
+        Ok(Some(v as f64))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> std::result::Result<Self::Value, E> {
+        match v {
+            "NaN" => Ok(None),
+            "Infinity" => Ok(Some(f64::INFINITY)),
+            "-Infinity" => Ok(Some(f64::NEG_INFINITY)),
+            _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+        }
+    }
+}
+
+/// Appends a single value to the builder of its column.
+struct CellSeed<'a>(&'a mut ColumnBuilder);
+
+impl<'de, 'a> DeserializeSeed<'de> for CellSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        match self.0 {
+            ColumnBuilder::String(b) => {
+                deserializer.deserialize_any(StrVisitor(|s: Option<&str>| {
+                    b.append_option(s);
+                    Ok(())
+                }))
+            }
+            ColumnBuilder::Bool(b) => {
+                b.append_option(Option::<bool>::deserialize(deserializer)?);
+                Ok(())
+            }
+            ColumnBuilder::Int(b) => {
+                b.append_option(Option::<i32>::deserialize(deserializer)?);
+                Ok(())
+            }
+            ColumnBuilder::Long(b) => {
+                b.append_option(Option::<i64>::deserialize(deserializer)?);
+                Ok(())
+            }
+            ColumnBuilder::Real(b) => {
+                b.append_option(deserializer.deserialize_any(RealVisitor)?);
+                Ok(())
+            }
+            // As in the conversion of whole columns, values that are not valid datetimes or timespans are nulls.
+            ColumnBuilder::Datetime(b) => {
+                deserializer.deserialize_any(StrVisitor(|s: Option<&str>| {
+                    b.append_option(s.and_then(|s| {
+                        KustoDateTime::from_str(s)
+                            .ok()
+                            .and_then(|d| i64::try_from(d.unix_timestamp_nanos()).ok())
+                    }));
+                    Ok(())
+                }))
+            }
+            ColumnBuilder::Timespan(b) => {
+                deserializer.deserialize_any(StrVisitor(|s: Option<&str>| {
+                    b.append_option(s.and_then(|s| {
+                        KustoDuration::from_str(s)
+                            .ok()
+                            .and_then(|d| i64::try_from(d.whole_nanoseconds()).ok())
+                    }));
+                    Ok(())
+                }))
+            }
+            ColumnBuilder::Dynamic(b) => {
+                b.append_option(Option::<Value>::deserialize(deserializer)?.map(|v| v.to_string()));
+                Ok(())
+            }
+            ColumnBuilder::Guid(b) => {
+                deserializer.deserialize_any(StrVisitor(|s: Option<&str>| match s {
+                    None => {
+                        b.append_null();
+                        Ok(())
+                    }
+                    Some(s) => {
+                        let guid = uuid::Uuid::parse_str(s)
+                            .map_err(|e| format!("invalid guid {s}: {e}"))?;
+                        b.append_value(guid.as_bytes()).map_err(|e| e.to_string())
+                    }
+                }))
+            }
+            ColumnBuilder::Decimal(b) => {
+                deserializer.deserialize_any(StrVisitor(|s: Option<&str>| {
+                    let decimal = s
+                        .map(|s| {
+                            parse_decimal(s)
+                                .filter(|d| {
+                                    Decimal256Type::validate_decimal_precision(
+                                        *d,
+                                        DECIMAL_PRECISION,
+                                    )
+                                    .is_ok()
+                                })
+                                .ok_or_else(|| format!("invalid decimal {s}"))
+                        })
+                        .transpose()?;
+                    b.append_option(decimal);
+                    Ok(())
+                }))
+            }
+        }
+    }
+}
+
+/// Appends a row, an array with a value for each column, to the builders.
+struct RowSeed<'a>(&'a mut [ColumnBuilder]);
+
+impl<'de, 'a> DeserializeSeed<'de> for RowSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for RowSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a row of {} values", self.0.len())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        let expected = self.0.len();
+        for (i, builder) in self.0.iter_mut().enumerate() {
+            if seq.next_element_seed(CellSeed(builder))?.is_none() {
+                return Err(de::Error::custom(format!(
+                    "row has {i} values instead of {expected}"
+                )));
+            }
+        }
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::custom(format!(
+                "row has more than {expected} values"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Appends an array of rows to a decoder, discarding its rows on failure.
+struct RowsSeed<'a>(&'a mut RecordBatchDecoder);
+
+impl<'de, 'a> DeserializeSeed<'de> for RowsSeed<'a> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<usize, D::Error> {
+        let decoder = self.0;
+        match deserializer.deserialize_seq(RowsVisitor(&mut decoder.builders)) {
+            Ok(count) => {
+                decoder.len += count;
+                Ok(count)
+            }
+            Err(e) => {
+                decoder.reset();
+                Err(e)
+            }
+        }
+    }
+}
+
+struct RowsVisitor<'a>(&'a mut [ColumnBuilder]);
+
+impl<'de, 'a> Visitor<'de> for RowsVisitor<'a> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of rows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<usize, A::Error> {
+        let mut count = 0;
+        while seq.next_element_seed(RowSeed(&mut *self.0))?.is_some() {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Decodes the primary results of a V2 response body into [RecordBatch]es, one per table,
+/// without parsing the frames into [V2QueryResult](crate::models::V2QueryResult)s first.
+///
+/// Both progressive and non progressive responses are supported, with the rows of other tables skipped over.
+/// Rows are decoded as they are read when the columns of their table precede them, as they do in responses of the service.
+/// This is the decoder used by [V2QueryRunner::into_record_batches](crate::operations::query::V2QueryRunner::into_record_batches).
+pub fn decode_primary_results(body: &[u8]) -> Result<Vec<RecordBatch>> {
+    let mut decoder = FramesDecoder::new(BatchMode::Tables);
+    decoder.decode_frames(body)?;
+    decoder.finish()?;
+    Ok(decoder.take_batches())
+}
+
+/// When the rows of the primary results are turned into batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BatchMode {
+    /// A batch per primary result table, once the table is complete.
+    Tables,
+    /// Batches of the first primary result table only, one per fragment whose rows are final,
+    /// i.e. will not be replaced by a later fragment.
+    Fragments,
+}
+
+/// A progressive primary result table that is not completed yet.
+struct ProgressiveTable {
+    decoder: RecordBatchDecoder,
+    /// The number of rows of the table, including those already flushed.
+    row_count: usize,
+    /// Whether rows of the table were already flushed, after which they can no longer be replaced.
+    streamed: bool,
+}
+
+/// Decodes the frames of a V2 response, collecting the batches of its primary results.
+pub(crate) struct FramesDecoder {
+    mode: BatchMode,
+    /// The progressive primary result tables that are not completed yet.
+    tables: HashMap<i32, ProgressiveTable>,
+    /// The schema of the first primary result table, once its columns are known.
+    schema: Option<SchemaRef>,
+    batches: Vec<RecordBatch>,
+    /// The errors reported by the `DataSetCompletion` frame, if it reported any.
+    errors: Option<Vec<OneApiError>>,
+    /// An error that is not a deserialization error, reported in place of the error it aborted deserialization with.
+    error: Option<Error>,
+}
+
+impl FramesDecoder {
+    pub(crate) fn new(mode: BatchMode) -> Self {
+        Self {
+            mode,
+            tables: HashMap::new(),
+            schema: None,
+            batches: Vec::new(),
+            errors: None,
+            error: None,
+        }
+    }
+
+    /// Decodes a JSON array of frames, e.g. a whole response body.
+    pub(crate) fn decode_frames(&mut self, frames: &[u8]) -> Result<()> {
+        self.decode(frames, |state, deserializer| {
+            FramesSeed(state).deserialize(deserializer)
+        })
+    }
+
+    /// Decodes a single frame, e.g. a line of a response read as it arrives.
+    pub(crate) fn decode_frame(&mut self, frame: &[u8]) -> Result<()> {
+        self.decode(frame, |state, deserializer| {
+            FrameSeed(state).deserialize(deserializer)
+        })
+    }
+
+    fn decode(
+        &mut self,
+        json: &[u8],
+        seed: impl FnOnce(
+            &mut Self,
+            &mut serde_json::Deserializer<serde_json::de::SliceRead>,
+        ) -> serde_json::Result<()>,
+    ) -> Result<()> {
+        let mut deserializer = serde_json::Deserializer::from_slice(json);
+        let result = seed(self, &mut deserializer).and_then(|()| deserializer.end());
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        Ok(result?)
+    }
+
+    /// The schema of the first primary result table, once its header was decoded.
+    pub(crate) fn schema(&self) -> Option<SchemaRef> {
+        self.schema.clone()
+    }
+
+    /// Takes the batches decoded so far.
+    pub(crate) fn take_batches(&mut self) -> Vec<RecordBatch> {
+        std::mem::take(&mut self.batches)
+    }
+
+    /// The errors reported by the `DataSetCompletion` frame, if it reported any.
+    pub(crate) fn take_errors(&mut self) -> Option<Vec<OneApiError>> {
+        self.errors.take()
+    }
+
+    /// Checks that all the primary result tables were completed, once all frames have been decoded.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        if self.tables.is_empty() {
+            return Ok(());
+        }
+        let mut table_ids: Vec<i32> = self.tables.drain().map(|(id, _)| id).collect();
+        table_ids.sort_unstable();
+        Err(ProtocolError::IncompleteTables { table_ids }.into())
+    }
+
+    /// Whether a table of the given kind is decoded, noting the schema of the first primary result.
+    fn accept(&mut self, table_kind: Option<&TableKind>, columns: &[Column]) -> bool {
+        if table_kind != Some(&TableKind::PrimaryResult)
+            || (self.mode == BatchMode::Fragments && self.schema.is_some())
+        {
+            return false;
+        }
+        if self.schema.is_none() {
+            self.schema = Some(RecordBatchDecoder::new(columns).schema());
+        }
+        true
+    }
+
+    fn push(&mut self, batch: RecordBatch) {
+        if self.mode == BatchMode::Tables || batch.num_rows() > 0 {
+            self.batches.push(batch);
+        }
+    }
+
+    fn fail<E: de::Error>(&mut self, error: Error) -> E {
+        let message = error.to_string();
+        self.error = Some(error);
+        E::custom(message)
+    }
+}
+
+struct FramesSeed<'a>(&'a mut FramesDecoder);
+
+impl<'de, 'a> DeserializeSeed<'de> for FramesSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for FramesSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of frames")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<(), A::Error> {
+        while seq.next_element_seed(FrameSeed(&mut *self.0))?.is_some() {}
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier)]
+enum FrameField {
+    FrameType,
+    TableId,
+    TableKind,
+    TableFragmentType,
+    Columns,
+    Rows,
+    RowCount,
+    HasErrors,
+    OneApiErrors,
+    #[serde(other)]
+    Other,
+}
+
+struct FrameSeed<'a>(&'a mut FramesDecoder);
+
+impl<'de, 'a> DeserializeSeed<'de> for FrameSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for FrameSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a frame")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<(), A::Error> {
+        let state = self.0;
+        let mut frame_type: Option<String> = None;
+        let mut table_id: Option<i32> = None;
+        let mut table_kind: Option<TableKind> = None;
+        let mut fragment_type: Option<TableFragmentType> = None;
+        let mut columns: Option<Vec<Column>> = None;
+        let mut row_count: Option<i32> = None;
+        let mut has_errors = false;
+        let mut errors: Option<Vec<OneApiError>> = None;
+        // The decoder of a `DataTable` frame, if its rows were decoded as they were read.
+        let mut decoded: Option<RecordBatchDecoder> = None;
+        // The number of rows of a `TableFragment` frame, if they were decoded as they were read.
+        let mut fragment_rows: Option<usize> = None;
+        // Rows read before the fields needed to decode them.
+        let mut deferred: Option<Vec<Value>> = None;
+
+        while let Some(field) = map.next_key::<FrameField>()? {
+            match field {
+                FrameField::FrameType => frame_type = Some(map.next_value()?),
+                FrameField::TableId => table_id = Some(map.next_value()?),
+                FrameField::TableKind => table_kind = Some(map.next_value()?),
+                FrameField::TableFragmentType => fragment_type = Some(map.next_value()?),
+                FrameField::Columns => columns = Some(map.next_value()?),
+                FrameField::RowCount => row_count = Some(map.next_value()?),
+                FrameField::HasErrors => has_errors = map.next_value()?,
+                FrameField::OneApiErrors => errors = map.next_value()?,
+                FrameField::Rows => match (frame_type.as_deref(), &table_kind, &columns) {
+                    (Some("DataTable"), Some(_), Some(columns)) => {
+                        if state.accept(table_kind.as_ref(), columns) {
+                            let mut decoder = RecordBatchDecoder::new(columns);
+                            map.next_value_seed(RowsSeed(&mut decoder))?;
+                            decoded = Some(decoder);
+                        } else {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                    (Some("TableFragment"), _, _)
+                        if table_id.is_some() && fragment_type.is_some() =>
+                    {
+                        match state.tables.get_mut(&table_id.unwrap_or_default()) {
+                            Some(table) => {
+                                if fragment_type == Some(TableFragmentType::DataReplace) {
+                                    if table.streamed {
+                                        let error = ProtocolError::ReplaceAfterStreamedRows {
+                                            table_id: table_id.unwrap_or_default(),
+                                        };
+                                        return Err(state.fail(error.into()));
+                                    }
+                                    table.decoder.reset();
+                                }
+                                fragment_rows =
+                                    Some(map.next_value_seed(RowsSeed(&mut table.decoder))?);
+                            }
+                            None => {
+                                map.next_value::<IgnoredAny>()?;
+                            }
+                        }
+                    }
+                    _ => deferred = Some(map.next_value()?),
+                },
+                FrameField::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let result = match frame_type.as_deref() {
+            Some("DataTable") => {
+                let decoder = match (decoded, &columns) {
+                    (Some(decoder), _) => Some(decoder),
+                    (None, Some(columns)) => state
+                        .accept(table_kind.as_ref(), columns)
+                        .then(|| RecordBatchDecoder::new(columns)),
+                    (None, None) if table_kind == Some(TableKind::PrimaryResult) => {
+                        return Err(de::Error::missing_field("Columns"))
+                    }
+                    (None, None) => None,
+                };
+                match decoder {
+                    Some(mut decoder) => deferred
+                        .map_or(Ok(0), |rows| decoder.decode_values(rows))
+                        .and_then(|_| decoder.flush())
+                        .map(|batch| state.push(batch)),
+                    None => Ok(()),
+                }
+            }
+            Some("TableHeader") if table_kind == Some(TableKind::PrimaryResult) => {
+                let table_id = table_id.ok_or_else(|| de::Error::missing_field("TableId"))?;
+                let columns = columns.ok_or_else(|| de::Error::missing_field("Columns"))?;
+                if state.tables.contains_key(&table_id) {
+                    Err(ProtocolError::DuplicateTableHeader { table_id }.into())
+                } else {
+                    if state.accept(table_kind.as_ref(), &columns) {
+                        let table = ProgressiveTable {
+                            decoder: RecordBatchDecoder::new(&columns),
+                            row_count: 0,
+                            streamed: false,
+                        };
+                        state.tables.insert(table_id, table);
+                    }
+                    Ok(())
+                }
+            }
+            Some("TableFragment") => {
+                fragment(state, table_id, fragment_type, fragment_rows, deferred)
+            }
+            Some("TableCompletion") => match table_id.and_then(|id| state.tables.remove(&id)) {
+                Some(mut table) => match row_count {
+                    Some(expected) if usize::try_from(expected) != Ok(table.row_count) => {
+                        Err(ProtocolError::RowCountMismatch {
+                            table_id: table_id.unwrap_or_default(),
+                            expected,
+                            actual: table.row_count,
+                        }
+                        .into())
+                    }
+                    _ => table.decoder.flush().map(|batch| state.push(batch)),
+                },
+                None => Ok(()),
+            },
+            Some("DataSetCompletion") if has_errors => {
+                state.errors = Some(errors.unwrap_or_default());
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+
+        result.map_err(|e| state.fail(e))
+    }
+}
+
+/// Applies a `TableFragment` frame to its table, once all of its fields have been read.
+fn fragment(
+    state: &mut FramesDecoder,
+    table_id: Option<i32>,
+    fragment_type: Option<TableFragmentType>,
+    decoded_rows: Option<usize>,
+    deferred: Option<Vec<Value>>,
+) -> Result<()> {
+    let Some(table) = table_id.and_then(|id| state.tables.get_mut(&id)) else {
+        return Ok(());
+    };
+    let replace = fragment_type == Some(TableFragmentType::DataReplace);
+    let rows = match (decoded_rows, deferred) {
+        (Some(rows), _) => rows,
+        (None, Some(rows)) => {
+            if replace {
+                if table.streamed {
+                    return Err(ProtocolError::ReplaceAfterStreamedRows {
+                        table_id: table_id.unwrap_or_default(),
+                    }
+                    .into());
+                }
+                table.decoder.reset();
+            }
+            table.decoder.decode_values(rows)?
+        }
+        (None, None) => return Ok(()),
+    };
+
+    if replace {
+        table.row_count = rows;
+    } else {
+        table.row_count += rows;
+        if state.mode == BatchMode::Fragments && !table.decoder.is_empty() {
+            table.streamed = true;
+            let batch = table.decoder.flush()?;
+            state.push(batch);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::operations::query::KustoResponseDataSetV2;
+    use arrow_array::Decimal256Array;
+    use std::path::PathBuf;
+
+    #[test]
+    fn decodes_like_record_batches() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/inputs/dataframe.json");
+        let body = std::fs::read(path).expect("Failed to read file");
+
+        let results = serde_json::from_slice(&body).expect("Failed to parse frames");
+        let expected = KustoResponseDataSetV2 {
+            results,
+            ..Default::default()
+        }
+        .record_batches()
+        .collect::<Result<Vec<_>>>()
+        .expect("Failed to convert tables");
+
+        let batches = decode_primary_results(&body).expect("Failed to decode");
+        assert_eq!(batches, expected);
+    }
+
+    #[test]
+    fn decodes_progressive_fragments() {
+        let body = br#"[
+            {"FrameType": "DataSetHeader", "IsProgressive": true, "Version": "v2.0"},
+            {"FrameType": "TableHeader", "TableId": 0, "TableName": "props", "TableKind": "QueryProperties", "Columns": []},
+            {"FrameType": "TableFragment", "TableId": 0, "TableFragmentType": "DataAppend", "Rows": [[]]},
+            {"FrameType": "TableCompletion", "TableId": 0, "RowCount": 1},
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}, {"ColumnName": "s", "ColumnType": "string"}]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[1, "a"]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[2, "b"], [3, null]]},
+            {"Rows": [[4, "d"]], "TableFragmentType": "DataAppend", "TableId": 1, "FrameType": "TableFragment"},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 3},
+            {"FrameType": "DataSetCompletion", "HasErrors": false, "Cancelled": false}
+        ]"#;
+
+        let batches = decode_primary_results(body).expect("Failed to decode");
+        assert_eq!(batches.len(), 1);
+        let expected = super::super::convert_rows(
+            &serde_json::from_str::<Vec<Column>>(
+                r#"[{"ColumnName": "x", "ColumnType": "long"}, {"ColumnName": "s", "ColumnType": "string"}]"#,
+            )
+            .unwrap(),
+            serde_json::from_str(r#"[[2, "b"], [3, null], [4, "d"]]"#).unwrap(),
+        )
+        .expect("Failed to convert rows");
+        assert_eq!(batches[0], expected);
+    }
+
+    #[test]
+    fn decodes_largest_decimals() {
+        let columns: Vec<Column> =
+            serde_json::from_str(r#"[{"ColumnName": "m", "ColumnType": "decimal"}]"#).unwrap();
+        let mut decoder = RecordBatchDecoder::new(&columns);
+
+        decoder
+            .decode_json(
+                br#"[["79228162514264337593543950335"], ["-0.0000000000000000000000000001"]]"#,
+            )
+            .expect("Failed to decode");
+        let batch = decoder.flush().expect("Failed to flush");
+        let decimals = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Decimal256Array>()
+            .unwrap();
+        assert_eq!(
+            decimals.value_as_string(0),
+            "79228162514264337593543950335.0000000000000000000000000000"
+        );
+        assert_eq!(
+            decimals.value_as_string(1),
+            "-0.0000000000000000000000000001"
+        );
+    }
+
+    #[test]
+    fn decoder_discards_rows_on_failure() {
+        let columns: Vec<Column> =
+            serde_json::from_str(r#"[{"ColumnName": "x", "ColumnType": "int"}]"#).unwrap();
+        let mut decoder = RecordBatchDecoder::new(&columns);
+
+        assert_eq!(
+            decoder
+                .decode_json(b"[[1], [2]]")
+                .expect("Failed to decode"),
+            2
+        );
+        assert!(decoder.decode_json(b"[[3], [4, 5]]").is_err());
+        assert!(decoder.is_empty());
+
+        assert_eq!(decoder.decode_json(b"[[6]]").expect("Failed to decode"), 1);
+        let batch = decoder.flush().expect("Failed to flush");
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.schema(), decoder.schema());
+    }
+
+    #[test]
+    fn row_count_mismatch_fails() {
+        let body = br#"[
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[1]]},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 2}
+        ]"#;
+
+        assert!(matches!(
+            decode_primary_results(body),
+            Err(Error::ProtocolError(
+                ProtocolError::RowCountMismatch {
+                    table_id: 1,
+                    expected: 2,
+                    actual: 1
+                },
+                _
+            ))
+        ));
+    }
+}
@@ -77,7 +77,7 @@ impl Policy for AuthorizationPolicy {
 
         let token = cred.get_token(&[&scope]).await?;
 
-        request.insert_header(AUTHORIZATION, &format!("Bearer {}", token.token.secret()));
+        request.insert_header(AUTHORIZATION, format!("Bearer {}", token.token.secret()));
 
         next[0].send(ctx, request, &next[1..]).await
     }
@@ -3,7 +3,10 @@
 use crate::authorization_policy::AuthorizationPolicy;
 use crate::connection_string::{ConnectionString, ConnectionStringAuth};
 use crate::error::{Error, Result};
+use crate::management::{DatabaseSchema, Management};
 use crate::operations::query::{QueryRunner, QueryRunnerBuilder, V1QueryRunner, V2QueryRunner};
+use crate::row_deserializer::RowDeserializerOptions;
+use crate::service_error_policy::ServiceErrorPolicy;
 
 use azure_core::{ClientOptions, Pipeline};
 
@@ -16,15 +19,23 @@ use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::sync::Arc;
 
+/// Prefix of the client request ids generated by the client, unless overridden in [KustoClientOptions].
+pub const DEFAULT_CLIENT_REQUEST_ID_PREFIX: &str = "KRS.execute";
+
 /// Options for specifying how a Kusto client will behave
 #[derive(Clone, Default)]
 pub struct KustoClientOptions {
     options: ClientOptions,
+    client_request_id_prefix: Option<String>,
+    row_deserializer_options: RowDeserializerOptions,
 }
 
 impl From<ClientOptions> for KustoClientOptions {
     fn from(c: ClientOptions) -> Self {
-        Self { options: c }
+        Self {
+            options: c,
+            ..Default::default()
+        }
     }
 }
 
@@ -34,21 +45,38 @@ impl KustoClientOptions {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Sets the prefix of the client request ids generated for requests that do not set one,
+    /// which are of the form `<prefix>;<uuid>`. Defaults to [DEFAULT_CLIENT_REQUEST_ID_PREFIX].
+    #[must_use]
+    pub fn with_client_request_id_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.client_request_id_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Sets how struct fields are matched to columns when deserializing rows,
+    /// e.g. in [execute_query_to_struct](KustoClient::execute_query_to_struct).
+    #[must_use]
+    pub fn with_row_deserializer_options(mut self, options: RowDeserializerOptions) -> Self {
+        self.row_deserializer_options = options;
+        self
+    }
 }
 
 fn new_pipeline_from_options(
     auth: ConnectionStringAuth,
     resource: String,
-    options: KustoClientOptions,
+    options: ClientOptions,
 ) -> Pipeline {
     let auth_policy = Arc::new(AuthorizationPolicy::new(auth, resource));
     // take care of adding the AuthorizationPolicy as **last** retry policy.
-    let per_retry_policies: Vec<Arc<(dyn azure_core::Policy + 'static)>> = vec![auth_policy];
+    let per_retry_policies: Vec<Arc<dyn azure_core::Policy + 'static>> =
+        vec![Arc::new(ServiceErrorPolicy), auth_policy];
 
     Pipeline::new(
         option_env!("CARGO_PKG_NAME"),
         option_env!("CARGO_PKG_VERSION"),
-        options.options,
+        options,
         Vec::new(),
         per_retry_policies,
     )
@@ -66,6 +94,8 @@ pub struct KustoClient {
     query_url: Arc<String>,
     management_url: Arc<String>,
     default_headers: Arc<Headers>,
+    client_request_id_prefix: Arc<String>,
+    row_deserializer_options: RowDeserializerOptions,
 }
 
 /// Denotes what kind of query is being executed.
@@ -96,13 +126,19 @@ impl KustoClient {
         let service_url = Arc::new(data_source.trim_end_matches('/').to_string());
         let query_url = format!("{service_url}/v2/rest/query");
         let management_url = format!("{service_url}/v1/rest/mgmt");
-        let pipeline = new_pipeline_from_options(credentials, (*service_url).clone(), options);
+        let client_request_id_prefix = options
+            .client_request_id_prefix
+            .unwrap_or_else(|| DEFAULT_CLIENT_REQUEST_ID_PREFIX.to_string());
+        let pipeline =
+            new_pipeline_from_options(credentials, (*service_url).clone(), options.options);
 
         Ok(Self {
             pipeline: pipeline.into(),
             query_url: query_url.into(),
             management_url: management_url.into(),
             default_headers,
+            client_request_id_prefix: client_request_id_prefix.into(),
+            row_deserializer_options: options.row_deserializer_options,
         })
     }
 
@@ -133,6 +169,15 @@ impl KustoClient {
         &self.pipeline
     }
 
+    /// Generates a new client request id, of the form `<prefix>;<uuid>`.
+    pub(crate) fn new_client_request_id(&self) -> String {
+        format!("{};{}", self.client_request_id_prefix, uuid::Uuid::new_v4())
+    }
+
+    pub(crate) fn row_deserializer_options(&self) -> RowDeserializerOptions {
+        self.row_deserializer_options
+    }
+
     /// Execute a query against the Kusto cluster.
     /// The `kind` parameter determines whether the request is a query (retrieves data from the tables) or a management query (commands to monitor and manage the cluster).
     /// This method should only be used if the query kind is not known at compile time, otherwise use [execute](#method.execute) or [execute_command](#method.execute_command).
@@ -188,7 +233,7 @@ impl KustoClient {
     ///     .await?;
     ///
     ///   for table in result.into_primary_results() {
-    ///        println!("{}", table.table_name);
+    ///        println!("{}", table?.table_name);
     ///    }
     /// # Ok(())}
     /// ```
@@ -209,6 +254,9 @@ impl KustoClient {
     /// This method is the simplest way to just convert your data into a struct.
     /// It assumes there is one primary result table.
     ///
+    /// The fields of the struct are matched to the columns of the table by name, as configured by
+    /// [KustoClientOptions::with_row_deserializer_options]. Columns without a matching field are ignored by default.
+    ///
     /// Your struct should implement the [serde::DeserializeOwned](https://docs.serde.rs/serde/trait.DeserializeOwned.html) trait.
     ///
     /// # Example
@@ -245,11 +293,9 @@ impl KustoClient {
         let results = response
             .into_primary_results()
             .next()
-            .ok_or_else(|| Error::QueryError("No primary results found".into()))?;
+            .ok_or_else(|| Error::QueryError("No primary results found".into()))??;
 
-        Ok(serde_json::from_value::<Vec<T>>(serde_json::Value::Array(
-            results.rows,
-        ))?)
+        results.into_deserialized_rows(self.row_deserializer_options)
     }
 
     /// Execute a management command with additional options.
@@ -281,6 +327,19 @@ impl KustoClient {
     ) -> V1QueryRunner {
         V1QueryRunner(self.execute_with_options(database, query, QueryKind::Management, options))
     }
+
+    /// Returns a handle to run typed management commands, such as `.show tables`, against a database.
+    /// See [Management].
+    #[must_use]
+    pub fn management(&self, database: impl Into<String>) -> Management {
+        Management::new(self.clone(), database.into())
+    }
+
+    /// Gets the full schema of a database: its tables and columns, functions, materialized views and external tables.
+    /// See [Management::show_database_schema].
+    pub async fn database_schema(&self, database: impl Into<String>) -> Result<DatabaseSchema> {
+        self.management(database).show_database_schema().await
+    }
 }
 
 impl TryFrom<ConnectionString> for KustoClient {
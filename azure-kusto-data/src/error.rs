@@ -1,4 +1,7 @@
 //! Defines [Error] for representing failures in various operations.
+use crate::models::{ColumnType, OneApiError, OneApiErrorMessage};
+use crate::operations::query::RequestIds;
+use azure_core::headers::{Headers, ACTIVITY_ID};
 use azure_core::StatusCode;
 use std::fmt::Debug;
 use std::num::TryFromIntError;
@@ -51,6 +54,175 @@ pub enum Error {
     /// Errors raised for IO operations
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
+
+    /// Error returned by the Kusto service for a failed query or command
+    #[error("Kusto service error: {0}")]
+    ServiceError(Box<KustoServiceError>),
+
+    /// Error raised when a request does not complete within its client side timeout, along with the ids of the request
+    #[error("Request timed out after {0:?}")]
+    TimeoutError(std::time::Duration, RequestIds),
+
+    /// Error raised when the frames of a V2 response violate the protocol, along with the ids of the request
+    #[error("Protocol error: {0}")]
+    ProtocolError(#[source] ProtocolError, RequestIds),
+
+    /// Error raised when a query completed, but the service reported errors, so the results may be partial.
+    /// Carries the errors and the ids of the request.
+    #[error("Query completed with {} error(s), results may be partial", .0.len())]
+    PartialQueryFailure(Vec<OneApiError>, RequestIds),
+}
+
+impl Error {
+    /// The identifiers of the failed request, if the error came from a request and they are known.
+    #[must_use]
+    pub fn request_ids(&self) -> Option<RequestIds> {
+        match self {
+            Self::ServiceError(error) => Some(RequestIds {
+                client_request_id: error.client_request_id.clone(),
+                activity_id: error.activity_id.clone(),
+            }),
+            Self::TimeoutError(_, request_ids)
+            | Self::ProtocolError(_, request_ids)
+            | Self::PartialQueryFailure(_, request_ids) => {
+                Some(request_ids.clone()).filter(|ids| *ids != RequestIds::default())
+            }
+            _ => None,
+        }
+    }
+
+    /// Attaches the ids of the request the error was raised for, unless the error already carries ids.
+    pub(crate) fn with_request_ids(mut self, ids: &RequestIds) -> Self {
+        if let Self::TimeoutError(_, request_ids)
+        | Self::ProtocolError(_, request_ids)
+        | Self::PartialQueryFailure(_, request_ids) = &mut self
+        {
+            if *request_ids == RequestIds::default() {
+                *request_ids = ids.clone();
+            }
+        }
+        self
+    }
+}
+
+impl From<KustoServiceError> for Error {
+    fn from(error: KustoServiceError) -> Self {
+        Self::ServiceError(Box::new(error))
+    }
+}
+
+impl From<ProtocolError> for Error {
+    fn from(error: ProtocolError) -> Self {
+        Self::ProtocolError(error, RequestIds::default())
+    }
+}
+
+/// Broad categories of [KustoServiceError]s, to allow branching on the cause of a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceErrorKind {
+    /// The query or command text could not be parsed.
+    SyntaxError,
+    /// The request was rejected because the service is throttling requests.
+    Throttled,
+    /// The caller is not permitted to perform the request.
+    PermissionDenied,
+    /// The caller could not be authenticated.
+    Unauthorized,
+    /// The request was invalid for a reason other than its syntax.
+    BadRequest,
+    /// Any other failure.
+    Other,
+}
+
+/// An error returned by the Kusto service, parsed from the `OneApiError` body of a failed response.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("{status} {code}: {message}")]
+pub struct KustoServiceError {
+    /// HTTP status code of the failed response.
+    pub status: StatusCode,
+    /// Error code, for example `General_BadRequest` or `LimitsExceeded`.
+    pub code: String,
+    /// Short, human readable error message.
+    pub message: String,
+    /// The full name of the exception type raised by the service, for example `Kusto.Data.Exceptions.SyntaxException`.
+    pub error_type: Option<String>,
+    /// Detailed description of the error.
+    pub description: Option<String>,
+    /// Whether the error is permanent, i.e. retrying the request will fail again.
+    pub is_permanent: Option<bool>,
+    /// The id the service assigned to the failed request.
+    pub activity_id: Option<String>,
+    /// The client request id of the failed request.
+    pub client_request_id: Option<String>,
+}
+
+impl KustoServiceError {
+    /// Parses a failed response. If the body is not a `OneApiError`, the raw body is used as the message.
+    pub(crate) fn from_response(
+        status: StatusCode,
+        headers: &Headers,
+        body: &[u8],
+        client_request_id: Option<String>,
+    ) -> Self {
+        let header_activity_id = headers.get_optional_string(&ACTIVITY_ID);
+
+        match serde_json::from_slice::<OneApiError>(body) {
+            Ok(OneApiError { error }) => {
+                let mut service_error = Self::from_message(status, error);
+                service_error.activity_id = service_error.activity_id.or(header_activity_id);
+                service_error.client_request_id =
+                    service_error.client_request_id.or(client_request_id);
+                service_error
+            }
+            Err(_) => Self {
+                status,
+                code: status.canonical_reason().to_string(),
+                message: String::from_utf8_lossy(body).into_owned(),
+                error_type: None,
+                description: None,
+                is_permanent: None,
+                activity_id: header_activity_id,
+                client_request_id,
+            },
+        }
+    }
+
+    /// Creates an error from a [OneApiErrorMessage] received along with the given status.
+    pub(crate) fn from_message(status: StatusCode, error: OneApiErrorMessage) -> Self {
+        let context = error.context.unwrap_or_default();
+        Self {
+            status,
+            code: error.code,
+            message: error.message,
+            error_type: error.error_type,
+            description: error.description,
+            is_permanent: error.is_permanent,
+            activity_id: context.activity_id,
+            client_request_id: context.client_request_id,
+        }
+    }
+
+    /// Categorizes the error by its status, code and type.
+    #[must_use]
+    pub fn kind(&self) -> ServiceErrorKind {
+        let error_type = self.error_type.as_deref().unwrap_or_default();
+        if self.status == StatusCode::TooManyRequests
+            || self.code.contains("Throttl")
+            || error_type.contains("Throttl")
+        {
+            ServiceErrorKind::Throttled
+        } else if self.code == "BadRequest_SyntaxError" || error_type.ends_with("SyntaxException") {
+            ServiceErrorKind::SyntaxError
+        } else if self.status == StatusCode::Forbidden {
+            ServiceErrorKind::PermissionDenied
+        } else if self.status == StatusCode::Unauthorized {
+            ServiceErrorKind::Unauthorized
+        } else if self.status == StatusCode::BadRequest {
+            ServiceErrorKind::BadRequest
+        } else {
+            ServiceErrorKind::Other
+        }
+    }
 }
 
 /// Errors raised when an invalid argument or option is provided.
@@ -62,6 +234,72 @@ pub enum InvalidArgumentError {
     /// Error raised when failing to convert a number to u32.
     #[error("{0} is too large to fit in a u32")]
     PayloadTooLarge(#[from] TryFromIntError),
+    /// Error raised when a table has no column with the given name.
+    #[error("No column named {0}")]
+    UnknownColumn(String),
+    /// Error raised when a row index is past the end of a table.
+    #[error("Row {0} is out of range")]
+    RowOutOfRange(usize),
+    /// Error raised when a string is not a valid decimal number.
+    #[error("{0} is not a valid decimal")]
+    InvalidDecimal(String),
+    /// Error raised when a name is not a valid name for a table, column or other entity.
+    #[error("{0} is not a valid entity name")]
+    InvalidEntityName(String),
+    /// Error raised when a name is not a valid KQL identifier.
+    #[error("{0} is not a valid identifier")]
+    InvalidIdentifier(String),
+    /// Error raised when a value does not have the type it is declared with.
+    #[error("Value of {name} is of type {actual} instead of {expected}")]
+    TypeMismatch {
+        /// The name of the value, e.g. of a query parameter.
+        name: String,
+        /// The declared type.
+        expected: ColumnType,
+        /// The type of the value.
+        actual: ColumnType,
+    },
+}
+
+/// Errors raised when the frames of a V2 response cannot be reassembled into tables.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// Raised when a frame refers to a table whose `TableHeader` was not received, or which was already completed.
+    #[error("Received a {frame} frame for table {table_id}, which is not in progress")]
+    UnknownTable {
+        /// The id of the table.
+        table_id: i32,
+        /// The type of the frame.
+        frame: &'static str,
+    },
+    /// Raised when a `TableHeader` is received for a table that is already in progress.
+    #[error("Received a second TableHeader frame for table {table_id}")]
+    DuplicateTableHeader {
+        /// The id of the table.
+        table_id: i32,
+    },
+    /// Raised when the row count in the `TableCompletion` frame does not match the rows received.
+    #[error("Table {table_id} completed with a row count of {expected}, but {actual} rows were received")]
+    RowCountMismatch {
+        /// The id of the table.
+        table_id: i32,
+        /// The row count reported by the `TableCompletion` frame.
+        expected: i32,
+        /// The number of rows received.
+        actual: usize,
+    },
+    /// Raised when a `DataReplace` fragment is received for a table whose rows were already streamed.
+    #[error("Received a DataReplace fragment for table {table_id} after its rows were streamed")]
+    ReplaceAfterStreamedRows {
+        /// The id of the table.
+        table_id: i32,
+    },
+    /// Raised when the response ends before all tables were completed.
+    #[error("Response ended before tables {table_ids:?} were completed")]
+    IncompleteTables {
+        /// The ids of the tables that were not completed.
+        table_ids: Vec<i32>,
+    },
 }
 
 /// Errors raised when parsing connection strings.
@@ -101,3 +339,55 @@ impl ConnectionStringError {
 
 /// Result type for kusto operations.
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_one_api_error_response() {
+        let body = br#"{
+            "error": {
+                "code": "General_BadRequest",
+                "message": "Request is invalid and cannot be executed.",
+                "@type": "Kusto.Data.Exceptions.SyntaxException",
+                "@message": "Syntax error: Query could not be parsed",
+                "@context": {
+                    "timestamp": "2023-04-18T12:00:00.0000000Z",
+                    "serviceAlias": "MYCLUSTER",
+                    "clientRequestId": "KRS.execute;1234",
+                    "activityId": "5678"
+                },
+                "@permanent": true
+            }
+        }"#;
+
+        let error =
+            KustoServiceError::from_response(StatusCode::BadRequest, &Headers::new(), body, None);
+
+        assert_eq!(error.code, "General_BadRequest");
+        assert_eq!(error.is_permanent, Some(true));
+        assert_eq!(error.activity_id.as_deref(), Some("5678"));
+        assert_eq!(error.client_request_id.as_deref(), Some("KRS.execute;1234"));
+        assert_eq!(error.kind(), ServiceErrorKind::SyntaxError);
+    }
+
+    #[test]
+    fn parse_non_one_api_error_response() {
+        let mut headers = Headers::new();
+        headers.insert(ACTIVITY_ID, "5678");
+
+        let error = KustoServiceError::from_response(
+            StatusCode::TooManyRequests,
+            &headers,
+            b"slow down",
+            Some("KRS.execute;1234".to_string()),
+        );
+
+        assert_eq!(error.message, "slow down");
+        assert_eq!(error.is_permanent, None);
+        assert_eq!(error.activity_id.as_deref(), Some("5678"));
+        assert_eq!(error.client_request_id.as_deref(), Some("KRS.execute;1234"));
+        assert_eq!(error.kind(), ServiceErrorKind::Throttled);
+    }
+}
@@ -0,0 +1,242 @@
+//! Ingestion mappings, describing how the fields of ingested data map to the columns of a table.
+//!
+//! Mappings can be generated from the columns of a table, e.g. those of a [KustoRow](crate::kusto_row::KustoRow)
+//! or of an Arrow schema (see [schema_columns](crate::arrow::schema_columns)), and then created on the table with
+//! [Management::create_ingestion_mapping](crate::management::Management::create_ingestion_mapping)
+//! or passed inline when ingesting.
+
+use crate::models::{Column, ColumnType};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// The kind of an ingestion mapping, which depends on the format of the ingested data.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestionMappingKind {
+    /// Maps columns by ordinal, for delimited formats such as CSV, TSV or PSV.
+    Csv,
+    /// Maps columns by JSON path, for the JSON formats.
+    Json,
+    /// Maps columns by path, for Avro.
+    Avro,
+    /// Maps columns by path, for Avro as implemented by Apache.
+    ApacheAvro,
+    /// Maps columns by path, for Parquet.
+    Parquet,
+    /// Maps columns by path, for ORC.
+    Orc,
+    /// Maps columns by path, for SStream.
+    SStream,
+    /// Maps columns by field name, for W3C log files.
+    W3CLogFile,
+}
+
+impl IngestionMappingKind {
+    /// Whether columns are mapped by path (`$.field`) rather than by ordinal or name.
+    #[must_use]
+    pub fn uses_paths(self) -> bool {
+        !matches!(self, Self::Csv | Self::W3CLogFile)
+    }
+}
+
+impl Display for IngestionMappingKind {
+    /// Formats the kind as it is named in management commands, e.g. `.create table T ingestion json mapping`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Avro => "avro",
+            Self::ApacheAvro => "apacheavro",
+            Self::Parquet => "parquet",
+            Self::Orc => "orc",
+            Self::SStream => "sstream",
+            Self::W3CLogFile => "w3clogfile",
+        })
+    }
+}
+
+/// The mapping of a single column. See [the docs](https://learn.microsoft.com/en-us/azure/data-explorer/kusto/management/mappings).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// Name of the column the data is ingested into.
+    pub column: String,
+    /// Type of the column, used to create it if it does not exist.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_column_type"
+    )]
+    pub datatype: Option<ColumnType>,
+    /// Where the value is read from, e.g. `Path`, `Ordinal` or `ConstValue`, and how it is transformed (`Transform`).
+    #[serde(rename = "Properties", default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+fn serialize_column_type<S: Serializer>(
+    column_type: &Option<ColumnType>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match column_type {
+        Some(column_type) => serializer.collect_str(column_type),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl ColumnMapping {
+    /// Maps a column to the value at a path, e.g. `$.a.b`, for formats such as JSON or Parquet.
+    pub fn with_path(column: impl Into<String>, path: impl Into<String>) -> Self {
+        Self::with_property(column, "Path", path)
+    }
+
+    /// Maps a column to the field at an ordinal, starting at 0, for delimited formats such as CSV.
+    pub fn with_ordinal(column: impl Into<String>, ordinal: usize) -> Self {
+        Self::with_property(column, "Ordinal", ordinal.to_string())
+    }
+
+    /// Maps a column to the field with the given name, for W3C log files.
+    pub fn with_field(column: impl Into<String>, field: impl Into<String>) -> Self {
+        Self::with_property(column, "Field", field)
+    }
+
+    /// Maps a column to a constant value, the same for all the ingested rows.
+    pub fn with_const_value(column: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::with_property(column, "ConstValue", value)
+    }
+
+    fn with_property(column: impl Into<String>, name: &str, value: impl Into<String>) -> Self {
+        Self {
+            column: column.into(),
+            datatype: None,
+            properties: BTreeMap::from([(name.to_string(), value.into())]),
+        }
+    }
+
+    /// Sets the type of the column.
+    #[must_use]
+    pub fn with_datatype(mut self, datatype: ColumnType) -> Self {
+        self.datatype = Some(datatype);
+        self
+    }
+
+    /// Sets how the value is transformed before being ingested into the column.
+    #[must_use]
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.properties
+            .insert("Transform".to_string(), transform.to_string());
+        self
+    }
+}
+
+/// A transformation applied to the values of a column when ingesting them.
+/// See [the docs](https://learn.microsoft.com/en-us/azure/data-explorer/kusto/management/mappings#mapping-transformations).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Converts an array of `{"Key": k, "Value": v}` objects into a property bag.
+    PropertyBagArrayToDictionary,
+    /// The location of the ingested data, e.g. the URI of the blob.
+    SourceLocation,
+    /// The number of the line the value was read from.
+    SourceLineNumber,
+    /// Converts a number of seconds since the epoch into a datetime.
+    DateTimeFromUnixSeconds,
+    /// Converts a number of milliseconds since the epoch into a datetime.
+    DateTimeFromUnixMilliseconds,
+    /// Converts a number of microseconds since the epoch into a datetime.
+    DateTimeFromUnixMicroseconds,
+    /// Converts a number of nanoseconds since the epoch into a datetime.
+    DateTimeFromUnixNanoseconds,
+    /// Maps an object without the fields that are mapped to other columns.
+    DropMappedFields,
+    /// Ingests bytes as their base64 encoding.
+    BytesAsBase64,
+}
+
+impl Display for Transform {
+    /// Formats the transform as it is named in the `Transform` property of a mapping.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+/// Creates a mapping of the given kind for the columns, mapping each column to the field with the same name,
+/// or to the field at the same position for [IngestionMappingKind::Csv].
+#[must_use]
+pub fn column_mappings(kind: IngestionMappingKind, columns: &[Column]) -> Vec<ColumnMapping> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let name = column.column_name.clone();
+            let mapping = match kind {
+                IngestionMappingKind::Csv => ColumnMapping::with_ordinal(name, i),
+                IngestionMappingKind::W3CLogFile => {
+                    ColumnMapping::with_field(name, &column.column_name)
+                }
+                _ => ColumnMapping::with_path(name, json_path(&column.column_name)),
+            };
+            mapping.with_datatype(column.column_type.clone())
+        })
+        .collect()
+}
+
+/// The JSON path of a top level field, e.g. `$.a` or `$['a b']`.
+fn json_path(field: &str) -> String {
+    if crate::kql::is_identifier(field) {
+        format!("$.{field}")
+    } else {
+        format!("$['{}']", field.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generates_mappings() {
+        let columns = vec![
+            Column {
+                column_name: "a".to_string(),
+                column_type: ColumnType::Long,
+            },
+            Column {
+                column_name: "b 'c'".to_string(),
+                column_type: ColumnType::Dynamic,
+            },
+        ];
+
+        assert_eq!(
+            serde_json::to_value(column_mappings(IngestionMappingKind::Parquet, &columns))
+                .expect("Failed to serialize mapping"),
+            serde_json::json!([
+                {"column": "a", "datatype": "long", "Properties": {"Path": "$.a"}},
+                {"column": "b 'c'", "datatype": "dynamic", "Properties": {"Path": "$['b \\'c\\'']"}}
+            ])
+        );
+        assert_eq!(
+            column_mappings(IngestionMappingKind::Csv, &columns)[1],
+            ColumnMapping::with_ordinal("b 'c'", 1).with_datatype(ColumnType::Dynamic)
+        );
+
+        assert_eq!(
+            serde_json::to_value(
+                ColumnMapping::with_path("t", "$.time")
+                    .with_transform(Transform::DateTimeFromUnixSeconds)
+            )
+            .expect("Failed to serialize mapping"),
+            serde_json::json!({
+                "column": "t",
+                "Properties": {"Path": "$.time", "Transform": "DateTimeFromUnixSeconds"}
+            })
+        );
+
+        let parsed: Vec<ColumnMapping> = serde_json::from_str(
+            r#"[{"column": "a", "datatype": "long", "Properties": {"Ordinal": "0"}}]"#,
+        )
+        .expect("Failed to parse mapping");
+        assert_eq!(
+            parsed[0],
+            ColumnMapping::with_ordinal("a", 0).with_datatype(ColumnType::Long)
+        );
+    }
+}
@@ -0,0 +1,462 @@
+//! Safe composition of KQL queries.
+//!
+//! Values are embedded in queries as KQL literals through [ToKql], so that user input can't change the meaning of a query.
+//! The [kql!](crate::kql!) macro formats a query like [format!], but only accepts values implementing [ToKql]:
+//!
+//! ```
+//! use azure_kusto_data::kql;
+//! use azure_kusto_data::kql::entity;
+//!
+//! let name = "O'Brien \" or 1 == 1";
+//! let query = kql!("{} | where Name == {} | take {}", entity("My Table"), name, 10);
+//!
+//! assert_eq!(
+//!     query.as_str(),
+//!     r#"['My Table'] | where Name == "O'Brien \" or 1 == 1" | take 10"#
+//! );
+//! ```
+
+mod builder;
+
+pub use builder::{
+    avg, bin, col, count, dcount, func, left, lit, max, min, right, sum, Expr, JoinKind, Query,
+};
+
+use crate::error::{InvalidArgumentError, Result};
+use crate::types::{KustoDateTime, KustoDuration, KustoValue};
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+/// A fragment of KQL text, either written by the caller or made of escaped literals.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Kql(String);
+
+impl Kql {
+    /// Wraps KQL text as is. The text must not contain untrusted input, use [kql!](crate::kql!) to embed values.
+    pub fn raw(text: impl Into<String>) -> Self {
+        Self(text.into())
+    }
+
+    /// The text of the fragment.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the text of the fragment.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Replaces each `{}` placeholder of the template with the next argument, rendered as a KQL literal.
+    /// Braces are escaped by doubling them, as with [format!]. Used by [kql!](crate::kql!), which checks the template when compiling.
+    ///
+    /// # Panics
+    /// If the template is invalid, or if the number of arguments does not match the number of placeholders.
+    #[doc(hidden)]
+    #[must_use]
+    pub fn format(template: &str, args: &[&dyn ToKql]) -> Self {
+        let mut text = String::with_capacity(template.len());
+        let mut args = args.iter();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    text.push(c);
+                    chars.next();
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    let arg = args.next().expect("Too few arguments for the KQL template");
+                    text.push_str(arg.to_kql().as_str());
+                }
+                ('{' | '}', _) => {
+                    panic!("Invalid brace in KQL template, only `{{}}` placeholders are supported")
+                }
+                (c, _) => text.push(c),
+            }
+        }
+        assert!(
+            args.next().is_none(),
+            "Too many arguments for the KQL template"
+        );
+        Self(text)
+    }
+}
+
+impl Display for Kql {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<Kql> for String {
+    fn from(kql: Kql) -> Self {
+        kql.0
+    }
+}
+
+/// Counts the `{}` placeholders of a [kql!](crate::kql!) template, failing on any other use of braces.
+#[doc(hidden)]
+#[must_use]
+pub const fn placeholder_count(template: &str) -> usize {
+    let bytes = template.as_bytes();
+    let mut count = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = if i + 1 < bytes.len() { bytes[i + 1] } else { 0 };
+        match (bytes[i], next) {
+            (b'{', b'{') | (b'}', b'}') => i += 1,
+            (b'{', b'}') => {
+                count += 1;
+                i += 1;
+            }
+            (b'{' | b'}', _) => {
+                panic!("Invalid brace in KQL template, only `{{}}` placeholders are supported")
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Formats a KQL query, embedding each argument as a KQL literal with [ToKql].
+///
+/// Only positional `{}` placeholders are supported, so that every value goes through [ToKql], and the template is checked when compiling.
+/// Returns a [Kql](crate::kql::Kql), which can be passed wherever a query is expected.
+///
+/// ```
+/// use azure_kusto_data::kql;
+///
+/// let since = time::macros::datetime!(2023-01-02 03:04:05 UTC);
+/// let query = kql!("Events | where Timestamp > {} | extend Kind = {}", since, "a\\b");
+///
+/// assert_eq!(
+///     query.as_str(),
+///     r#"Events | where Timestamp > datetime(2023-01-02T03:04:05Z) | extend Kind = "a\\b""#
+/// );
+/// ```
+///
+/// Templates with named or captured placeholders, or with the wrong number of arguments, don't compile:
+///
+/// ```compile_fail
+/// let name = "x";
+/// let query = azure_kusto_data::kql!("T | where Name == {name}");
+/// ```
+///
+/// ```compile_fail
+/// let query = azure_kusto_data::kql!("T | take {}");
+/// ```
+#[macro_export]
+macro_rules! kql {
+    ($template:literal $(, $arg:expr)* $(,)?) => {{
+        const _: () = ::std::assert!(
+            $crate::kql::placeholder_count($template)
+                == <[&str]>::len(&[$(::std::stringify!($arg)),*]),
+            "The number of arguments does not match the number of placeholders of the KQL template"
+        );
+        $crate::kql::Kql::format($template, &[$(&$arg as &dyn $crate::kql::ToKql),*])
+    }};
+}
+
+/// A value that can be embedded in a query as a KQL literal.
+pub trait ToKql {
+    /// Renders the value as a KQL literal.
+    fn to_kql(&self) -> Kql;
+}
+
+impl<T: ToKql + ?Sized> ToKql for &T {
+    fn to_kql(&self) -> Kql {
+        (**self).to_kql()
+    }
+}
+
+impl ToKql for Kql {
+    fn to_kql(&self) -> Kql {
+        self.clone()
+    }
+}
+
+impl ToKql for KustoValue {
+    fn to_kql(&self) -> Kql {
+        Kql(self.to_string())
+    }
+}
+
+impl ToKql for str {
+    fn to_kql(&self) -> Kql {
+        string(self)
+    }
+}
+
+impl ToKql for String {
+    fn to_kql(&self) -> Kql {
+        string(self)
+    }
+}
+
+macro_rules! to_kql_integer {
+    ($($t:ty),*) => {
+        $(
+            /// Integers are rendered as plain numbers, so that integer literals in [kql!](crate::kql!) read naturally.
+            impl ToKql for $t {
+                fn to_kql(&self) -> Kql {
+                    Kql(self.to_string())
+                }
+            }
+        )*
+    };
+}
+
+to_kql_integer!(i32, i64);
+
+macro_rules! to_kql_through_value {
+    ($($t:ty),*) => {
+        $(
+            impl ToKql for $t {
+                fn to_kql(&self) -> Kql {
+                    KustoValue::from(self.clone()).to_kql()
+                }
+            }
+        )*
+    };
+}
+
+to_kql_through_value!(
+    bool,
+    f64,
+    KustoDateTime,
+    KustoDuration,
+    OffsetDateTime,
+    Duration,
+    Uuid,
+    Value
+);
+
+/// Renders a string as an escaped KQL string literal, e.g. `"a \"quoted\" string"`.
+#[must_use]
+pub fn string(s: &str) -> Kql {
+    Kql(quote(s, '"'))
+}
+
+/// Renders a string as a verbatim KQL string literal, e.g. `@"C:\temp"`, in which only quotes are escaped (by doubling them).
+///
+/// Verbatim strings can't span lines, so strings with line breaks are rendered as escaped literals instead.
+#[must_use]
+pub fn verbatim(s: &str) -> Kql {
+    if s.contains(['\n', '\r']) {
+        string(s)
+    } else {
+        Kql(format!("@\"{}\"", s.replace('"', "\"\"")))
+    }
+}
+
+/// Renders the name of an entity (table, column, function, ...) in brackets, e.g. `['My Table']`,
+/// so that any name can be used, including names with spaces or which are keywords.
+#[must_use]
+pub fn entity(name: &str) -> Kql {
+    Kql(format!("[{}]", quote(name, '\'')))
+}
+
+/// Renders a decimal number, given as its text representation (e.g. `"-1.50"` or `"1e-5"`), as a `decimal()` literal.
+///
+/// Fails if the text is not a decimal number.
+pub fn decimal(value: &str) -> Result<Kql> {
+    if is_decimal_literal(value) {
+        Ok(Kql(format!("decimal({value})")))
+    } else {
+        Err(InvalidArgumentError::InvalidDecimal(value.to_string()).into())
+    }
+}
+
+/// Whether the text is a decimal number, with an optional sign, fraction and exponent.
+pub(crate) fn is_decimal_literal(value: &str) -> bool {
+    fn digits(s: &str) -> (usize, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        (end, &s[end..])
+    }
+
+    let value = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (integer, rest) = digits(value);
+    let (fraction, rest) = match rest.strip_prefix('.') {
+        Some(rest) => digits(rest),
+        None => (0, rest),
+    };
+    if integer + fraction == 0 {
+        return false;
+    }
+    match rest.strip_prefix(['e', 'E']) {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+            let (digit_count, rest) = digits(exponent);
+            digit_count > 0 && rest.is_empty()
+        }
+        None => rest.is_empty(),
+    }
+}
+
+/// The longest name allowed for an entity.
+const MAX_ENTITY_NAME_LENGTH: usize = 1024;
+
+/// Names that are rendered in brackets even though they are identifiers, as they have a meaning in KQL.
+const KEYWORDS: &[&str] = &[
+    "and",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "contains",
+    "datatable",
+    "declare",
+    "desc",
+    "distinct",
+    "endswith",
+    "extend",
+    "false",
+    "has",
+    "in",
+    "join",
+    "kind",
+    "let",
+    "limit",
+    "not",
+    "null",
+    "on",
+    "or",
+    "order",
+    "print",
+    "project",
+    "range",
+    "set",
+    "sort",
+    "startswith",
+    "summarize",
+    "take",
+    "top",
+    "true",
+    "union",
+    "where",
+    "with",
+];
+
+/// Validates the name of an entity (table, column, ...) and renders it, in brackets if it is not a plain identifier.
+///
+/// Names are made of letters, digits, underscores, spaces, dots and dashes, and are at most 1024 characters long.
+pub(crate) fn entity_name(name: &str) -> std::result::Result<String, InvalidArgumentError> {
+    let valid = !name.is_empty()
+        && name.chars().count() <= MAX_ENTITY_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ' ' | '.' | '-'));
+    if !valid {
+        return Err(InvalidArgumentError::InvalidEntityName(name.to_string()));
+    }
+
+    if is_identifier(name) && !KEYWORDS.contains(&name.to_ascii_lowercase().as_str()) {
+        Ok(name.to_string())
+    } else {
+        Ok(format!("[{}]", quote(name, '\'')))
+    }
+}
+
+/// Whether a name is a plain identifier, made of ASCII letters, digits and underscores, and not starting with a digit.
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Quotes a string, escaping the quote character, backslashes and control characters.
+fn quote(s: &str, quote: char) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push(quote);
+    for c in s.chars() {
+        match c {
+            c if c == quote || c == '\\' => {
+                literal.push('\\');
+                literal.push(c);
+            }
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    literal.push_str(&format!("\\u{unit:04x}"));
+                }
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push(quote);
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn renders_literals() {
+        assert_eq!(string("a\"b\\c\n").as_str(), r#""a\"b\\c\n""#);
+        assert_eq!(verbatim(r#"C:\a "b""#).as_str(), r#"@"C:\a ""b""""#);
+        assert_eq!(verbatim("a\nb").as_str(), r#""a\nb""#);
+        assert_eq!(entity("My 'Table'").as_str(), r#"['My \'Table\'']"#);
+        assert_eq!(
+            datetime!(2023-01-02 03:04:05.5 UTC).to_kql().as_str(),
+            "datetime(2023-01-02T03:04:05.5Z)"
+        );
+        assert_eq!(
+            Duration::seconds(-90).to_kql().as_str(),
+            "time(-00:01:30.0000000)"
+        );
+        assert_eq!(
+            Uuid::nil().to_kql().as_str(),
+            "guid(00000000-0000-0000-0000-000000000000)"
+        );
+        assert_eq!(
+            serde_json::json!({"a": [1]}).to_kql().as_str(),
+            r#"dynamic({"a":[1]})"#
+        );
+        assert_eq!((-5_i64).to_kql().as_str(), "-5");
+        assert_eq!(5.to_kql().as_str(), "5");
+    }
+
+    #[test]
+    fn validates_decimals() {
+        for valid in ["1", "-1.50", "+.5", "1.", "1e5", "2.5E-3"] {
+            assert_eq!(
+                decimal(valid).expect("Failed to render decimal").as_str(),
+                format!("decimal({valid})")
+            );
+        }
+        for invalid in ["", "-", ".", "1e", "1.5.5", "1) | drop table T", "1 "] {
+            assert!(decimal(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn formats_queries() {
+        let table = entity("T");
+        let name = "x\" | drop table T //";
+        let query = crate::kql!("{} | where Name == {} | take {{ {} }}", table, name, 3_i64);
+        assert_eq!(
+            query.as_str(),
+            r#"['T'] | where Name == "x\" | drop table T //" | take { 3 }"#
+        );
+        assert_eq!(crate::kql!("T | count").as_str(), "T | count");
+        assert_eq!(placeholder_count("{}{{}}{}"), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many arguments")]
+    fn format_checks_arguments() {
+        let _ = Kql::format("{}", &[&1_i64, &2_i64]);
+    }
+}
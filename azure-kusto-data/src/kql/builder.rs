@@ -0,0 +1,620 @@
+//! A fluent builder for tabular KQL queries.
+
+use crate::error::{InvalidArgumentError, Result};
+use crate::kql::{entity_name, is_identifier, Kql, ToKql};
+use std::ops::{Add, Div, Mul, Not, Sub};
+
+/// How tightly an expression binds, from atoms to `or`. Operands binding more loosely than their operator are parenthesized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Atom,
+    Multiplicative,
+    Additive,
+    Comparison,
+    And,
+    Or,
+}
+
+impl Precedence {
+    /// The next tighter level.
+    fn tighter(self) -> Self {
+        match self {
+            Self::Atom | Self::Multiplicative => Self::Atom,
+            Self::Additive => Self::Multiplicative,
+            Self::Comparison => Self::Additive,
+            Self::And => Self::Comparison,
+            Self::Or => Self::And,
+        }
+    }
+}
+
+/// A scalar expression, e.g. `col("x").gt(5)`.
+///
+/// Values convert into expressions as literals through [ToKql], so `col("name").eq("x")` compares the column `name`
+/// with the string `"x"`. Invalid column names are reported when the query is [built](Query::build).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    text: std::result::Result<String, InvalidArgumentError>,
+    precedence: Precedence,
+}
+
+/// A column, e.g. `col("Timestamp")` or `col("My Column")`, which is rendered as `['My Column']`.
+#[must_use]
+pub fn col(name: &str) -> Expr {
+    Expr::atom(entity_name(name))
+}
+
+/// A literal value.
+#[must_use]
+pub fn lit(value: impl ToKql) -> Expr {
+    Expr::atom(Ok(value.to_kql().into_string()))
+}
+
+/// A column of the left side of a [join](Query::join), i.e. `$left.name`.
+#[must_use]
+pub fn left(name: &str) -> Expr {
+    Expr::atom(entity_name(name).map(|name| format!("$left.{name}")))
+}
+
+/// A column of the right side of a [join](Query::join), i.e. `$right.name`.
+#[must_use]
+pub fn right(name: &str) -> Expr {
+    Expr::atom(entity_name(name).map(|name| format!("$right.{name}")))
+}
+
+/// A call to a function, e.g. `func("strlen", [col("Name")])`. The name of the function must be an identifier.
+#[must_use]
+pub fn func(name: &str, args: impl IntoIterator<Item = Expr>) -> Expr {
+    if !is_identifier(name) {
+        return Expr::atom(Err(InvalidArgumentError::InvalidIdentifier(
+            name.to_string(),
+        )));
+    }
+    Expr::atom(join_exprs(args).map(|args| format!("{name}({args})")))
+}
+
+/// The `count()` aggregation.
+#[must_use]
+pub fn count() -> Expr {
+    func("count", [])
+}
+
+/// The `dcount()` aggregation.
+#[must_use]
+pub fn dcount(expr: impl Into<Expr>) -> Expr {
+    func("dcount", [expr.into()])
+}
+
+/// The `sum()` aggregation.
+#[must_use]
+pub fn sum(expr: impl Into<Expr>) -> Expr {
+    func("sum", [expr.into()])
+}
+
+/// The `avg()` aggregation.
+#[must_use]
+pub fn avg(expr: impl Into<Expr>) -> Expr {
+    func("avg", [expr.into()])
+}
+
+/// The `min()` aggregation.
+#[must_use]
+pub fn min(expr: impl Into<Expr>) -> Expr {
+    func("min", [expr.into()])
+}
+
+/// The `max()` aggregation.
+#[must_use]
+pub fn max(expr: impl Into<Expr>) -> Expr {
+    func("max", [expr.into()])
+}
+
+/// The `bin()` function, rounding values down to a multiple of `size`, e.g. `bin(col("Timestamp"), lit(time::Duration::hours(1)))`.
+#[must_use]
+pub fn bin(expr: impl Into<Expr>, size: impl Into<Expr>) -> Expr {
+    func("bin", [expr.into(), size.into()])
+}
+
+impl<T: ToKql> From<T> for Expr {
+    fn from(value: T) -> Self {
+        lit(value)
+    }
+}
+
+impl Expr {
+    /// Wraps KQL text as an expression, which is parenthesized when combined with other expressions.
+    #[must_use]
+    pub fn raw(kql: Kql) -> Self {
+        Self {
+            text: Ok(format!("({kql})")),
+            precedence: Precedence::Atom,
+        }
+    }
+
+    fn atom(text: std::result::Result<String, InvalidArgumentError>) -> Self {
+        Self {
+            text,
+            precedence: Precedence::Atom,
+        }
+    }
+
+    /// Renders the expression, parenthesized if it binds more loosely than `precedence`.
+    fn operand(self, precedence: Precedence) -> std::result::Result<String, InvalidArgumentError> {
+        let text = self.text?;
+        Ok(if self.precedence > precedence {
+            format!("({text})")
+        } else {
+            text
+        })
+    }
+
+    fn binary(self, operator: &str, other: impl Into<Expr>, precedence: Precedence) -> Self {
+        let other = other.into();
+        // Arithmetic operators are left associative, so an operand of the same precedence is only left bare on the left.
+        // Comparisons don't chain, and `and`/`or` are associative.
+        let (left, right) = match precedence {
+            Precedence::Comparison => (precedence.tighter(), precedence.tighter()),
+            Precedence::And | Precedence::Or => (precedence, precedence),
+            _ => (precedence, precedence.tighter()),
+        };
+        let text = self.operand(left).and_then(|left| {
+            let right = other.operand(right)?;
+            Ok(format!("{left} {operator} {right}"))
+        });
+        Self { text, precedence }
+    }
+
+    /// `self == other`.
+    #[must_use]
+    pub fn eq(self, other: impl Into<Expr>) -> Self {
+        self.binary("==", other, Precedence::Comparison)
+    }
+
+    /// `self != other`.
+    #[must_use]
+    pub fn ne(self, other: impl Into<Expr>) -> Self {
+        self.binary("!=", other, Precedence::Comparison)
+    }
+
+    /// `self > other`.
+    #[must_use]
+    pub fn gt(self, other: impl Into<Expr>) -> Self {
+        self.binary(">", other, Precedence::Comparison)
+    }
+
+    /// `self >= other`.
+    #[must_use]
+    pub fn ge(self, other: impl Into<Expr>) -> Self {
+        self.binary(">=", other, Precedence::Comparison)
+    }
+
+    /// `self < other`.
+    #[must_use]
+    pub fn lt(self, other: impl Into<Expr>) -> Self {
+        self.binary("<", other, Precedence::Comparison)
+    }
+
+    /// `self <= other`.
+    #[must_use]
+    pub fn le(self, other: impl Into<Expr>) -> Self {
+        self.binary("<=", other, Precedence::Comparison)
+    }
+
+    /// `self contains other`, a case-insensitive substring match.
+    #[must_use]
+    pub fn contains(self, other: impl Into<Expr>) -> Self {
+        self.binary("contains", other, Precedence::Comparison)
+    }
+
+    /// `self has other`, a case-insensitive match of a whole term.
+    #[must_use]
+    pub fn has(self, other: impl Into<Expr>) -> Self {
+        self.binary("has", other, Precedence::Comparison)
+    }
+
+    /// `self startswith other`, case-insensitive.
+    #[must_use]
+    pub fn starts_with(self, other: impl Into<Expr>) -> Self {
+        self.binary("startswith", other, Precedence::Comparison)
+    }
+
+    /// `self endswith other`, case-insensitive.
+    #[must_use]
+    pub fn ends_with(self, other: impl Into<Expr>) -> Self {
+        self.binary("endswith", other, Precedence::Comparison)
+    }
+
+    /// `self in (values...)`.
+    #[must_use]
+    pub fn in_<E: Into<Expr>>(self, values: impl IntoIterator<Item = E>) -> Self {
+        let values = values.into_iter().map(Into::into);
+        let text = self
+            .operand(Precedence::Additive)
+            .and_then(|text| Ok(format!("{text} in ({})", join_exprs(values)?)));
+        Self {
+            text,
+            precedence: Precedence::Comparison,
+        }
+    }
+
+    /// `self between (low .. high)`.
+    #[must_use]
+    pub fn between(self, low: impl Into<Expr>, high: impl Into<Expr>) -> Self {
+        let (low, high) = (low.into(), high.into());
+        let text = self.operand(Precedence::Additive).and_then(|text| {
+            Ok(format!(
+                "{text} between ({} .. {})",
+                low.operand(Precedence::Additive)?,
+                high.operand(Precedence::Additive)?
+            ))
+        });
+        Self {
+            text,
+            precedence: Precedence::Comparison,
+        }
+    }
+
+    /// `isnull(self)`.
+    #[must_use]
+    pub fn is_null(self) -> Self {
+        func("isnull", [self])
+    }
+
+    /// `isnotnull(self)`.
+    #[must_use]
+    pub fn is_not_null(self) -> Self {
+        func("isnotnull", [self])
+    }
+
+    /// `self and other`.
+    #[must_use]
+    pub fn and(self, other: impl Into<Expr>) -> Self {
+        self.binary("and", other, Precedence::And)
+    }
+
+    /// `self or other`.
+    #[must_use]
+    pub fn or(self, other: impl Into<Expr>) -> Self {
+        self.binary("or", other, Precedence::Or)
+    }
+
+    /// Names the expression in [project](Query::project), [extend](Query::extend) or [summarize](Query::summarize), i.e. `name = self`.
+    #[must_use]
+    pub fn alias(self, name: &str) -> Self {
+        let text = entity_name(name)
+            .and_then(|name| Ok(format!("{name} = {}", self.operand(Precedence::Or)?)));
+        Self {
+            text,
+            precedence: Precedence::Or,
+        }
+    }
+
+    /// Sorts by the expression in ascending order, in [order_by](Query::order_by) or [top](Query::top).
+    #[must_use]
+    pub fn asc(self) -> Self {
+        self.sort("asc")
+    }
+
+    /// Sorts by the expression in descending order, in [order_by](Query::order_by) or [top](Query::top).
+    #[must_use]
+    pub fn desc(self) -> Self {
+        self.sort("desc")
+    }
+
+    fn sort(self, direction: &str) -> Self {
+        let text = self
+            .operand(Precedence::Additive)
+            .map(|text| format!("{text} {direction}"));
+        Self {
+            text,
+            precedence: Precedence::Or,
+        }
+    }
+}
+
+macro_rules! arithmetic_operators {
+    ($($trait:ident $method:ident $operator:literal $precedence:ident),*) => {
+        $(
+            impl<T: Into<Expr>> $trait<T> for Expr {
+                type Output = Expr;
+
+                fn $method(self, other: T) -> Expr {
+                    self.binary($operator, other, Precedence::$precedence)
+                }
+            }
+        )*
+    };
+}
+
+arithmetic_operators!(
+    Add add "+" Additive,
+    Sub sub "-" Additive,
+    Mul mul "*" Multiplicative,
+    Div div "/" Multiplicative
+);
+
+impl Not for Expr {
+    type Output = Expr;
+
+    /// `not(self)`.
+    fn not(self) -> Expr {
+        func("not", [self])
+    }
+}
+
+fn join_exprs(
+    exprs: impl IntoIterator<Item = Expr>,
+) -> std::result::Result<String, InvalidArgumentError> {
+    Ok(exprs
+        .into_iter()
+        .map(|e| e.operand(Precedence::Or))
+        .collect::<std::result::Result<Vec<_>, _>>()?
+        .join(", "))
+}
+
+/// The kind of a [join](Query::join).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JoinKind {
+    /// The default kind of joins in KQL, an inner join keeping only one row of the left side per key.
+    #[default]
+    InnerUnique,
+    /// All combinations of matching rows.
+    Inner,
+    /// All rows of the left side, with the matching rows of the right side if any.
+    LeftOuter,
+    /// All rows of the right side, with the matching rows of the left side if any.
+    RightOuter,
+    /// All rows of both sides, matched where possible.
+    FullOuter,
+    /// Rows of the left side that have a match on the right side, with the columns of the left side only.
+    LeftSemi,
+    /// Rows of the right side that have a match on the left side, with the columns of the right side only.
+    RightSemi,
+    /// Rows of the left side that have no match on the right side.
+    LeftAnti,
+    /// Rows of the right side that have no match on the left side.
+    RightAnti,
+}
+
+impl JoinKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::InnerUnique => "innerunique",
+            Self::Inner => "inner",
+            Self::LeftOuter => "leftouter",
+            Self::RightOuter => "rightouter",
+            Self::FullOuter => "fullouter",
+            Self::LeftSemi => "leftsemi",
+            Self::RightSemi => "rightsemi",
+            Self::LeftAnti => "leftanti",
+            Self::RightAnti => "rightanti",
+        }
+    }
+}
+
+/// A tabular query, built from a table and a pipeline of operators.
+///
+/// ```
+/// use azure_kusto_data::kql::{col, count, Query};
+///
+/// let query = Query::table("Storm Events")
+///     .where_(col("State").eq("TEXAS").and(col("Damage").gt(1000)))
+///     .summarize([count().alias("Events")], [col("EventType")])
+///     .order_by([col("Events").desc()])
+///     .take(10)
+///     .build()?;
+///
+/// assert_eq!(
+///     query.as_str(),
+///     r#"['Storm Events'] | where State == "TEXAS" and Damage > 1000 | summarize Events = count() by EventType | order by Events desc | take 10"#
+/// );
+/// # Ok::<(), azure_kusto_data::error::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// The rendered source and operators, or the first error met while building the query.
+    parts: std::result::Result<Vec<String>, InvalidArgumentError>,
+}
+
+impl Query {
+    /// Starts a query from a table, failing when [built](Query::build) if the name is not a valid entity name.
+    #[must_use]
+    pub fn table(name: &str) -> Self {
+        Self {
+            parts: entity_name(name).map(|name| vec![name]),
+        }
+    }
+
+    /// Renders the query, failing if any name in it is invalid.
+    pub fn build(self) -> Result<Kql> {
+        Ok(Kql::raw(self.parts?.join(" | ")))
+    }
+
+    fn push(
+        mut self,
+        operator: impl FnOnce() -> std::result::Result<String, InvalidArgumentError>,
+    ) -> Self {
+        if let Ok(parts) = &mut self.parts {
+            match operator() {
+                Ok(operator) => parts.push(operator),
+                Err(e) => self.parts = Err(e),
+            }
+        }
+        self
+    }
+
+    /// Renders a query used as an operand of another, in parentheses.
+    fn subquery(self) -> std::result::Result<String, InvalidArgumentError> {
+        Ok(format!("({})", self.parts?.join(" | ")))
+    }
+
+    /// Keeps the rows matching the predicate: `| where predicate`.
+    #[must_use]
+    pub fn where_(self, predicate: Expr) -> Self {
+        self.push(|| Ok(format!("where {}", predicate.operand(Precedence::Or)?)))
+    }
+
+    /// Keeps only the given columns or named expressions: `| project a, b = expr`.
+    #[must_use]
+    pub fn project(self, columns: impl IntoIterator<Item = Expr>) -> Self {
+        self.push(|| Ok(format!("project {}", join_exprs(columns)?)))
+    }
+
+    /// Adds computed columns: `| extend name = expr`.
+    #[must_use]
+    pub fn extend(self, columns: impl IntoIterator<Item = Expr>) -> Self {
+        self.push(|| Ok(format!("extend {}", join_exprs(columns)?)))
+    }
+
+    /// Aggregates rows by groups: `| summarize aggregations by groups`. Without groups, all rows are aggregated together.
+    #[must_use]
+    pub fn summarize(
+        self,
+        aggregations: impl IntoIterator<Item = Expr>,
+        by: impl IntoIterator<Item = Expr>,
+    ) -> Self {
+        self.push(|| {
+            let aggregations = join_exprs(aggregations)?;
+            let by = join_exprs(by)?;
+            Ok(match (aggregations.is_empty(), by.is_empty()) {
+                (_, true) => format!("summarize {aggregations}"),
+                (true, false) => format!("summarize by {by}"),
+                (false, false) => format!("summarize {aggregations} by {by}"),
+            })
+        })
+    }
+
+    /// Joins with the rows of another query: `| join kind=... (right) on conditions`.
+    ///
+    /// Conditions are either columns with the same name on both sides (`col("Id")`),
+    /// or equalities between [left] and [right] columns (`left("Id").eq(right("UserId"))`).
+    #[must_use]
+    pub fn join(self, kind: JoinKind, right: Query, on: impl IntoIterator<Item = Expr>) -> Self {
+        self.push(|| {
+            Ok(format!(
+                "join kind={} {} on {}",
+                kind.as_str(),
+                right.subquery()?,
+                join_exprs(on)?
+            ))
+        })
+    }
+
+    /// Sorts the rows: `| order by a desc, b asc`. See [Expr::asc] and [Expr::desc], columns are sorted in descending order by default.
+    #[must_use]
+    pub fn order_by(self, columns: impl IntoIterator<Item = Expr>) -> Self {
+        self.push(|| Ok(format!("order by {}", join_exprs(columns)?)))
+    }
+
+    /// Keeps at most `count` rows, in no particular order: `| take count`.
+    #[must_use]
+    pub fn take(self, count: u64) -> Self {
+        self.push(|| Ok(format!("take {count}")))
+    }
+
+    /// Keeps the first `count` rows when sorted by an expression: `| top count by expr`.
+    #[must_use]
+    pub fn top(self, count: u64, by: Expr) -> Self {
+        self.push(|| Ok(format!("top {count} by {}", by.operand(Precedence::Or)?)))
+    }
+
+    /// Keeps the distinct combinations of the given columns: `| distinct a, b`, or of all columns if none are given.
+    #[must_use]
+    pub fn distinct(self, columns: impl IntoIterator<Item = Expr>) -> Self {
+        self.push(|| {
+            let columns = join_exprs(columns)?;
+            Ok(if columns.is_empty() {
+                "distinct *".to_string()
+            } else {
+                format!("distinct {columns}")
+            })
+        })
+    }
+
+    /// Appends the rows of other queries: `| union (query), ...`.
+    #[must_use]
+    pub fn union(self, others: impl IntoIterator<Item = Query>) -> Self {
+        self.push(|| {
+            let others = others
+                .into_iter()
+                .map(Query::subquery)
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Ok(format!("union {}", others.join(", ")))
+        })
+    }
+}
+
+impl TryFrom<Query> for Kql {
+    type Error = crate::error::Error;
+
+    fn try_from(query: Query) -> Result<Self> {
+        query.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_operators() {
+        let query = Query::table("Events")
+            .where_(col("Level").in_(["Error", "Warning"]).and(!col("Muted")))
+            .extend([(col("End") - col("Start")).alias("Duration")])
+            .project([col("Id"), col("Duration"), col("by")])
+            .join(
+                JoinKind::LeftOuter,
+                Query::table("Users").distinct([]),
+                [left("UserId").eq(right("Id"))],
+            )
+            .union([Query::table("Archive").top(5, col("Duration").asc())])
+            .summarize([], [bin(col("Start"), lit(time::Duration::hours(1)))])
+            .build()
+            .expect("Failed to build query");
+
+        assert_eq!(
+            query.as_str(),
+            "Events \
+            | where Level in (\"Error\", \"Warning\") and not(Muted) \
+            | extend Duration = End - Start \
+            | project Id, Duration, ['by'] \
+            | join kind=leftouter (Users | distinct *) on $left.UserId == $right.Id \
+            | union (Archive | top 5 by Duration asc) \
+            | summarize by bin(Start, time(01:00:00.0000000))"
+        );
+    }
+
+    #[test]
+    fn parenthesizes_by_precedence() {
+        let render = |e: Expr| e.text.expect("Failed to render");
+
+        assert_eq!(
+            render(col("a").or(col("b")).and(col("c").or(col("d")))),
+            "(a or b) and (c or d)"
+        );
+        assert_eq!(render(col("a").and(col("b")).or(col("c"))), "a and b or c");
+        assert_eq!(render((col("a") + 1) * (col("b") - 2)), "(a + 1) * (b - 2)");
+        assert_eq!(render(col("a") - (col("b") - col("c"))), "a - (b - c)");
+        assert_eq!(render(col("a").gt(1).eq(true)), "(a > 1) == true");
+        assert_eq!(render(Expr::raw(Kql::raw("x | y")).eq(1)), "(x | y) == 1");
+    }
+
+    #[test]
+    fn validates_entity_names() {
+        assert_eq!(
+            entity_name("My Table-1.x"),
+            Ok("['My Table-1.x']".to_string())
+        );
+        assert_eq!(entity_name("Where"), Ok("['Where']".to_string()));
+        assert_eq!(entity_name("_T1"), Ok("_T1".to_string()));
+
+        for invalid in ["", "T']; drop", "a\nb", "x".repeat(1025).as_str()] {
+            assert!(Query::table(invalid).build().is_err(), "{invalid}");
+        }
+        assert!(Query::table("T")
+            .where_(col("x'").eq(1))
+            .take(1)
+            .build()
+            .is_err());
+    }
+}
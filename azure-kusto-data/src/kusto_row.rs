@@ -0,0 +1,284 @@
+//! Typed rows, mapping the fields of a struct to the columns of a table.
+//!
+//! [KustoRow] is usually implemented with `#[derive(KustoRow)]` from the `azure-kusto-derive` crate,
+//! re-exported here with the `derive` feature:
+//!
+//! ```ignore
+//! use azure_kusto_data::kusto_row::KustoRow;
+//!
+//! #[derive(KustoRow)]
+//! struct Event {
+//!     #[kusto(rename = "Timestamp")]
+//!     timestamp: time::OffsetDateTime,
+//!     #[kusto(column_type = "decimal")]
+//!     amount: String,
+//!     // Columns of `Option` fields may hold nulls.
+//!     user: Option<String>,
+//!     // Columns of optional fields may be missing from the results, leaving the field at its default.
+//!     #[kusto(optional)]
+//!     tags: Option<serde_json::Value>,
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::models::{Column, ColumnType, TableColumn};
+use crate::types::{FromKustoValue, KustoValue};
+use serde_json::Value;
+use std::fmt::Write;
+use std::marker::PhantomData;
+
+#[cfg(feature = "derive")]
+pub use azure_kusto_derive::KustoRow;
+
+/// A type whose values are rows of a table with a fixed schema.
+pub trait KustoRow: Sized {
+    /// The columns of the row, in the order of the fields.
+    fn columns() -> Vec<Column>;
+
+    /// Whether the column at the given index may be missing from a result.
+    fn is_optional(index: usize) -> bool {
+        let _ = index;
+        false
+    }
+
+    /// Creates a row from its values, in the order of [columns](KustoRow::columns), with [None] for missing columns.
+    fn from_values(values: Vec<Option<KustoValue>>) -> Result<Self>;
+
+    /// The values of the row, in the order of [columns](KustoRow::columns).
+    fn to_values(&self) -> Vec<KustoValue>;
+}
+
+/// Converts the value of a column into a field, failing if the column is missing.
+pub fn field_value<T: FromKustoValue>(value: Option<KustoValue>, column_name: &str) -> Result<T> {
+    let value =
+        value.ok_or_else(|| Error::ConversionError(format!("missing column {column_name}")))?;
+    T::from_kusto_value(value)
+        .map_err(|e| Error::ConversionError(format!("column {column_name}: {e}")))
+}
+
+/// Converts the value of an optional column into a field, which is left at its default if the column is missing.
+pub fn optional_field_value<T: FromKustoValue + Default>(
+    value: Option<KustoValue>,
+    column_name: &str,
+) -> Result<T> {
+    match value {
+        Some(value) => field_value(Some(value), column_name),
+        None => Ok(T::default()),
+    }
+}
+
+/// Converts a field into the value of a column of the given type, e.g. a `String` field of a `guid` column into a [KustoValue::Guid].
+///
+/// Values that are not valid for the column type, such as a string that is not a guid, are kept as they are.
+pub fn column_value(value: impl Into<KustoValue>, column_type: &ColumnType) -> KustoValue {
+    let value = value.into();
+    if value.is_null() || value.column_type().as_ref() == Some(column_type) {
+        return value;
+    }
+    KustoValue::from_json(value.to_json(), column_type).unwrap_or(value)
+}
+
+/// Decodes rows of a result into `T`, after checking its columns against the schema of `T` once.
+///
+/// Columns are matched by name, regardless of their position, and columns not in the schema of `T` are ignored.
+pub struct RowDecoder<T> {
+    /// The expected type, and the index in the result, of each column of `T`.
+    columns: Vec<(ColumnType, Option<usize>)>,
+    row: PhantomData<fn() -> T>,
+}
+
+impl<T: KustoRow> RowDecoder<T> {
+    /// Creates a decoder for rows with the given columns.
+    ///
+    /// Fails if a column of `T` that is not optional is missing, or if a column has a different type than in `T`.
+    /// Results of management commands may have untyped columns, which match any type.
+    pub fn new(result_columns: &[impl TableColumn]) -> Result<Self> {
+        let columns = T::columns()
+            .into_iter()
+            .enumerate()
+            .map(|(i, column)| {
+                let index = result_columns
+                    .iter()
+                    .position(|c| c.name() == column.column_name);
+                match index.map(|index| (index, result_columns[index].kusto_type())) {
+                    None if T::is_optional(i) => Ok((column.column_type, None)),
+                    None => Err(Error::ConversionError(format!(
+                        "missing column {}",
+                        column.column_name
+                    ))),
+                    Some((index, actual))
+                        if actual == column.column_type || actual == ColumnType::Dynamic =>
+                    {
+                        Ok((column.column_type, Some(index)))
+                    }
+                    Some((_, actual)) => Err(Error::ConversionError(format!(
+                        "column {} is of type {actual:?} instead of {:?}",
+                        column.column_name, column.column_type
+                    ))),
+                }
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            columns,
+            row: PhantomData,
+        })
+    }
+
+    /// Decodes a row, given its values in the order of the result columns.
+    pub fn decode(&self, values: &[Value]) -> Result<T> {
+        let values = self
+            .columns
+            .iter()
+            .map(|(column_type, index)| {
+                index
+                    .map(|index| {
+                        let value = values.get(index).cloned().ok_or_else(|| {
+                            Error::ConversionError(format!("row has no value for column {index}"))
+                        })?;
+                        KustoValue::from_json(value, column_type)
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_>>()?;
+        T::from_values(values)
+    }
+}
+
+/// Encodes a row as a JSON object keyed by column name, e.g. for ingestion in the `json` or `multijson` formats.
+pub fn to_json_row<T: KustoRow>(row: &T) -> Value {
+    Value::Object(
+        T::columns()
+            .into_iter()
+            .zip(row.to_values())
+            .map(|(column, value)| (column.column_name, value.to_json()))
+            .collect(),
+    )
+}
+
+/// Encodes a row as a line of CSV, without a line terminator, in the order of the columns of `T`.
+///
+/// Nulls are empty fields, and fields are quoted when needed.
+pub fn to_csv_row<T: KustoRow>(row: &T) -> String {
+    let mut line = String::new();
+    for (i, value) in row.to_values().into_iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        let field = match value.to_json() {
+            Value::Null => continue,
+            Value::String(s) => s,
+            value => value.to_string(),
+        };
+        if field.contains([',', '"', '\n', '\r']) {
+            let _ = write!(line, "\"{}\"", field.replace('"', "\"\""));
+        } else {
+            line.push_str(&field);
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::KustoDuration;
+
+    /// What `#[derive(KustoRow)]` generates.
+    #[derive(Debug, PartialEq)]
+    struct Event {
+        name: String,
+        took: Option<KustoDuration>,
+        count: i64,
+    }
+
+    impl KustoRow for Event {
+        fn columns() -> Vec<Column> {
+            vec![
+                Column {
+                    column_name: "Name".to_string(),
+                    column_type: ColumnType::String,
+                },
+                Column {
+                    column_name: "took".to_string(),
+                    column_type: ColumnType::Timespan,
+                },
+                Column {
+                    column_name: "count".to_string(),
+                    column_type: ColumnType::Long,
+                },
+            ]
+        }
+
+        fn is_optional(index: usize) -> bool {
+            [false, false, true][index]
+        }
+
+        fn from_values(values: Vec<Option<KustoValue>>) -> Result<Self> {
+            let mut values = values.into_iter();
+            Ok(Self {
+                name: field_value(values.next().flatten(), "Name")?,
+                took: field_value(values.next().flatten(), "took")?,
+                count: optional_field_value(values.next().flatten(), "count")?,
+            })
+        }
+
+        fn to_values(&self) -> Vec<KustoValue> {
+            vec![
+                column_value(self.name.clone(), &ColumnType::String),
+                column_value(self.took, &ColumnType::Timespan),
+                column_value(self.count, &ColumnType::Long),
+            ]
+        }
+    }
+
+    fn columns(json: &str) -> Vec<Column> {
+        serde_json::from_str(json).expect("Failed to parse columns")
+    }
+
+    #[test]
+    fn decodes_by_name() {
+        let decoder = RowDecoder::<Event>::new(&columns(
+            r#"[{"ColumnName": "took", "ColumnType": "timespan"}, {"ColumnName": "extra", "ColumnType": "int"}, {"ColumnName": "Name", "ColumnType": "string"}]"#,
+        ))
+        .expect("Failed to create decoder");
+
+        let event = decoder
+            .decode(&[Value::Null, Value::from(1), Value::from("a")])
+            .expect("Failed to decode");
+        assert_eq!(
+            event,
+            Event {
+                name: "a".to_string(),
+                took: None,
+                count: 0
+            }
+        );
+    }
+
+    #[test]
+    fn validates_columns() {
+        assert!(RowDecoder::<Event>::new(&columns(
+            r#"[{"ColumnName": "Name", "ColumnType": "string"}]"#
+        ))
+        .is_err());
+        assert!(RowDecoder::<Event>::new(&columns(
+            r#"[{"ColumnName": "Name", "ColumnType": "int"}, {"ColumnName": "took", "ColumnType": "timespan"}]"#
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn encodes_rows() {
+        let event = Event {
+            name: "a, \"b\"".to_string(),
+            took: None,
+            count: 3,
+        };
+        assert_eq!(to_csv_row(&event), r#""a, ""b""",,3"#);
+        assert_eq!(
+            to_json_row(&event),
+            serde_json::json!({"Name": "a, \"b\"", "took": null, "count": 3})
+        );
+    }
+}
@@ -5,7 +5,7 @@
 //! Learn more about Azure Data Explorer at [https://docs.microsoft.com/en-us/azure/data-explorer/](https://docs.microsoft.com/en-us/azure/data-explorer/).
 
 #[cfg(feature = "arrow")]
-mod arrow;
+pub mod arrow;
 mod authorization_policy;
 pub mod client;
 pub mod client_details;
@@ -13,8 +13,15 @@ pub mod cloud_info;
 pub mod connection_string;
 pub mod credentials;
 pub mod error;
+pub mod ingestion_mapping;
+pub mod kql;
+pub mod kusto_row;
+pub mod management;
 pub mod models;
 mod operations;
 pub mod prelude;
 pub mod request_options;
+pub mod row_deserializer;
+mod service_error_policy;
+pub mod statistics;
 pub mod types;
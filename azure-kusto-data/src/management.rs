@@ -0,0 +1,858 @@
+//! Typed management commands for tables, databases and functions.
+//!
+//! Commands are run through a [Management] handle, obtained with [KustoClient::management],
+//! and their results are parsed into the structs of this module.
+//!
+//! ```no_run
+//! use azure_kusto_data::prelude::*;
+//! use azure_kusto_data::models::{Column, ColumnType};
+//! # #[tokio::main] async fn main() -> Result<(), Error> {
+//! let client = KustoClient::new(
+//!     ConnectionString::with_default_auth("https://mycluster.region.kusto.windows.net/"),
+//!     KustoClientOptions::default())?;
+//! let management = client.management("some_database");
+//!
+//! management
+//!     .create_merge_table("Events", &[Column {
+//!         column_name: "Timestamp".to_string(),
+//!         column_type: ColumnType::Datetime,
+//!     }])
+//!     .await?;
+//!
+//! for table in management.show_tables().await? {
+//!     println!("{}", table.table_name);
+//! }
+//! # Ok(())}
+//! ```
+
+use crate::client::KustoClient;
+use crate::error::{Error, Result};
+use crate::ingestion_mapping::{ColumnMapping, IngestionMappingKind};
+use crate::kql::{self, entity_name, Kql};
+use crate::models::{Column, ColumnType, TableV1};
+use crate::operations::query::KustoResponseDataSetV1;
+use crate::row_deserializer::RowDeserializerOptions;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+/// Runs management commands against a database.
+#[derive(Debug, Clone)]
+pub struct Management {
+    client: KustoClient,
+    database: String,
+}
+
+impl Management {
+    pub(crate) fn new(client: KustoClient, database: String) -> Self {
+        Self { client, database }
+    }
+
+    /// The database the commands are run against.
+    #[must_use]
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+
+    /// Runs a command, returning the rows of its result.
+    async fn execute<T: DeserializeOwned>(&self, command: String) -> Result<Vec<T>> {
+        let response = self
+            .client
+            .execute_command(self.database.clone(), command, None)
+            .await?;
+        command_result(response)?.into_deserialized_rows(RowDeserializerOptions::default())
+    }
+
+    /// Runs a command returning a single row.
+    async fn execute_one<T: DeserializeOwned>(&self, command: String) -> Result<T> {
+        self.execute(command)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                Error::ConversionError("management command returned no rows".to_string())
+            })
+    }
+
+    /// Lists the tables of the database: `.show tables`.
+    pub async fn show_tables(&self) -> Result<Vec<TableInfo>> {
+        self.execute(".show tables".to_string()).await
+    }
+
+    /// Gets the schema of a table: `.show table T schema as json`.
+    pub async fn show_table_schema(&self, table: &str) -> Result<TableSchema> {
+        let table = entity_name(table)?;
+        self.execute_one::<SchemaRow>(format!(".show table {table} schema as json"))
+            .await?
+            .try_into()
+    }
+
+    /// Gets the full schema of a database, with its tables, functions, materialized views and external tables:
+    /// `.show database db schema as json`.
+    pub async fn show_database_schema(&self) -> Result<DatabaseSchema> {
+        let database = entity_name(&self.database)?;
+        let row: DatabaseSchemaRow = self
+            .execute_one(format!(".show database {database} schema as json"))
+            .await?;
+        parse_database_schema(&row.database_schema, &self.database)
+    }
+
+    /// Lists the databases of the cluster that the principal can access: `.show databases`.
+    pub async fn show_databases(&self) -> Result<Vec<DatabaseInfo>> {
+        self.execute(".show databases".to_string()).await
+    }
+
+    /// Lists the stored functions of the database: `.show functions`.
+    pub async fn show_functions(&self) -> Result<Vec<FunctionInfo>> {
+        self.execute(".show functions".to_string()).await
+    }
+
+    /// Creates a table, or adds the missing columns to an existing table: `.create-merge table T (a:string, ...)`.
+    /// Returns the resulting schema of the table.
+    pub async fn create_merge_table(&self, table: &str, columns: &[Column]) -> Result<TableSchema> {
+        let command = create_merge_table_command(table, columns)?;
+        self.execute_one::<SchemaRow>(command.into())
+            .await?
+            .try_into()
+    }
+
+    /// Creates a table from an Arrow schema, or adds the missing columns to an existing table.
+    /// See [create_merge_table](Self::create_merge_table) and [schema_columns](crate::arrow::schema_columns).
+    #[cfg(feature = "arrow")]
+    pub async fn create_merge_table_from_schema(
+        &self,
+        table: &str,
+        schema: &arrow_schema::Schema,
+    ) -> Result<TableSchema> {
+        self.create_merge_table(table, &crate::arrow::schema_columns(schema)?)
+            .await
+    }
+
+    /// Creates or replaces an ingestion mapping of a table:
+    /// `.create-or-alter table T ingestion kind mapping "name" "[...]"`.
+    ///
+    /// Ingestions can then refer to the mapping by name. See [column_mappings](crate::ingestion_mapping::column_mappings).
+    pub async fn create_ingestion_mapping(
+        &self,
+        table: &str,
+        name: &str,
+        kind: IngestionMappingKind,
+        mappings: &[ColumnMapping],
+    ) -> Result<IngestionMappingInfo> {
+        let command = create_ingestion_mapping_command(table, name, kind, mappings)?;
+        self.execute_one(command.into()).await
+    }
+
+    /// Drops a table: `.drop table T`, returning the remaining tables.
+    /// Dropping a table that does not exist fails, unless `if_exists` is set.
+    pub async fn drop_table(&self, table: &str, if_exists: bool) -> Result<Vec<TableInfo>> {
+        let table = entity_name(table)?;
+        let if_exists = if if_exists { " ifexists" } else { "" };
+        self.execute(format!(".drop table {table}{if_exists}"))
+            .await
+    }
+
+    /// Creates or replaces a stored function: `.create-or-alter function`.
+    pub async fn create_or_alter_function(
+        &self,
+        function: &FunctionDefinition,
+    ) -> Result<FunctionInfo> {
+        self.execute_one(function.command()?).await
+    }
+
+    /// Sets a policy of a table: `.alter table T policy kind "policy"`, returning the policy as stored.
+    pub async fn alter_table_policy(
+        &self,
+        table: &str,
+        kind: PolicyKind,
+        policy: &Value,
+    ) -> Result<PolicyInfo> {
+        let table = entity_name(table)?;
+        let policy = kql::string(&policy.to_string());
+        self.execute_one(format!(".alter table {table} policy {kind} {policy}"))
+            .await
+    }
+}
+
+/// The table holding the result of a command, which is the first primary result of the response.
+fn command_result(response: KustoResponseDataSetV1) -> Result<TableV1> {
+    response
+        .into_primary_results()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| Error::ConversionError("management command returned no tables".to_string()))
+}
+
+/// Renders the `.create-merge table` command creating a table with the given columns, or adding the missing columns to it.
+///
+/// Fails if the name of the table or of a column is not a valid entity name.
+pub fn create_merge_table_command(table: &str, columns: &[Column]) -> Result<Kql> {
+    let columns = columns
+        .iter()
+        .map(|c| {
+            Ok(format!(
+                "{}:{}",
+                entity_name(&c.column_name)?,
+                c.column_type
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Kql::raw(format!(
+        ".create-merge table {} ({})",
+        entity_name(table)?,
+        columns.join(", ")
+    )))
+}
+
+/// Renders the `.create-or-alter table T ingestion kind mapping` command creating or replacing an ingestion mapping.
+///
+/// Fails if the name of the table is not a valid entity name.
+pub fn create_ingestion_mapping_command(
+    table: &str,
+    name: &str,
+    kind: IngestionMappingKind,
+    mappings: &[ColumnMapping],
+) -> Result<Kql> {
+    Ok(Kql::raw(format!(
+        ".create-or-alter table {} ingestion {kind} mapping {} {}",
+        entity_name(table)?,
+        kql::string(name),
+        kql::string(&serde_json::to_string(mappings)?)
+    )))
+}
+
+/// An ingestion mapping, as returned by `.create-or-alter table ingestion mapping`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct IngestionMappingInfo {
+    /// Name of the mapping.
+    pub name: String,
+    /// Kind of the mapping, e.g. `Json`.
+    pub kind: String,
+    /// The mapping, as a JSON array of column mappings.
+    pub mapping: String,
+}
+
+impl IngestionMappingInfo {
+    /// Parses the column mappings.
+    pub fn column_mappings(&self) -> Result<Vec<ColumnMapping>> {
+        Ok(serde_json::from_str(&self.mapping)?)
+    }
+}
+
+/// A table, as listed by `.show tables` or `.drop table`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TableInfo {
+    /// Name of the table.
+    pub table_name: String,
+    /// Name of the database of the table.
+    pub database_name: String,
+    /// Folder of the table, empty if none.
+    #[serde(default)]
+    pub folder: String,
+    /// Description of the table, empty if none.
+    #[serde(default)]
+    pub doc_string: String,
+}
+
+/// The schema of a table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TableSchema {
+    /// Name of the table.
+    pub table_name: String,
+    /// Name of the database of the table.
+    pub database_name: String,
+    /// Folder of the table, empty if none.
+    pub folder: String,
+    /// Description of the table, empty if none.
+    pub doc_string: String,
+    /// Columns of the table, in order.
+    pub columns: Vec<Column>,
+}
+
+/// A row of `.show table T schema` or `.create-merge table`, whose schema is either JSON or CSL (`a:string, b:long`).
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct SchemaRow {
+    table_name: String,
+    schema: String,
+    database_name: String,
+    #[serde(default)]
+    folder: String,
+    #[serde(default)]
+    doc_string: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct JsonSchema {
+    ordered_columns: Vec<JsonColumn>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct JsonColumn {
+    name: String,
+    csl_type: ColumnType,
+}
+
+impl TryFrom<SchemaRow> for TableSchema {
+    type Error = Error;
+
+    fn try_from(row: SchemaRow) -> Result<Self> {
+        let columns = if row.schema.trim_start().starts_with('{') {
+            serde_json::from_str::<JsonSchema>(&row.schema)?
+                .ordered_columns
+                .into_iter()
+                .map(|c| Column {
+                    column_name: c.name,
+                    column_type: c.csl_type,
+                })
+                .collect()
+        } else {
+            parse_csl_schema(&row.schema)?
+        };
+        Ok(Self {
+            table_name: row.table_name,
+            database_name: row.database_name,
+            folder: row.folder,
+            doc_string: row.doc_string,
+            columns,
+        })
+    }
+}
+
+/// Parses a schema in CSL format, e.g. `a:string, ['b c']:long`.
+fn parse_csl_schema(schema: &str) -> Result<Vec<Column>> {
+    let invalid = || Error::ConversionError(format!("table schema {schema}"));
+    let mut columns = Vec::new();
+    let mut rest = schema.trim();
+    while !rest.is_empty() {
+        let (name, after_name) = match rest.strip_prefix("['") {
+            Some(quoted) => {
+                let mut name = String::new();
+                let mut chars = quoted.char_indices();
+                let end = loop {
+                    match chars.next().ok_or_else(invalid)? {
+                        (_, '\\') => name.push(chars.next().ok_or_else(invalid)?.1),
+                        (i, '\'') => break i,
+                        (_, c) => name.push(c),
+                    }
+                };
+                let after_name = quoted[end + 1..].strip_prefix(']').ok_or_else(invalid)?;
+                (name, after_name)
+            }
+            None => {
+                let end = rest.find(':').ok_or_else(invalid)?;
+                (rest[..end].trim().to_string(), &rest[end..])
+            }
+        };
+        let after_colon = after_name
+            .trim_start()
+            .strip_prefix(':')
+            .ok_or_else(invalid)?;
+        let (column_type, after_type) = after_colon.split_once(',').unwrap_or((after_colon, ""));
+        columns.push(Column {
+            column_name: name,
+            column_type: column_type.trim().parse()?,
+        });
+        rest = after_type.trim();
+    }
+    Ok(columns)
+}
+
+/// The schema of a database, as returned by [Management::show_database_schema]. Entities are keyed by name.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct DatabaseSchema {
+    /// Name of the database.
+    pub name: String,
+    /// Tables of the database.
+    #[serde(default)]
+    pub tables: BTreeMap<String, TableDefinition>,
+    /// External tables of the database.
+    #[serde(default)]
+    pub external_tables: BTreeMap<String, TableDefinition>,
+    /// Materialized views of the database.
+    #[serde(default)]
+    pub materialized_views: BTreeMap<String, MaterializedViewDefinition>,
+    /// Stored functions of the database.
+    #[serde(default)]
+    pub functions: BTreeMap<String, StoredFunction>,
+    /// Access mode of the database, e.g. `ReadWrite`.
+    #[serde(default)]
+    pub database_access_mode: Option<String>,
+}
+
+/// A table or external table of a [DatabaseSchema].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TableDefinition {
+    /// Name of the table.
+    pub name: String,
+    /// Columns of the table, in order.
+    #[serde(rename = "OrderedColumns", default)]
+    pub columns: Vec<ColumnDefinition>,
+    /// Folder of the table.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Description of the table.
+    #[serde(default)]
+    pub doc_string: Option<String>,
+}
+
+/// A materialized view of a [DatabaseSchema].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct MaterializedViewDefinition {
+    /// Name of the view.
+    pub name: String,
+    /// Name of the table the view is computed from.
+    #[serde(default)]
+    pub source: String,
+    /// Query computing the view.
+    #[serde(default)]
+    pub query: String,
+    /// Columns of the view, in order.
+    #[serde(rename = "OrderedColumns", default)]
+    pub columns: Vec<ColumnDefinition>,
+    /// Folder of the view.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Description of the view.
+    #[serde(default)]
+    pub doc_string: Option<String>,
+}
+
+/// A column of a [TableDefinition] or [MaterializedViewDefinition], or of a tabular function parameter.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct ColumnDefinition {
+    /// Name of the column.
+    pub name: String,
+    /// Type of the column.
+    pub csl_type: ColumnType,
+    /// Description of the column.
+    #[serde(default)]
+    pub doc_string: Option<String>,
+}
+
+impl From<ColumnDefinition> for Column {
+    fn from(column: ColumnDefinition) -> Self {
+        Self {
+            column_name: column.name,
+            column_type: column.csl_type,
+        }
+    }
+}
+
+/// A stored function of a [DatabaseSchema].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct StoredFunction {
+    /// Name of the function.
+    pub name: String,
+    /// Parameters of the function, in order.
+    #[serde(rename = "InputParameters", default)]
+    pub parameters: Vec<FunctionParameter>,
+    /// Body of the function, including the braces.
+    pub body: String,
+    /// Folder of the function.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Description of the function.
+    #[serde(default)]
+    pub doc_string: Option<String>,
+}
+
+/// A parameter of a [StoredFunction], either scalar (with a type) or tabular (with columns).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct FunctionParameter {
+    /// Name of the parameter.
+    pub name: String,
+    /// Type of a scalar parameter.
+    #[serde(default)]
+    pub csl_type: Option<ColumnType>,
+    /// Columns of a tabular parameter, empty for tabular parameters accepting any columns.
+    #[serde(default)]
+    pub columns: Vec<ColumnDefinition>,
+    /// Default value of an optional scalar parameter, as a KQL literal.
+    #[serde(default)]
+    pub csl_default_value: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DatabaseSchemaRow {
+    database_schema: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ClusterSchema {
+    databases: BTreeMap<String, DatabaseSchema>,
+}
+
+/// Parses the JSON schema of a cluster, returning the schema of the given database.
+fn parse_database_schema(json: &str, database: &str) -> Result<DatabaseSchema> {
+    let mut databases = serde_json::from_str::<ClusterSchema>(json)?.databases;
+    let name = databases
+        .keys()
+        .find(|name| name.eq_ignore_ascii_case(database))
+        .cloned()
+        .ok_or_else(|| Error::ConversionError(format!("schema of database {database}")))?;
+    Ok(databases
+        .remove(&name)
+        .expect("the name is a key of the map"))
+}
+
+/// A database, as listed by `.show databases`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct DatabaseInfo {
+    /// Name of the database.
+    pub database_name: String,
+    /// Pretty name of the database, if set.
+    #[serde(default)]
+    pub pretty_name: Option<String>,
+    /// Storage location of the database.
+    #[serde(default)]
+    pub persistent_storage: String,
+    /// Version of the database.
+    #[serde(default)]
+    pub version: String,
+    /// Whether the database is the one the command was run against.
+    #[serde(default)]
+    pub is_current: bool,
+    /// Access mode of the database, e.g. `ReadWrite`.
+    #[serde(default)]
+    pub database_access_mode: String,
+    /// Unique id of the database.
+    #[serde(default)]
+    pub database_id: Option<String>,
+}
+
+/// A stored function, as listed by `.show functions`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct FunctionInfo {
+    /// Name of the function.
+    pub name: String,
+    /// Parameters of the function, in CSL format, e.g. `(a:string, b:long)`.
+    pub parameters: String,
+    /// Body of the function, including the braces.
+    pub body: String,
+    /// Folder of the function, empty if none.
+    #[serde(default)]
+    pub folder: String,
+    /// Description of the function, empty if none.
+    #[serde(default)]
+    pub doc_string: String,
+}
+
+/// The definition of a stored function, for [Management::create_or_alter_function].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionDefinition {
+    /// Name of the function.
+    pub name: String,
+    /// Scalar parameters of the function, in order.
+    pub parameters: Vec<(String, ColumnType)>,
+    /// Body of the function, without the braces.
+    pub body: String,
+    /// Folder of the function.
+    pub folder: Option<String>,
+    /// Description of the function.
+    pub doc_string: Option<String>,
+}
+
+impl FunctionDefinition {
+    /// Creates the definition of a function without parameters.
+    pub fn new(name: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            parameters: Vec::new(),
+            body: body.into(),
+            folder: None,
+            doc_string: None,
+        }
+    }
+
+    /// Adds a parameter to the function.
+    #[must_use]
+    pub fn with_parameter(mut self, name: impl Into<String>, column_type: ColumnType) -> Self {
+        self.parameters.push((name.into(), column_type));
+        self
+    }
+
+    /// Sets the folder of the function.
+    #[must_use]
+    pub fn with_folder(mut self, folder: impl Into<String>) -> Self {
+        self.folder = Some(folder.into());
+        self
+    }
+
+    /// Sets the description of the function.
+    #[must_use]
+    pub fn with_doc_string(mut self, doc_string: impl Into<String>) -> Self {
+        self.doc_string = Some(doc_string.into());
+        self
+    }
+
+    fn command(&self) -> Result<String> {
+        let properties = [("folder", &self.folder), ("docstring", &self.doc_string)]
+            .into_iter()
+            .filter_map(|(name, value)| {
+                value
+                    .as_ref()
+                    .map(|value| format!("{name}={}", kql::string(value)))
+            })
+            .collect::<Vec<_>>();
+        let with = if properties.is_empty() {
+            String::new()
+        } else {
+            format!("with ({}) ", properties.join(", "))
+        };
+        let parameters = self
+            .parameters
+            .iter()
+            .map(|(name, column_type)| Ok(format!("{}:{column_type}", entity_name(name)?)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(format!(
+            ".create-or-alter function {with}{}({}) {{\n{}\n}}",
+            entity_name(&self.name)?,
+            parameters.join(", "),
+            self.body
+        ))
+    }
+}
+
+/// The kinds of table policies that are set with a JSON document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyKind {
+    /// How long data is kept.
+    Retention,
+    /// How ingested data is batched.
+    IngestionBatching,
+    /// How extents are partitioned.
+    Partitioning,
+    /// How extents are merged.
+    Merge,
+    /// Update policies, ingesting into the table the results of queries over other tables.
+    Update,
+    /// Whether streaming ingestion is enabled.
+    StreamingIngestion,
+}
+
+impl Display for PolicyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Retention => "retention",
+            Self::IngestionBatching => "ingestionbatching",
+            Self::Partitioning => "partitioning",
+            Self::Merge => "merge",
+            Self::Update => "update",
+            Self::StreamingIngestion => "streamingingestion",
+        })
+    }
+}
+
+/// A policy, as returned by `.alter table policy`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct PolicyInfo {
+    /// Name of the policy, e.g. `RetentionPolicy`.
+    pub policy_name: String,
+    /// Name of the entity the policy applies to, e.g. `[db].[table]`.
+    pub entity_name: String,
+    /// The policy, as a JSON document.
+    pub policy: String,
+    /// Entities the policy also applies to.
+    #[serde(default)]
+    pub child_entities: Value,
+    /// Kind of the entity the policy applies to, e.g. `Table`.
+    #[serde(default)]
+    pub entity_type: String,
+}
+
+impl PolicyInfo {
+    /// Parses the policy document.
+    pub fn parsed_policy(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&self.policy)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, column_type: ColumnType) -> Column {
+        Column {
+            column_name: name.to_string(),
+            column_type,
+        }
+    }
+
+    #[test]
+    fn parses_schemas() {
+        let table: TableV1 = serde_json::from_str(
+            r#"{
+            "TableName": "Table_0",
+            "Columns": [
+                {"ColumnName": "TableName", "DataType": "String"},
+                {"ColumnName": "Schema", "DataType": "String"},
+                {"ColumnName": "DatabaseName", "DataType": "String"},
+                {"ColumnName": "Folder", "DataType": "String"},
+                {"ColumnName": "DocString", "DataType": "String"}
+            ],
+            "Rows": [
+                ["T", "{\"Name\":\"T\",\"OrderedColumns\":[{\"Name\":\"a b\",\"Type\":\"System.Int64\",\"CslType\":\"long\"}]}", "db", "", ""],
+                ["T", "x:string, ['y \\'z\\'']:datetime,w:dynamic", "db", "f", "d"]
+            ]
+        }"#,
+        )
+        .expect("Failed to parse table");
+
+        let schemas = table
+            .into_deserialized_rows::<SchemaRow>(RowDeserializerOptions::default())
+            .expect("Failed to deserialize rows")
+            .into_iter()
+            .map(TableSchema::try_from)
+            .collect::<Result<Vec<_>>>()
+            .expect("Failed to parse schemas");
+
+        assert_eq!(schemas[0].columns, vec![column("a b", ColumnType::Long)]);
+        assert_eq!(
+            schemas[1].columns,
+            vec![
+                column("x", ColumnType::String),
+                column("y 'z'", ColumnType::Datetime),
+                column("w", ColumnType::Dynamic),
+            ]
+        );
+        assert_eq!(schemas[1].folder, "f");
+        assert!(parse_csl_schema("x:string, y").is_err());
+        assert!(parse_csl_schema("x:nothing").is_err());
+    }
+
+    #[test]
+    fn renders_commands() {
+        assert_eq!(
+            create_merge_table_command(
+                "My Table",
+                &[
+                    column("a", ColumnType::String),
+                    column("b c", ColumnType::Timespan)
+                ]
+            )
+            .expect("Failed to render command")
+            .as_str(),
+            ".create-merge table ['My Table'] (a:string, ['b c']:timespan)"
+        );
+        assert!(create_merge_table_command("T']", &[]).is_err());
+
+        assert_eq!(
+            create_ingestion_mapping_command(
+                "T",
+                "m\"",
+                IngestionMappingKind::Json,
+                &[ColumnMapping::with_path("a", "$.a")]
+            )
+            .expect("Failed to render command")
+            .as_str(),
+            r#".create-or-alter table T ingestion json mapping "m\"" "[{\"column\":\"a\",\"Properties\":{\"Path\":\"$.a\"}}]""#
+        );
+
+        let function = FunctionDefinition::new("F", "T | where x > n")
+            .with_parameter("n", ColumnType::Long)
+            .with_doc_string("Doc \"quoted\"");
+        assert_eq!(
+            function.command().expect("Failed to render command"),
+            ".create-or-alter function with (docstring=\"Doc \\\"quoted\\\"\") F(n:long) {\nT | where x > n\n}"
+        );
+    }
+
+    #[test]
+    fn parses_database_schema() {
+        let json = r#"{
+            "Plugins": [],
+            "Databases": {
+                "Db": {
+                    "Name": "Db",
+                    "Tables": {
+                        "T": {
+                            "Name": "T",
+                            "EntityType": "Table",
+                            "OrderedColumns": [
+                                {"Name": "a", "Type": "System.String", "CslType": "string", "DocString": "The a"},
+                                {"Name": "b", "Type": "System.Int64", "CslType": "long"}
+                            ],
+                            "Folder": "f",
+                            "DocString": null
+                        }
+                    },
+                    "ExternalTables": {},
+                    "MaterializedViews": {
+                        "V": {
+                            "Name": "V",
+                            "Source": "T",
+                            "Query": "T | summarize count() by a",
+                            "OrderedColumns": [{"Name": "a", "Type": "System.String", "CslType": "string"}]
+                        }
+                    },
+                    "Functions": {
+                        "F": {
+                            "Name": "F",
+                            "InputParameters": [
+                                {"Name": "n", "Type": "System.Int64", "CslType": "long", "CslDefaultValue": "5"},
+                                {"Name": "t", "Columns": [{"Name": "x", "Type": "System.Double", "CslType": "real"}]}
+                            ],
+                            "Body": "{ t | take n }",
+                            "Folder": null,
+                            "DocString": "Takes n",
+                            "FunctionKind": "Unknown",
+                            "OutputColumns": []
+                        }
+                    },
+                    "EntityGroups": {},
+                    "MajorVersion": 12,
+                    "MinorVersion": 1,
+                    "DatabaseAccessMode": "ReadWrite"
+                }
+            }
+        }"#;
+
+        let schema = parse_database_schema(json, "db").expect("Failed to parse schema");
+        let table = &schema.tables["T"];
+        assert_eq!(table.folder.as_deref(), Some("f"));
+        assert_eq!(table.columns[0].doc_string.as_deref(), Some("The a"));
+        assert_eq!(
+            table
+                .columns
+                .iter()
+                .cloned()
+                .map(Column::from)
+                .collect::<Vec<_>>(),
+            vec![
+                column("a", ColumnType::String),
+                column("b", ColumnType::Long)
+            ]
+        );
+        assert_eq!(schema.materialized_views["V"].source, "T");
+
+        let function = &schema.functions["F"];
+        assert_eq!(function.parameters[0].csl_type, Some(ColumnType::Long));
+        assert_eq!(
+            function.parameters[0].csl_default_value.as_deref(),
+            Some("5")
+        );
+        assert_eq!(function.parameters[1].csl_type, None);
+        assert_eq!(function.parameters[1].columns[0].csl_type, ColumnType::Real);
+        assert_eq!(schema.database_access_mode.as_deref(), Some("ReadWrite"));
+
+        assert!(parse_database_schema(json, "other").is_err());
+    }
+}
@@ -1,6 +1,14 @@
 //! Models to parse responses from ADX.
+use crate::error::{Error, InvalidArgumentError, Result};
+use crate::kusto_row::{KustoRow, RowDecoder};
 use crate::prelude::ClientRequestProperties;
+use crate::row_deserializer::{from_rows, RowDeserializerOptions};
+use crate::types::{FromKustoValue, KustoDateTime, KustoValue};
+use serde::de::DeserializeOwned;
+use serde::de::IntoDeserializer;
 use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(non_snake_case)]
@@ -17,7 +25,7 @@ pub(crate) struct QueryBody {
 /// Represents the scalar data types of ADX. see [the docs for more information](https://docs.microsoft.com/en-us/azure/data-explorer/kusto/query/scalar-data-types/)
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize, Clone)]
 pub enum ColumnType {
-    #[serde(alias = "Boolean", alias = "bool", alias = "boolean")]
+    #[serde(alias = "Boolean", alias = "bool", alias = "boolean", alias = "SByte")]
     /// Boolean type, true or false. Internally is a u8.
     Bool,
     /// Datetime, represents a specific point in time.
@@ -57,11 +65,40 @@ pub enum ColumnType {
     /// Timespan type, represents a duration of time.
     #[serde(alias = "TimeSpan", alias = "timespan", alias = "Time", alias = "time")]
     Timespan,
-    #[serde(alias = "decimal")]
+    #[serde(alias = "decimal", alias = "SqlDecimal")]
     /// Decimal, represents a fixed-point number with a defined precision and scale.
     Decimal,
 }
 
+impl Display for ColumnType {
+    /// Formats the type as it is named in KQL, e.g. in `declare query_parameters` or `.create table`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Bool => "bool",
+            Self::Datetime => "datetime",
+            Self::Dynamic => "dynamic",
+            Self::Guid => "guid",
+            Self::Int => "int",
+            Self::Long => "long",
+            Self::Real => "real",
+            Self::String => "string",
+            Self::Timespan => "timespan",
+            Self::Decimal => "decimal",
+        })
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = Error;
+
+    /// Parses the name of a type, accepting the same names as the service, e.g. `long`, `Int64` or `datetime`.
+    fn from_str(s: &str) -> Result<Self> {
+        Self::deserialize(s.into_deserializer()).map_err(|e: serde::de::value::Error| {
+            Error::ConversionError(format!("column type {s}: {e}"))
+        })
+    }
+}
+
 /// Represents a column in ADX, for a V1 (usually management) query.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "PascalCase")]
@@ -74,6 +111,109 @@ pub struct ColumnV1 {
     pub data_type: Option<ColumnType>,
 }
 
+/// The name and type of a column of a table, as needed by [RowView].
+pub trait TableColumn {
+    /// Name of the column.
+    fn name(&self) -> &str;
+    /// Type of the values of the column.
+    fn kusto_type(&self) -> ColumnType;
+}
+
+impl TableColumn for Column {
+    fn name(&self) -> &str {
+        &self.column_name
+    }
+
+    fn kusto_type(&self) -> ColumnType {
+        self.column_type.clone()
+    }
+}
+
+impl TableColumn for ColumnV1 {
+    fn name(&self) -> &str {
+        &self.column_name
+    }
+
+    /// V1 columns may have only one of `ColumnType` and `DataType`, and values of columns with neither are treated as dynamic.
+    fn kusto_type(&self) -> ColumnType {
+        self.column_type
+            .clone()
+            .or_else(|| self.data_type.clone())
+            .unwrap_or(ColumnType::Dynamic)
+    }
+}
+
+/// A view of a row of a table, with typed accessors for its values.
+#[derive(Debug, Clone, Copy)]
+pub struct RowView<'a, C = Column> {
+    columns: &'a [C],
+    values: &'a [serde_json::Value],
+}
+
+impl<'a, C: TableColumn> RowView<'a, C> {
+    /// Creates a view of the values of a row, in the order of the columns.
+    #[must_use]
+    pub fn new(columns: &'a [C], values: &'a [serde_json::Value]) -> Self {
+        Self { columns, values }
+    }
+
+    /// The columns of the table the row belongs to.
+    #[must_use]
+    pub fn columns(&self) -> &'a [C] {
+        self.columns
+    }
+
+    /// The raw values of the row, in the order of the columns.
+    #[must_use]
+    pub fn values(&self) -> &'a [serde_json::Value] {
+        self.values
+    }
+
+    /// Returns the value at the given column index, typed according to its column.
+    pub fn value_at(&self, index: usize) -> Result<KustoValue> {
+        match (self.columns.get(index), self.values.get(index)) {
+            (Some(column), Some(value)) => {
+                KustoValue::from_json(value.clone(), &column.kusto_type())
+            }
+            _ => Err(Error::ConversionError(format!(
+                "row has no value for column {index}"
+            ))),
+        }
+    }
+
+    /// Returns the value of the column with the given name, typed according to the column.
+    pub fn value(&self, column_name: &str) -> Result<KustoValue> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c.name() == column_name)
+            .ok_or_else(|| InvalidArgumentError::UnknownColumn(column_name.to_string()))?;
+        self.value_at(index)
+    }
+
+    /// Returns the value of the column with the given name, converted into `T`.
+    /// Use an `Option` for columns that may hold nulls.
+    pub fn get<T: FromKustoValue>(&self, column_name: &str) -> Result<T> {
+        T::from_kusto_value(self.value(column_name)?)
+    }
+
+    /// Returns the value at the given column index, converted into `T`.
+    pub fn get_at<T: FromKustoValue>(&self, index: usize) -> Result<T> {
+        T::from_kusto_value(self.value_at(index)?)
+    }
+}
+
+impl From<ColumnV1> for Column {
+    /// Converts a V1 column, using the same type fallback as [RowView].
+    fn from(column: ColumnV1) -> Self {
+        let column_type = column.kusto_type();
+        Self {
+            column_name: column.column_name,
+            column_type,
+        }
+    }
+}
+
 /// Represents a table in ADX, for a V1 (usually management) query.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "PascalCase")]
@@ -86,6 +226,101 @@ pub struct TableV1 {
     pub rows: Vec<Vec<serde_json::Value>>,
 }
 
+impl TableV1 {
+    /// Returns the index of the column with the given name.
+    #[must_use]
+    pub fn column_index(&self, column_name: &str) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.column_name == column_name)
+    }
+
+    /// Returns a view of the row at the given index.
+    pub fn row(&self, index: usize) -> Result<RowView<'_, ColumnV1>> {
+        self.rows
+            .get(index)
+            .map(|values| RowView::new(&self.columns, values))
+            .ok_or_else(|| InvalidArgumentError::RowOutOfRange(index).into())
+    }
+
+    /// Iterates over views of the rows of the table.
+    pub fn row_views(&self) -> impl Iterator<Item = RowView<'_, ColumnV1>> {
+        self.rows
+            .iter()
+            .map(|values| RowView::new(&self.columns, values))
+    }
+
+    /// Returns the value of a column in the row at the given index, converted into `T`. See [RowView::get].
+    pub fn get<T: FromKustoValue>(&self, row: usize, column_name: &str) -> Result<T> {
+        self.row(row)?.get(column_name)
+    }
+
+    /// Decodes the rows of the table into `T`, after checking the columns against its schema. See [RowDecoder].
+    pub fn decode_rows<T: KustoRow>(&self) -> Result<Vec<T>> {
+        let decoder = RowDecoder::new(&self.columns)?;
+        self.rows.iter().map(|row| decoder.decode(row)).collect()
+    }
+
+    /// Deserializes the rows of the table, matching the fields of `T` to the columns by name. See [RowDeserializer](crate::row_deserializer::RowDeserializer).
+    pub fn deserialize_rows<T: DeserializeOwned>(
+        &self,
+        options: RowDeserializerOptions,
+    ) -> Result<Vec<T>> {
+        from_rows(
+            self.columns.iter().map(|c| c.column_name.as_str()),
+            self.rows.iter().map(Ok),
+            options,
+        )
+    }
+
+    /// Consumes the table, deserializing its rows. See [deserialize_rows](Self::deserialize_rows).
+    pub fn into_deserialized_rows<T: DeserializeOwned>(
+        self,
+        options: RowDeserializerOptions,
+    ) -> Result<Vec<T>> {
+        from_rows(
+            self.columns.iter().map(|c| c.column_name.as_str()),
+            self.rows.into_iter().map(Ok),
+            options,
+        )
+    }
+}
+
+/// The kind of a table of a V1 query response, as listed in its table of contents.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TableKindV1 {
+    /// The table contains the actual data returned by the query.
+    QueryResult,
+    /// Properties of the query, such as its visualization.
+    QueryProperties,
+    /// Status and statistics of the query.
+    QueryStatus,
+    /// Unknown table kind.
+    #[serde(other)]
+    Unknown,
+}
+
+/// A row of the table of contents of a V1 query response, describing one of the other tables.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct TableOfContentsEntry {
+    /// Index of the described table in the response.
+    pub ordinal: i64,
+    /// Kind of the described table.
+    pub kind: TableKindV1,
+    /// Name of the described table, e.g. `PrimaryResult` or `@ExtendedProperties`.
+    pub name: String,
+    /// Id of the described table.
+    pub id: String,
+    /// Display name of the described table.
+    pub pretty_name: String,
+}
+
+impl TableOfContentsEntry {
+    /// The names of the columns of the table of contents, which is how it is recognized.
+    pub(crate) const COLUMNS: [&'static str; 5] = ["Ordinal", "Kind", "Name", "Id", "PrettyName"];
+}
+
 /// The header of the V2 query response.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "PascalCase")]
@@ -135,6 +370,83 @@ pub struct DataTable {
     pub rows: Vec<serde_json::Value>,
 }
 
+impl DataTable {
+    /// Returns a view of the row at the given index.
+    pub fn row(&self, index: usize) -> Result<RowView<'_>> {
+        match self.rows.get(index) {
+            Some(serde_json::Value::Array(values)) => Ok(RowView::new(&self.columns, values)),
+            Some(_) => Err(Error::ConversionError("row is not an array".to_string())),
+            None => Err(InvalidArgumentError::RowOutOfRange(index).into()),
+        }
+    }
+
+    /// Iterates over views of the rows of the table.
+    pub fn row_views(&self) -> impl Iterator<Item = Result<RowView<'_>>> {
+        (0..self.rows.len()).map(|i| self.row(i))
+    }
+
+    /// Returns the value of a column in the row at the given index, converted into `T`. See [RowView::get].
+    ///
+    /// # Example
+    /// ```
+    /// use azure_kusto_data::models::DataTable;
+    /// use azure_kusto_data::types::KustoDuration;
+    ///
+    /// let table: DataTable = serde_json::from_str(r#"{
+    ///     "TableId": 1, "TableName": "t", "TableKind": "PrimaryResult",
+    ///     "Columns": [{"ColumnName": "name", "ColumnType": "string"}, {"ColumnName": "took", "ColumnType": "timespan"}],
+    ///     "Rows": [["a", "00:00:01"], [null, null]]
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(table.get::<String>(0, "name").unwrap(), "a");
+    /// assert_eq!(table.get::<KustoDuration>(0, "took").unwrap().whole_seconds(), 1);
+    /// assert_eq!(table.get::<Option<String>>(1, "name").unwrap(), None);
+    /// ```
+    pub fn get<T: FromKustoValue>(&self, row: usize, column_name: &str) -> Result<T> {
+        self.row(row)?.get(column_name)
+    }
+
+    /// Decodes the rows of the table into `T`, after checking the columns against its schema. See [RowDecoder].
+    pub fn decode_rows<T: KustoRow>(&self) -> Result<Vec<T>> {
+        let decoder = RowDecoder::new(&self.columns)?;
+        self.row_views()
+            .map(|row| decoder.decode(row?.values()))
+            .collect()
+    }
+
+    /// Deserializes the rows of the table, matching the fields of `T` to the columns by name. See [RowDeserializer](crate::row_deserializer::RowDeserializer).
+    pub fn deserialize_rows<T: DeserializeOwned>(
+        &self,
+        options: RowDeserializerOptions,
+    ) -> Result<Vec<T>> {
+        let rows = self.rows.iter().map(|row| match row {
+            serde_json::Value::Array(values) => Ok(values),
+            _ => Err(Error::ConversionError("row is not an array".to_string())),
+        });
+        from_rows(
+            self.columns.iter().map(|c| c.column_name.as_str()),
+            rows,
+            options,
+        )
+    }
+
+    /// Consumes the table, deserializing its rows. See [deserialize_rows](Self::deserialize_rows).
+    pub fn into_deserialized_rows<T: DeserializeOwned>(
+        self,
+        options: RowDeserializerOptions,
+    ) -> Result<Vec<T>> {
+        let rows = self.rows.into_iter().map(|row| match row {
+            serde_json::Value::Array(values) => Ok(values),
+            _ => Err(Error::ConversionError("row is not an array".to_string())),
+        });
+        from_rows(
+            self.columns.iter().map(|c| c.column_name.as_str()),
+            rows,
+            options,
+        )
+    }
+}
+
 /// A header of a fragment of a table (in progressive mode).
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(rename_all = "PascalCase")]
@@ -230,4 +542,107 @@ pub struct DataSetCompletion {
     pub has_errors: bool,
     /// Was the query cancelled.
     pub cancelled: bool,
+    /// The errors the query encountered, if any.
+    #[serde(rename = "OneApiErrors", skip_serializing_if = "Option::is_none")]
+    pub one_api_errors: Option<Vec<OneApiError>>,
+}
+
+/// A row of the `QueryCompletionInformation` table, describing an event that occurred while running the query.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "PascalCase")]
+pub struct QueryCompletionInformation {
+    /// Time of the event.
+    pub timestamp: KustoDateTime,
+    /// The client request id of the query.
+    pub client_request_id: String,
+    /// The id the service assigned to the query.
+    pub activity_id: String,
+    /// Id of the sub activity that reported the event.
+    pub sub_activity_id: String,
+    /// Id of the parent activity.
+    pub parent_activity_id: String,
+    /// Severity of the event, where 1 is critical and 2 is error.
+    pub level: i32,
+    /// Name of the severity of the event, for example `Error` or `Info`.
+    pub level_name: String,
+    /// Status code of the event.
+    pub status_code: i32,
+    /// Name of the status code of the event.
+    pub status_code_name: String,
+    /// Type of the event.
+    pub event_type: i32,
+    /// Name of the type of the event, for example `QueryInfo` or `QueryResourceConsumption`.
+    pub event_type_name: String,
+    /// JSON payload of the event.
+    pub payload: String,
+}
+
+impl QueryCompletionInformation {
+    /// Returns the error described by this event, if the event is an error.
+    #[must_use]
+    pub fn one_api_error(&self) -> Option<OneApiError> {
+        if self.level > 2 {
+            return None;
+        }
+        serde_json::from_str(&self.payload).ok()
+    }
+}
+
+/// An error in the `OneApiError` format, as returned by Kusto in failed responses and in V2 datasets.
+/// See [the docs for more information](https://learn.microsoft.com/en-us/azure/data-explorer/kusto/api/rest/response#error-messages).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct OneApiError {
+    /// The details of the error.
+    pub error: OneApiErrorMessage,
+}
+
+/// The details of a [OneApiError].
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct OneApiErrorMessage {
+    /// Error code, for example `General_BadRequest` or `LimitsExceeded`.
+    pub code: String,
+    /// Short, human readable error message.
+    pub message: String,
+    /// The full name of the exception type raised by the service.
+    #[serde(rename = "@type", skip_serializing_if = "Option::is_none")]
+    pub error_type: Option<String>,
+    /// Detailed description of the error.
+    #[serde(rename = "@message", skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Context in which the error occurred.
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    pub context: Option<OneApiErrorContext>,
+    /// Whether the error is permanent, i.e. retrying the request will fail again.
+    #[serde(rename = "@permanent", skip_serializing_if = "Option::is_none")]
+    pub is_permanent: Option<bool>,
+}
+
+/// The context of a [OneApiErrorMessage], identifying where in the service the error occurred.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OneApiErrorContext {
+    /// Time at which the error occurred.
+    pub timestamp: Option<String>,
+    /// Alias of the service that raised the error.
+    pub service_alias: Option<String>,
+    /// Name of the machine that raised the error.
+    pub machine_name: Option<String>,
+    /// Name of the process that raised the error.
+    pub process_name: Option<String>,
+    /// Id of the process that raised the error.
+    pub process_id: Option<i64>,
+    /// Id of the thread that raised the error.
+    pub thread_id: Option<i64>,
+    /// The client request id of the failed request.
+    pub client_request_id: Option<String>,
+    /// The id the service assigned to the failed request.
+    pub activity_id: Option<String>,
+    /// Id of the sub activity that raised the error.
+    pub sub_activity_id: Option<String>,
+    /// Type of the activity that raised the error.
+    pub activity_type: Option<String>,
+    /// Id of the parent activity.
+    pub parent_activity_id: Option<String>,
+    /// Stack of activities leading to the error.
+    pub activity_stack: Option<String>,
 }
@@ -1,7 +1,6 @@
 use std::io;
 
 use futures::{stream, AsyncBufRead, AsyncBufReadExt, AsyncReadExt, Stream};
-use serde::de::DeserializeOwned;
 
 // TODO: Find a crate that does this better / move this into another crate
 
@@ -9,13 +8,14 @@ fn invalid_data(msg: &str) -> io::Error {
     io::Error::new(io::ErrorKind::InvalidData, msg)
 }
 
-async fn deserialize_single<T: DeserializeOwned>(
+async fn read_single(
     reader: &mut (impl AsyncBufRead + Send + Unpin),
     buf: &mut Vec<u8>,
-) -> io::Result<T> {
+) -> io::Result<()> {
     buf.clear();
     let size = reader.read_until(b'\n', buf).await?;
-    Ok(serde_json::from_slice(&buf[..size - 1])?)
+    buf.truncate(size.saturating_sub(1));
+    Ok(())
 }
 
 async fn read_byte(reader: &mut (impl AsyncBufRead + Send + Unpin)) -> io::Result<u8> {
@@ -24,11 +24,12 @@ async fn read_byte(reader: &mut (impl AsyncBufRead + Send + Unpin)) -> io::Resul
     Ok(buf[0])
 }
 
-async fn yield_next_obj<T: DeserializeOwned>(
+/// Reads the next object into `buf`, returning whether there was one.
+async fn read_next_obj(
     reader: &mut (impl AsyncBufRead + Send + Unpin),
     buf: &mut Vec<u8>,
-) -> Result<Option<T>, io::Error> {
-    Ok(Some(match read_byte(reader).await? {
+) -> Result<bool, io::Error> {
+    match read_byte(reader).await? {
         b'[' => {
             let newline = read_byte(reader).await?;
             if newline != b'\n' {
@@ -37,22 +38,23 @@ async fn yield_next_obj<T: DeserializeOwned>(
                     newline
                 )));
             }
-            deserialize_single(reader, buf).await?
+            read_single(reader, buf).await?;
         }
-        b',' => deserialize_single(reader, buf).await?,
-        b']' => return Ok(None),
+        b',' => read_single(reader, buf).await?,
+        b']' => return Ok(false),
         b => return Err(invalid_data(&format!("Unexpected byte {:?}", b))),
-    }))
+    }
+    Ok(true)
 }
 
-pub fn iter_results<T: DeserializeOwned>(
-    reader: (impl AsyncBufRead + Send + Unpin),
-) -> impl Stream<Item = Result<T, io::Error>> {
-    let buf = vec![];
-
-    stream::try_unfold((buf, reader), move |(mut buf, mut reader)| async {
-        yield_next_obj(&mut reader, &mut buf)
-            .await
-            .map(|r| r.map(|obj| (obj, (buf, reader))))
+/// Yields the raw JSON of each object of an array written with one object per line, to be decoded by the caller.
+pub fn iter_results(
+    reader: impl AsyncBufRead + Send + Unpin,
+) -> impl Stream<Item = Result<Vec<u8>, io::Error>> {
+    stream::try_unfold(reader, move |mut reader| async {
+        let mut buf = vec![];
+        Ok(read_next_obj(&mut reader, &mut buf)
+            .await?
+            .then_some((buf, reader)))
     })
 }
@@ -0,0 +1,150 @@
+use crate::client::KustoClient;
+use crate::error::{Error, Result};
+use crate::operations::query::{KustoResponseDataSetV1, RequestIds};
+use azure_core::sleep::sleep;
+use futures::future::{self, Either};
+use futures::Future;
+use std::time::Duration;
+
+/// Handle to cancel a running query, by issuing `.cancel query` for its client request id
+/// through the management endpoint of the client that started the query.
+#[derive(Debug, Clone)]
+pub struct CancellationHandle {
+    client: KustoClient,
+    database: String,
+    client_request_id: String,
+}
+
+impl CancellationHandle {
+    pub(crate) fn new(client: KustoClient, database: String, client_request_id: String) -> Self {
+        Self {
+            client,
+            database,
+            client_request_id,
+        }
+    }
+
+    /// The client request id of the query this handle cancels.
+    #[must_use]
+    pub fn client_request_id(&self) -> &str {
+        &self.client_request_id
+    }
+
+    /// Cancels the query. Cancelling a query that has already completed is not an error on the service side.
+    pub async fn cancel(&self) -> Result<KustoResponseDataSetV1> {
+        self.client
+            .execute_command(
+                self.database.clone(),
+                cancel_query_command(&self.client_request_id),
+                None,
+            )
+            .await
+    }
+}
+
+fn cancel_query_command(client_request_id: &str) -> String {
+    format!(
+        ".cancel query \"{}\"",
+        client_request_id.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+/// Cancels the query when dropped, unless disarmed first.
+///
+/// Dropping cannot wait for the cancellation, so it is spawned onto the current tokio runtime, if there is one.
+/// Without the `cancel_on_drop` feature, dropping does nothing.
+#[cfg_attr(not(feature = "cancel_on_drop"), allow(dead_code))]
+pub(crate) struct CancelOnDrop(Option<CancellationHandle>);
+
+impl CancelOnDrop {
+    pub(crate) fn new(handle: Option<CancellationHandle>) -> Self {
+        Self(handle)
+    }
+
+    /// Stops the query from being cancelled when dropped, once it has completed.
+    pub(crate) fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+#[cfg(feature = "cancel_on_drop")]
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let (Some(handle), Ok(runtime)) = (self.0.take(), tokio::runtime::Handle::try_current())
+        {
+            runtime.spawn(async move {
+                // Cancellation is best effort, there is no one left to report a failure to.
+                let _ = handle.cancel().await;
+            });
+        }
+    }
+}
+
+/// Runs the future to completion, or fails with [Error::TimeoutError] once the timeout elapses, cancelling the query if a handle is given.
+pub(crate) async fn with_timeout<T>(
+    future: impl Future<Output = Result<T>>,
+    timeout: Option<Duration>,
+    handle: Option<&CancellationHandle>,
+) -> Result<T> {
+    let Some(timeout) = timeout else {
+        return future.await;
+    };
+
+    match future::select(Box::pin(future), sleep(timeout)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => {
+            cancel_after_timeout(handle, timeout).await;
+            Err(Error::TimeoutError(timeout, RequestIds::default()))
+        }
+    }
+}
+
+/// Cancels the query once its timeout has elapsed, waiting at most for the same timeout again
+/// so that a hanging cancellation does not delay reporting the timeout.
+/// The timeout is reported even if the cancellation fails, so its result is ignored.
+pub(crate) async fn cancel_after_timeout(handle: Option<&CancellationHandle>, timeout: Duration) {
+    if let Some(handle) = handle {
+        let _ = future::select(Box::pin(handle.cancel()), sleep(timeout)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_query_command_escapes_id() {
+        assert_eq!(
+            cancel_query_command("KRS.execute;123"),
+            ".cancel query \"KRS.execute;123\""
+        );
+        assert_eq!(
+            cancel_query_command(r#"a"b\c"#),
+            r#".cancel query "a\"b\\c""#
+        );
+    }
+
+    #[tokio::test]
+    async fn with_timeout_fails_once_elapsed() {
+        let result = with_timeout(
+            future::pending::<Result<()>>(),
+            Some(Duration::from_millis(1)),
+            None,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::TimeoutError(timeout, _)) if timeout == Duration::from_millis(1)
+        ));
+    }
+
+    #[tokio::test]
+    async fn with_timeout_passes_results_through() {
+        let result = with_timeout(future::ready(Ok(42)), Some(Duration::from_secs(60)), None).await;
+        assert_eq!(result.ok(), Some(42));
+
+        let result = with_timeout(future::ready(Ok(42)), None, None).await;
+        assert_eq!(result.ok(), Some(42));
+    }
+}
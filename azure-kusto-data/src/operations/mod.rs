@@ -1,2 +1,4 @@
 mod async_deserializer;
+pub mod cancellation;
 pub mod query;
+pub mod rows;
@@ -1,24 +1,59 @@
 #[cfg(feature = "arrow")]
-use crate::arrow::convert_table;
+use crate::arrow::{convert_table, convert_table_v1};
 use crate::client::{KustoClient, QueryKind};
 
-use crate::error::{Error, Result};
-use crate::models::{DataTable, QueryBody, TableFragmentType, TableKind, TableV1, V2QueryResult};
+#[cfg(feature = "arrow")]
+use crate::arrow::{record_batch_stream, BatchMode, FramesDecoder};
+use crate::error::{Error, ProtocolError, Result};
+use crate::models::{
+    DataTable, OneApiError, QueryBody, QueryCompletionInformation, TableFragmentType, TableKind,
+    TableKindV1, TableOfContentsEntry, TableV1, V2QueryResult,
+};
 use crate::operations::async_deserializer;
+use crate::operations::cancellation::{
+    cancel_after_timeout, with_timeout, CancelOnDrop, CancellationHandle,
+};
+use crate::operations::rows::{primary_rows, Row};
 use crate::prelude::ClientRequestProperties;
+use crate::row_deserializer::RowDeserializerOptions;
+use crate::service_error_policy::ServiceErrorSlot;
+use crate::statistics::QueryStatistics;
 #[cfg(feature = "arrow")]
 use arrow_array::RecordBatch;
+#[cfg(feature = "arrow")]
+use arrow_schema::SchemaRef;
 use async_convert::TryFrom;
 use azure_core::error::Error as CoreError;
-use azure_core::headers::Headers;
+use azure_core::headers::{Headers, ACTIVITY_ID, CLIENT_REQUEST_ID};
 use azure_core::prelude::*;
+use azure_core::sleep::sleep;
 use azure_core::{CustomHeaders, Method, Request, Response as HttpResponse, Response};
-use futures::future::BoxFuture;
-use futures::{Stream, TryFutureExt, TryStreamExt};
+use futures::future::{self, BoxFuture, Either};
+use futures::stream::BoxStream;
+use futures::{stream, Stream, StreamExt, TryFutureExt, TryStreamExt};
+use hashbrown::HashMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::future::IntoFuture;
 use std::io::ErrorKind;
+#[cfg(feature = "arrow")]
+use std::num::NonZeroUsize;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::{Duration, Instant};
+
+/// Whether a query starts with a `declare query_parameters` statement.
+fn declares_parameters(query: &str) -> bool {
+    let mut words = query.split_whitespace();
+    words
+        .next()
+        .map_or(false, |w| w.eq_ignore_ascii_case("declare"))
+        && words.next().map_or(false, |w| {
+            w.get(..16)
+                .map_or(false, |w| w.eq_ignore_ascii_case("query_parameters"))
+        })
+}
 
 type QueryRun = BoxFuture<'static, Result<KustoResponse>>;
 type V1QueryRun = BoxFuture<'static, Result<KustoResponseDataSetV1>>;
@@ -33,20 +68,267 @@ pub struct QueryRunner {
     kind: QueryKind,
     client_request_properties: Option<ClientRequestProperties>,
     default_headers: Arc<Headers>,
+    #[builder(default)]
+    partial_failure_mode: PartialFailureMode,
+    #[builder(default, setter(skip))]
+    cancel_on_drop: bool,
+    #[builder(default)]
+    declare_parameters: bool,
+}
+
+/// Controls how a query that completed with errors (as reported in the `DataSetCompletion` frame) is surfaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PartialFailureMode {
+    /// A query that completed with errors fails with [Error::PartialQueryFailure].
+    #[default]
+    Strict,
+    /// A query that completed with errors returns its (possibly partial) results.
+    /// The errors are available through [KustoResponseDataSetV2::errors].
+    AllowPartialResults,
 }
+
 pub struct V1QueryRunner(pub QueryRunner);
 
 pub struct V2QueryRunner(pub QueryRunner);
 
 impl V2QueryRunner {
-    pub async fn into_stream(self) -> Result<impl Stream<Item = Result<V2QueryResult>>> {
+    /// Returns a handle that can cancel the query once it is running.
+    /// See [QueryRunner::cancellation_handle].
+    pub fn cancellation_handle(&mut self) -> Result<CancellationHandle> {
+        self.0.cancellation_handle()
+    }
+
+    /// Sets whether the query is cancelled on the service when the future or stream running it is dropped before completing.
+    /// See [QueryRunner::with_cancel_on_drop].
+    #[cfg(feature = "cancel_on_drop")]
+    #[must_use]
+    pub fn with_cancel_on_drop(self, cancel_on_drop: bool) -> Self {
+        Self(self.0.with_cancel_on_drop(cancel_on_drop))
+    }
+
+    /// Sets whether the typed parameters are declared in the query.
+    /// See [QueryRunner::with_parameter_declaration].
+    #[must_use]
+    pub fn with_parameter_declaration(self, declare_parameters: bool) -> Self {
+        Self(self.0.with_parameter_declaration(declare_parameters))
+    }
+
+    /// Sets how a query that completed with errors is surfaced. Defaults to [PartialFailureMode::Strict].
+    #[must_use]
+    pub fn with_partial_failure_mode(mut self, mode: PartialFailureMode) -> Self {
+        self.0.partial_failure_mode = mode;
+        self
+    }
+
+    /// Streams the rows of the primary result one at a time, deserialized into `T` like [Row::deserialize_with],
+    /// using the [row deserializer options](crate::client::KustoClientOptions::with_row_deserializer_options) of the client.
+    ///
+    /// Only a single fragment of the response is held in memory at once, so progressive mode
+    /// (see [Options::results_progressive_enabled](crate::request_options::Options::results_progressive_enabled)) should be enabled for large results.
+    pub async fn into_row_stream<T: DeserializeOwned>(
+        self,
+    ) -> Result<impl Stream<Item = Result<T>>> {
+        let options = self.0.client.row_deserializer_options();
+        Ok(self
+            .into_untyped_row_stream()
+            .await?
+            .and_then(move |row| async move { row.deserialize_with(options) }))
+    }
+
+    /// Streams the rows of the primary result one at a time, along with the columns of the table.
+    /// See [into_row_stream](#method.into_row_stream).
+    pub async fn into_untyped_row_stream(self) -> Result<impl Stream<Item = Result<Row>>> {
+        let stream = self.into_stream().await?;
+        let request_ids = stream.request_ids().clone();
+        Ok(primary_rows(stream).map_err(move |e| e.with_request_ids(&request_ids)))
+    }
+
+    /// Streams the primary result as `arrow` `RecordBatch`es, all sharing the schema derived from the columns of the table.
+    ///
+    /// When `max_rows` is [None], a batch is created for each fragment of the response, otherwise the rows are regrouped into batches of up to `max_rows` rows.
+    /// As with [into_row_stream](#method.into_row_stream), the response is not buffered as a whole,
+    /// and the rows are decoded from the JSON of each fragment straight into the arrays of the batches.
+    /// The response is read up to the header of the primary result before returning,
+    /// so that [RecordBatchStream::schema] is known even if the table has no rows.
+    #[cfg(feature = "arrow")]
+    pub async fn into_record_batch_stream(
+        self,
+        max_rows: Option<NonZeroUsize>,
+    ) -> Result<RecordBatchStream> {
+        let fail_on_errors = self.0.partial_failure_mode == PartialFailureMode::Strict;
+        let RawFrames {
+            request_ids,
+            frames,
+            guard,
+        } = self.0.into_raw_frames().await?;
+
+        let frames = disarm_on_completion(frames, guard, |_| false);
+        let (schema, batches) = record_batch_stream(frames, max_rows, fail_on_errors)
+            .await
+            .map_err(|e| e.with_request_ids(&request_ids))?;
+
+        let ids = request_ids.clone();
+        Ok(RecordBatchStream {
+            request_ids,
+            schema,
+            batches: batches.map_err(move |e| e.with_request_ids(&ids)).boxed(),
+        })
+    }
+
+    /// Runs the query and converts its primary results into `arrow` `RecordBatch`es, one per table.
+    ///
+    /// Unlike [KustoResponseDataSetV2::record_batches], the rows are decoded from the JSON of the response
+    /// straight into the arrays of the batches (see [decode_primary_results](crate::arrow::decode_primary_results)),
+    /// without parsing the response into [V2QueryResult]s first.
+    /// In [PartialFailureMode::AllowPartialResults], the errors the query completed with are not reported.
+    #[cfg(feature = "arrow")]
+    pub async fn into_record_batches(self) -> Result<Vec<RecordBatch>> {
+        let mode = self.0.partial_failure_mode;
+        let RawFrames {
+            request_ids,
+            mut frames,
+            mut guard,
+        } = self.0.into_raw_frames().await?;
+
+        let mut decoder = FramesDecoder::new(BatchMode::Tables);
+        while let Some(frame) = frames.next().await {
+            let frame = frame.map_err(|e| {
+                if matches!(e, Error::TimeoutError(..)) {
+                    guard.disarm();
+                }
+                e
+            })?;
+            decoder
+                .decode_frame(&frame)
+                .map_err(|e| e.with_request_ids(&request_ids))?;
+        }
+        guard.disarm();
+
+        decoder
+            .finish()
+            .map_err(|e| e.with_request_ids(&request_ids))?;
+        match decoder.take_errors() {
+            Some(errors) if mode == PartialFailureMode::Strict => {
+                Err(Error::PartialQueryFailure(errors, request_ids))
+            }
+            _ => Ok(decoder.take_batches()),
+        }
+    }
+
+    /// Streams the raw frames of the response.
+    /// In [PartialFailureMode::Strict], a `DataSetCompletion` frame reporting errors is yielded as [Error::PartialQueryFailure].
+    pub async fn into_stream(self) -> Result<V2QueryStream> {
         let V2QueryRunner(query_runner) = self;
-        query_runner.into_stream().await
+        let mode = query_runner.partial_failure_mode;
+        let V2QueryStream {
+            request_ids,
+            results,
+        } = query_runner.into_stream().await?;
+
+        let ids = request_ids.clone();
+        let results = results.and_then(move |frame| {
+            let result = match frame {
+                V2QueryResult::DataSetCompletion(completion)
+                    if completion.has_errors && mode == PartialFailureMode::Strict =>
+                {
+                    Err(Error::PartialQueryFailure(
+                        completion.one_api_errors.unwrap_or_default(),
+                        ids.clone(),
+                    ))
+                }
+                frame => Ok(frame),
+            };
+            future::ready(result)
+        });
+
+        Ok(V2QueryStream {
+            request_ids,
+            results: results.boxed(),
+        })
     }
 }
 
 impl QueryRunner {
-    async fn into_response(self) -> Result<Response> {
+    /// Returns a handle that can cancel the query once it is running.
+    ///
+    /// Cancellation is keyed on the client request id, so one is generated now if not set in the [ClientRequestProperties].
+    /// Only queries can be cancelled, not management commands.
+    pub fn cancellation_handle(&mut self) -> Result<CancellationHandle> {
+        if self.kind != QueryKind::Query {
+            return Err(Error::UnsupportedOperation(
+                "Cancellation is only supported for queries".to_string(),
+            ));
+        }
+
+        Ok(CancellationHandle::new(
+            self.client.clone(),
+            self.database.clone(),
+            self.client_request_id(),
+        ))
+    }
+
+    /// Sets whether the query is cancelled on the service when the future or stream running it is dropped before completing.
+    /// A stream is only cancelled once the response has started, and a query that failed is not cancelled.
+    ///
+    /// The cancellation is spawned onto the tokio runtime that drops the query,
+    /// so nothing is cancelled when dropping outside of a tokio runtime. Ignored for management commands.
+    #[cfg(feature = "cancel_on_drop")]
+    #[must_use]
+    pub fn with_cancel_on_drop(mut self, cancel_on_drop: bool) -> Self {
+        self.cancel_on_drop = cancel_on_drop;
+        self
+    }
+
+    /// Sets whether the query is prefixed with a `declare query_parameters` statement for the typed parameters
+    /// of the [ClientRequestProperties] (see [ClientRequestProperties::parameter_declaration]).
+    /// The statement is not added to management commands, or to queries that already declare their parameters.
+    #[must_use]
+    pub fn with_parameter_declaration(mut self, declare_parameters: bool) -> Self {
+        self.declare_parameters = declare_parameters;
+        self
+    }
+
+    /// The text of the query, prefixed with the declaration of its parameters if needed.
+    fn query_text(&self) -> String {
+        let declaration = self
+            .client_request_properties
+            .as_ref()
+            .and_then(ClientRequestProperties::parameter_declaration)
+            .filter(|_| self.declare_parameters && self.kind == QueryKind::Query);
+        match declaration {
+            Some(declaration) if !declares_parameters(&self.query) => {
+                format!("{declaration}\n{}", self.query)
+            }
+            _ => self.query.clone(),
+        }
+    }
+
+    /// Returns the client request id that will be sent with the request, generating one if not set in the [ClientRequestProperties].
+    pub fn client_request_id(&mut self) -> String {
+        let client = &self.client;
+        self.client_request_properties
+            .get_or_insert_with(ClientRequestProperties::default)
+            .client_request_id
+            .get_or_insert_with(|| client.new_client_request_id())
+            .clone()
+    }
+
+    fn client_timeout(&self) -> Option<Duration> {
+        self.client_request_properties
+            .as_ref()
+            .and_then(|p| p.client_timeout)
+    }
+
+    /// Creates the handle used to cancel the query on drop or timeout, if either is needed.
+    fn implicit_cancellation_handle(&mut self) -> Option<CancellationHandle> {
+        if self.cancel_on_drop || self.client_timeout().is_some() {
+            self.cancellation_handle().ok()
+        } else {
+            None
+        }
+    }
+
+    async fn into_response(mut self) -> Result<Response> {
         let url = match self.kind {
             QueryKind::Management => self.client.management_url(),
             QueryKind::Query => self.client.query_url(),
@@ -56,48 +338,161 @@ impl QueryRunner {
         let mut context = Context::new();
         let mut headers = self.default_headers.as_ref().clone();
 
-        if let Some(client_request_properties) = &self.client_request_properties {
-            if let Some(client_request_id) = &client_request_properties.client_request_id {
-                headers.insert("x-ms-client-request-id", client_request_id);
-            }
+        headers.insert(CLIENT_REQUEST_ID, self.client_request_id());
 
+        if let Some(client_request_properties) = &self.client_request_properties {
             if let Some(application) = &client_request_properties.application {
                 headers.insert("x-ms-app", application);
             }
         }
 
         context.insert(CustomHeaders::from(headers));
+        context.insert(ServiceErrorSlot::default());
 
         let body = QueryBody {
+            csl: self.query_text(),
             db: self.database,
-            csl: self.query,
             properties: self.client_request_properties,
         };
 
         let bytes = bytes::Bytes::from(serde_json::to_string(&body)?);
         request.set_body(bytes);
 
-        let response = self.client.pipeline().send(&context, &mut request).await?;
-        Ok(response)
+        let result = self.client.pipeline().send(&context, &mut request).await;
+        let service_error = context
+            .get::<ServiceErrorSlot>()
+            .and_then(ServiceErrorSlot::take);
+
+        match (result, service_error) {
+            (Ok(response), _) if response.status().is_success() => Ok(response),
+            (_, Some(service_error)) => Err(service_error.into()),
+            (Ok(response), None) => Err(Error::HttpError(
+                response.status(),
+                response.into_body().collect_string().await?,
+            )),
+            (Err(error), None) => Err(error.into()),
+        }
+    }
+
+    pub async fn into_stream(self) -> Result<V2QueryStream> {
+        let RawFrames {
+            request_ids,
+            frames,
+            guard,
+        } = self.into_raw_frames().await?;
+
+        let results = frames.and_then(|frame| async move { Ok(serde_json::from_slice(&frame)?) });
+        let results = disarm_on_completion(results, guard, |result| {
+            matches!(result, Ok(V2QueryResult::DataSetCompletion(_)))
+        });
+
+        Ok(V2QueryStream {
+            request_ids,
+            results,
+        })
     }
 
-    pub async fn into_stream(self) -> Result<impl Stream<Item = Result<V2QueryResult>>> {
+    /// Streams the raw JSON of the frames of the response as they are received, failing with [Error::TimeoutError] if the client side timeout elapses.
+    async fn into_raw_frames(mut self) -> Result<RawFrames> {
         if self.kind != QueryKind::Query {
             return Err(Error::UnsupportedOperation(
                 "Progressive streaming is only supported for queries".to_string(),
             ));
         }
 
-        let response = self.into_response().await?;
-        let (_status_code, _header_map, pinned_stream) = response.deconstruct();
+        let start = Instant::now();
+        let timeout = self.client_timeout();
+        let handle = self.implicit_cancellation_handle();
+        let cancel_on_drop = self.cancel_on_drop;
+
+        let client_request_id = self.client_request_id();
+
+        // Until the response is received, the query is cancelled by the timeout only:
+        // a failed request has no query to cancel, and a timed out one has been cancelled already.
+        let response = with_timeout(self.into_response(), timeout, handle.as_ref())
+            .await
+            .map_err(|e| {
+                e.with_request_ids(&RequestIds {
+                    client_request_id: Some(client_request_id.clone()),
+                    activity_id: None,
+                })
+            })?;
+        let guard = CancelOnDrop::new(handle.clone().filter(|_| cancel_on_drop));
+        let (_status_code, header_map, pinned_stream) = response.deconstruct();
+        let request_ids = RequestIds {
+            client_request_id: Some(client_request_id),
+            activity_id: header_map.get_optional_string(&ACTIVITY_ID),
+        };
         let reader = pinned_stream
             .map_err(|e| std::io::Error::new(ErrorKind::Other, e))
             .into_async_read();
+        let frames = async_deserializer::iter_results(reader)
+            .map_err(Error::from)
+            .boxed();
+
+        let deadline = timeout.map(|t| (t, sleep(t.saturating_sub(start.elapsed()))));
 
-        Ok(async_deserializer::iter_results(reader).map_err(Error::from))
+        let frames = stream::unfold(Some((frames, deadline, handle)), |state| async move {
+            let (mut frames, deadline, handle) = state?;
+            let Some((timeout, mut sleep)) = deadline else {
+                let next = frames.next().await?;
+                return Some((next, Some((frames, None, handle))));
+            };
+
+            match future::select(frames.next(), &mut sleep).await {
+                Either::Left((Some(next), _)) => {
+                    Some((next, Some((frames, Some((timeout, sleep)), handle))))
+                }
+                Either::Left((None, _)) => None,
+                Either::Right(_) => {
+                    cancel_after_timeout(handle.as_ref(), timeout).await;
+                    Some((
+                        Err(Error::TimeoutError(timeout, RequestIds::default())),
+                        None,
+                    ))
+                }
+            }
+        });
+
+        let ids = request_ids.clone();
+        Ok(RawFrames {
+            request_ids,
+            frames: frames.map_err(move |e| e.with_request_ids(&ids)).boxed(),
+            guard,
+        })
     }
 }
 
+/// The raw JSON of the frames of a query response, see [QueryRunner::into_raw_frames].
+struct RawFrames {
+    request_ids: RequestIds,
+    frames: BoxStream<'static, Result<Vec<u8>>>,
+    /// Cancels the query if dropped before the response is complete, see [disarm_on_completion].
+    guard: CancelOnDrop,
+}
+
+/// Stops the query from being cancelled on drop once the stream ends, times out,
+/// or yields an item after which the query is no longer running.
+fn disarm_on_completion<T: Send + 'static>(
+    items: impl Stream<Item = Result<T>> + Send + 'static,
+    mut guard: CancelOnDrop,
+    is_completion: impl Fn(&Result<T>) -> bool + Send + 'static,
+) -> BoxStream<'static, Result<T>> {
+    items
+        .map(Some)
+        .chain(stream::once(future::ready(None)))
+        .filter_map(move |item| {
+            let completed = item.as_ref().map_or(true, |item| {
+                matches!(item, Err(Error::TimeoutError(..))) || is_completion(item)
+            });
+            if completed {
+                guard.disarm();
+            }
+            future::ready(item)
+        })
+        .boxed()
+}
+
 impl IntoFuture for V1QueryRunner {
     type Output = Result<KustoResponseDataSetV1>;
     type IntoFuture = V1QueryRun;
@@ -120,10 +515,18 @@ impl IntoFuture for V2QueryRunner {
     fn into_future(self) -> V2QueryRun {
         Box::pin(async {
             let V2QueryRunner(query_runner) = self;
+            let mode = query_runner.partial_failure_mode;
             let future = query_runner.into_future().await?;
-            Ok(
-                std::convert::TryInto::try_into(future).expect("Unexpected conversion error from KustoResponse to KustoResponseDataSetV2 - please report this issue to the Kusto team")
-            )
+            let response: KustoResponseDataSetV2 =
+                std::convert::TryInto::try_into(future).expect("Unexpected conversion error from KustoResponse to KustoResponseDataSetV2 - please report this issue to the Kusto team");
+
+            if mode == PartialFailureMode::Strict && response.has_errors() {
+                return Err(Error::PartialQueryFailure(
+                    response.errors()?,
+                    response.request_ids,
+                ));
+            }
+            Ok(response)
         })
     }
 }
@@ -132,24 +535,44 @@ impl IntoFuture for QueryRunner {
     type Output = Result<KustoResponse>;
     type IntoFuture = QueryRun;
 
-    fn into_future(self) -> QueryRun {
-        let this = self.clone();
-
+    fn into_future(mut self) -> QueryRun {
         Box::pin(async move {
-            let response = self.into_response().await?;
+            let kind = self.kind;
+            let client_request_id = self.client_request_id();
+            let timeout = self.client_timeout();
+            let handle = self.implicit_cancellation_handle();
+            let mut guard = CancelOnDrop::new(handle.clone().filter(|_| self.cancel_on_drop));
 
-            Ok(match this.kind {
-                QueryKind::Management => {
-                    <KustoResponseDataSetV1 as TryFrom<HttpResponse>>::try_from(response)
-                        .map_ok(KustoResponse::V1)
-                        .await?
-                }
-                QueryKind::Query => {
-                    <KustoResponseDataSetV2 as TryFrom<HttpResponse>>::try_from(response)
-                        .map_ok(KustoResponse::V2)
-                        .await?
+            let run = async move {
+                let response = self.into_response().await?;
+
+                Ok(match kind {
+                    QueryKind::Management => {
+                        <KustoResponseDataSetV1 as TryFrom<HttpResponse>>::try_from(response)
+                            .map_ok(KustoResponse::V1)
+                            .await?
+                    }
+                    QueryKind::Query => {
+                        <KustoResponseDataSetV2 as TryFrom<HttpResponse>>::try_from(response)
+                            .map_ok(KustoResponse::V2)
+                            .await?
+                    }
+                })
+            };
+
+            let result = with_timeout(run, timeout, handle.as_ref()).await;
+            guard.disarm();
+
+            match result {
+                Ok(mut response) => {
+                    response.request_ids_mut().client_request_id = Some(client_request_id);
+                    Ok(response)
                 }
-            })
+                Err(error) => Err(error.with_request_ids(&RequestIds {
+                    client_request_id: Some(client_request_id),
+                    activity_id: None,
+                })),
+            }
         })
     }
 }
@@ -163,11 +586,94 @@ pub enum KustoResponse {
     V2(KustoResponseDataSetV2),
 }
 
+impl KustoResponse {
+    /// The identifiers of the request that produced this response.
+    #[must_use]
+    pub fn request_ids(&self) -> &RequestIds {
+        match self {
+            KustoResponse::V1(v1) => &v1.request_ids,
+            KustoResponse::V2(v2) => &v2.request_ids,
+        }
+    }
+
+    fn request_ids_mut(&mut self) -> &mut RequestIds {
+        match self {
+            KustoResponse::V1(v1) => &mut v1.request_ids,
+            KustoResponse::V2(v2) => &mut v2.request_ids,
+        }
+    }
+}
+
+/// Identifiers of a request, to correlate it with the service side logs, for example when filing a support ticket.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestIds {
+    /// The client request id sent in the `x-ms-client-request-id` header.
+    pub client_request_id: Option<String>,
+    /// The id the service assigned to the request, returned in the `x-ms-activity-id` header.
+    pub activity_id: Option<String>,
+}
+
+/// A stream of the raw frames of a V2 query response, see [V2QueryRunner::into_stream].
+pub struct V2QueryStream {
+    request_ids: RequestIds,
+    results: BoxStream<'static, Result<V2QueryResult>>,
+}
+
+impl V2QueryStream {
+    /// The identifiers of the request that produced this stream.
+    #[must_use]
+    pub fn request_ids(&self) -> &RequestIds {
+        &self.request_ids
+    }
+}
+
+impl Stream for V2QueryStream {
+    type Item = Result<V2QueryResult>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        self.results.poll_next_unpin(cx)
+    }
+}
+
+/// A stream of the `arrow` `RecordBatch`es of the primary result of a query, see [V2QueryRunner::into_record_batch_stream].
+#[cfg(feature = "arrow")]
+pub struct RecordBatchStream {
+    request_ids: RequestIds,
+    schema: SchemaRef,
+    batches: BoxStream<'static, Result<RecordBatch>>,
+}
+
+#[cfg(feature = "arrow")]
+impl RecordBatchStream {
+    /// The identifiers of the request that produced this stream.
+    #[must_use]
+    pub fn request_ids(&self) -> &RequestIds {
+        &self.request_ids
+    }
+
+    /// The schema shared by all the batches of the stream, empty if the response has no primary result.
+    #[must_use]
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+#[cfg(feature = "arrow")]
+impl Stream for RecordBatchStream {
+    type Item = Result<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        self.batches.poll_next_unpin(cx)
+    }
+}
+
 /// The top level response from a Kusto query.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct KustoResponseDataSetV2 {
     /// All of the raw results in the response.
     pub results: Vec<V2QueryResult>,
+    /// The identifiers of the request that produced this response.
+    pub request_ids: RequestIds,
 }
 
 impl std::convert::TryFrom<KustoResponse> for KustoResponseDataSetV2 {
@@ -194,6 +700,8 @@ impl std::convert::TryFrom<KustoResponse> for KustoResponseDataSetV1 {
 
 struct KustoResponseDataSetV2TableIterator<T: Iterator<Item = V2QueryResult>> {
     tables: T,
+    /// Progressive tables whose header has been received, but not yet their completion, keyed by table id.
+    in_progress: HashMap<i32, DataTable>,
     finished: bool,
 }
 
@@ -201,76 +709,100 @@ impl<T: Iterator<Item = V2QueryResult>> KustoResponseDataSetV2TableIterator<T> {
     fn new(tables: T) -> Self {
         Self {
             tables,
+            in_progress: HashMap::new(),
             finished: false,
         }
     }
+
+    /// Applies a single frame, returning a table if the frame completed one.
+    fn apply(&mut self, result: V2QueryResult) -> Result<Option<DataTable>> {
+        match result {
+            V2QueryResult::DataTable(table) => Ok(Some(table)),
+            V2QueryResult::TableHeader(header) => {
+                if self.in_progress.contains_key(&header.table_id) {
+                    return Err(ProtocolError::DuplicateTableHeader {
+                        table_id: header.table_id,
+                    }
+                    .into());
+                }
+                self.in_progress.insert(
+                    header.table_id,
+                    DataTable {
+                        table_id: header.table_id,
+                        table_name: header.table_name,
+                        table_kind: header.table_kind,
+                        columns: header.columns,
+                        rows: vec![],
+                    },
+                );
+                Ok(None)
+            }
+            V2QueryResult::TableFragment(fragment) => {
+                let table = self.in_progress_table(fragment.table_id, "TableFragment")?;
+                match fragment.table_fragment_type {
+                    TableFragmentType::DataAppend => table.rows.extend(fragment.rows),
+                    TableFragmentType::DataReplace => table.rows = fragment.rows,
+                };
+                Ok(None)
+            }
+            V2QueryResult::TableProgress(progress) => {
+                self.in_progress_table(progress.table_id, "TableProgress")?;
+                Ok(None)
+            }
+            V2QueryResult::TableCompletion(completion) => {
+                let table = self.in_progress.remove(&completion.table_id).ok_or(
+                    ProtocolError::UnknownTable {
+                        table_id: completion.table_id,
+                        frame: "TableCompletion",
+                    },
+                )?;
+                if usize::try_from(completion.row_count) != Ok(table.rows.len()) {
+                    return Err(ProtocolError::RowCountMismatch {
+                        table_id: completion.table_id,
+                        expected: completion.row_count,
+                        actual: table.rows.len(),
+                    }
+                    .into());
+                }
+                Ok(Some(table))
+            }
+            V2QueryResult::DataSetHeader(_) | V2QueryResult::DataSetCompletion(_) => Ok(None),
+        }
+    }
+
+    fn in_progress_table(&mut self, table_id: i32, frame: &'static str) -> Result<&mut DataTable> {
+        self.in_progress
+            .get_mut(&table_id)
+            .ok_or_else(|| ProtocolError::UnknownTable { table_id, frame }.into())
+    }
 }
 
 impl<T: Iterator<Item = V2QueryResult>> Iterator for KustoResponseDataSetV2TableIterator<T> {
-    type Item = DataTable;
+    type Item = Result<DataTable>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.finished {
             return None;
         }
-        let next_table = self.tables.find_map(|t| match t {
-            V2QueryResult::DataTable(_) | V2QueryResult::TableHeader(_) => Some(t),
-            _ => None,
-        });
-
-        if let Some(V2QueryResult::DataTable(t)) = next_table {
-            return Some(t);
-        }
-
-        let mut table = DataTable {
-            table_id: 0,
-            table_name: "".to_string(),
-            table_kind: TableKind::Unknown,
-            columns: vec![],
-            rows: vec![],
-        };
 
-        if let Some(V2QueryResult::TableHeader(header)) = next_table {
-            table.table_id = header.table_id;
-            table.table_kind = header.table_kind;
-            table.table_name = header.table_name;
-            table.columns = header.columns;
-        } else {
-            self.finished = true;
-            return None;
-        }
-
-        let mut finished_table = false;
-
-        for result in &mut self.tables {
-            match result {
-                V2QueryResult::TableFragment(fragment) => {
-                    assert_eq!(fragment.table_id, table.table_id);
-                    match fragment.table_fragment_type {
-                        TableFragmentType::DataAppend => table.rows.extend(fragment.rows),
-                        TableFragmentType::DataReplace => table.rows = fragment.rows,
-                    };
+        while let Some(result) = self.tables.next() {
+            match self.apply(result) {
+                Ok(Some(table)) => return Some(Ok(table)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.finished = true;
+                    return Some(Err(e));
                 }
-                V2QueryResult::TableProgress(progress) => {
-                    assert_eq!(progress.table_id, table.table_id);
-                }
-                V2QueryResult::TableCompletion(completion) => {
-                    assert_eq!(completion.table_id, table.table_id);
-                    assert_eq!(
-                        completion.row_count,
-                        TryInto::<i32>::try_into(table.rows.len()).expect("Row count overflow")
-                    );
-                    finished_table = true;
-                    break;
-                }
-                _ => unreachable!("Unexpected result type"),
             }
         }
 
-        if finished_table {
-            Some(table)
-        } else {
+        self.finished = true;
+        if self.in_progress.is_empty() {
             None
+        } else {
+            let mut table_ids: Vec<i32> = self.in_progress.keys().copied().collect();
+            table_ids.sort_unstable();
+            Some(Err(ProtocolError::IncompleteTables { table_ids }.into()))
         }
     }
 }
@@ -293,7 +825,9 @@ impl KustoResponseDataSetV2 {
     ///         columns: vec![],
     ///         rows: vec![],
     ///         }),
-    ///     ], };
+    ///     ],
+    ///     ..Default::default()
+    /// };
     ///
     /// assert_eq!(data_set.raw_results_count(), 2);
     /// ```
@@ -333,16 +867,19 @@ impl KustoResponseDataSetV2 {
     ///        row_count: 0,
     ///    }),
     ///],
+    ///..Default::default()
     ///};
     /// let mut results = vec![];
     /// for table in data_set.parsed_data_tables() {
+    ///    let table = table.expect("Failed to parse table");
     ///    results.push(format!("{} - {}", table.table_id, table.table_name));
     /// }
     ///
     /// assert_eq!(results, vec!["0 - table_1", "1 - table_2"]);
     /// ```
-    pub fn parsed_data_tables(&self) -> impl Iterator<Item = DataTable> + '_ {
+    pub fn parsed_data_tables(&self) -> impl Iterator<Item = Result<DataTable>> + '_ {
         KustoResponseDataSetV2TableIterator::new(self.results.iter().cloned())
+            .map(|table| table.map_err(|e| e.with_request_ids(&self.request_ids)))
     }
 
     /// Iterates over the tables in the response, yielding only the primary tables.
@@ -376,18 +913,19 @@ impl KustoResponseDataSetV2 {
     ///        row_count: 0,
     ///    }),
     ///],
+    ///..Default::default()
     ///};
     /// let mut results = vec![];
     /// for table in data_set.primary_results() {
+    ///    let table = table.expect("Failed to parse table");
     ///    results.push(format!("{} - {}", table.table_id, table.table_name));
     /// }
     ///
     /// assert_eq!(results, vec!["1 - table_2"]);
     /// ```
     /// Consumes the response into an iterator over all PrimaryResult tables within the response dataset
-    pub fn primary_results(&self) -> impl Iterator<Item = DataTable> + '_ {
-        self.parsed_data_tables()
-            .filter(|t| t.table_kind == TableKind::PrimaryResult)
+    pub fn primary_results(&self) -> impl Iterator<Item = Result<DataTable>> + '_ {
+        self.parsed_data_tables().filter(is_primary_result)
     }
 
     /// Iterates over the tables in the response, and converts them into `arrow` `Batches`
@@ -428,6 +966,7 @@ impl KustoResponseDataSetV2 {
     ///        row_count: 2,
     ///    }),
     ///],
+    ///..Default::default()
     ///};
     /// let mut results = vec![];
     /// for batch in data_set.record_batches() {
@@ -439,33 +978,116 @@ impl KustoResponseDataSetV2 {
     /// Consumes the response into an iterator over all PrimaryResult tables within the response dataset
     #[cfg(feature = "arrow")]
     pub fn record_batches(&self) -> impl Iterator<Item = Result<RecordBatch>> + '_ {
-        self.primary_results().map(convert_table)
+        self.primary_results().map(|t| t.and_then(convert_table))
     }
 
     /// Consuming version for [parse_data_tables](#method.parse_data_tables).
-    pub fn into_parsed_data_tables(self) -> impl Iterator<Item = DataTable> {
+    pub fn into_parsed_data_tables(self) -> impl Iterator<Item = Result<DataTable>> {
+        let request_ids = self.request_ids;
         KustoResponseDataSetV2TableIterator::new(self.results.into_iter())
+            .map(move |table| table.map_err(|e| e.with_request_ids(&request_ids)))
     }
 
     /// Consuming version for [primary_results](#method.primary_results).
-    pub fn into_primary_results(self) -> impl Iterator<Item = DataTable> {
-        self.into_parsed_data_tables()
-            .filter(|t| t.table_kind == TableKind::PrimaryResult)
+    pub fn into_primary_results(self) -> impl Iterator<Item = Result<DataTable>> {
+        self.into_parsed_data_tables().filter(is_primary_result)
     }
 
     #[cfg(feature = "arrow")]
     /// Consuming version for [record_batches](#method.record_batches).
     pub fn into_record_batches(self) -> impl Iterator<Item = Result<RecordBatch>> {
-        self.into_primary_results().map(convert_table)
+        self.into_primary_results()
+            .map(|t| t.and_then(convert_table))
     }
+
+    /// Whether the service reported errors when completing the query, in which case the results may be partial.
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.results.iter().any(|r| {
+            matches!(
+                r,
+                V2QueryResult::DataSetCompletion(completion) if completion.has_errors
+            )
+        })
+    }
+
+    /// Parses the rows of the `QueryCompletionInformation` table of the response.
+    pub fn query_completion_information(&self) -> Result<Vec<QueryCompletionInformation>> {
+        let mut information = vec![];
+        for table in self.parsed_data_tables() {
+            let table = table?;
+            if table.table_kind != TableKind::QueryCompletionInformation {
+                continue;
+            }
+            information.extend(table.into_deserialized_rows(RowDeserializerOptions::default())?);
+        }
+        Ok(information)
+    }
+
+    /// Parses the statistics of the query: its resource consumption, from the `QueryCompletionInformation` table,
+    /// and the properties of its results, such as the visualization, from the `@ExtendedProperties` table.
+    pub fn statistics(&self) -> Result<QueryStatistics> {
+        let mut properties = vec![];
+        for table in self.parsed_data_tables() {
+            let table = table?;
+            if table.table_kind == TableKind::QueryProperties {
+                properties.extend(table.into_deserialized_rows(RowDeserializerOptions::default())?);
+            }
+        }
+        QueryStatistics::new(&self.query_completion_information()?, properties)
+    }
+
+    /// Collects the errors the query encountered, both from the `DataSetCompletion` frame and from the `QueryCompletionInformation` table.
+    pub fn errors(&self) -> Result<Vec<OneApiError>> {
+        let mut errors: Vec<OneApiError> = self
+            .results
+            .iter()
+            .filter_map(|r| match r {
+                V2QueryResult::DataSetCompletion(completion) => completion.one_api_errors.clone(),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+
+        for error in self
+            .query_completion_information()?
+            .iter()
+            .filter_map(QueryCompletionInformation::one_api_error)
+        {
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+
+        Ok(errors)
+    }
+}
+
+/// Whether the table is the table of contents of a V1 response, by its columns.
+fn is_table_of_contents(table: &TableV1) -> bool {
+    table
+        .columns
+        .iter()
+        .map(|c| c.column_name.as_str())
+        .eq(TableOfContentsEntry::COLUMNS)
+}
+
+/// Keeps errors, so they are not silently dropped when filtering for primary results.
+fn is_primary_result(table: &Result<DataTable>) -> bool {
+    table
+        .as_ref()
+        .map_or(true, |t| t.table_kind == TableKind::PrimaryResult)
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
 #[serde(rename_all = "PascalCase")]
 /// The header of a Kusto response dataset for v1. Contains a list of tables.
 pub struct KustoResponseDataSetV1 {
     /// The list of tables in the dataset.
     pub tables: Vec<TableV1>,
+    /// The identifiers of the request that produced this response.
+    #[serde(skip)]
+    pub request_ids: RequestIds,
 }
 
 impl KustoResponseDataSetV1 {
@@ -482,13 +1104,122 @@ impl KustoResponseDataSetV1 {
     ///         columns: vec![],
     ///         rows: vec![],
     ///      },
-    /// ]};
+    ///    ],
+    ///    ..Default::default()
+    /// };
     ///
     /// assert_eq!(dataset.table_count(), 1);
     ///
     pub fn table_count(&self) -> usize {
         self.tables.len()
     }
+
+    /// Parses the table of contents, which is the last table of the responses to queries, describing the other tables.
+    /// Responses to management commands have no table of contents, in which case this returns [None].
+    pub fn table_of_contents(&self) -> Result<Option<Vec<TableOfContentsEntry>>> {
+        match self.tables.split_last() {
+            Some((last, others)) if !others.is_empty() && is_table_of_contents(last) => last
+                .deserialize_rows(RowDeserializerOptions::default())
+                .map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The tables holding the results of the query, as listed in the table of contents,
+    /// or all the tables if there is none, as is the case for management commands.
+    /// # Example
+    /// ```rust
+    /// use serde_json::json;
+    /// use azure_kusto_data::models::{ColumnV1, ColumnType, TableV1};
+    /// use azure_kusto_data::prelude::KustoResponseDataSetV1;
+    ///
+    /// let column = |name: &str, data_type| ColumnV1 {
+    ///     column_name: name.to_string(),
+    ///     column_type: None,
+    ///     data_type: Some(data_type),
+    /// };
+    /// let dataset = KustoResponseDataSetV1 {
+    ///     tables: vec![
+    ///         TableV1 {
+    ///             table_name: "Table_0".to_string(),
+    ///             columns: vec![column("Value", ColumnType::Long)],
+    ///             rows: vec![vec![json!(1)]],
+    ///         },
+    ///         TableV1 {
+    ///             table_name: "Table_1".to_string(),
+    ///             columns: vec![column("Value", ColumnType::String)],
+    ///             rows: vec![vec![json!("{}")]],
+    ///         },
+    ///         TableV1 {
+    ///             table_name: "Table_2".to_string(),
+    ///             columns: ["Ordinal", "Kind", "Name", "Id", "PrettyName"]
+    ///                 .into_iter()
+    ///                 .map(|name| column(name, ColumnType::String))
+    ///                 .collect(),
+    ///             rows: vec![
+    ///                 vec![json!(0), json!("QueryResult"), json!("PrimaryResult"), json!(""), json!("")],
+    ///                 vec![json!(1), json!("QueryProperties"), json!("@ExtendedProperties"), json!(""), json!("")],
+    ///             ],
+    ///         },
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let results = dataset.primary_results().expect("Failed to parse the table of contents");
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].table_name, "Table_0");
+    /// ```
+    pub fn primary_results(&self) -> Result<Vec<&TableV1>> {
+        Ok(self
+            .primary_result_ordinals()?
+            .into_iter()
+            .filter_map(|i| self.tables.get(i))
+            .collect())
+    }
+
+    /// Consuming version for [primary_results](#method.primary_results).
+    pub fn into_primary_results(self) -> Result<Vec<TableV1>> {
+        let ordinals = self.primary_result_ordinals()?;
+        Ok(self
+            .tables
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| ordinals.contains(i))
+            .map(|(_, table)| table)
+            .collect())
+    }
+
+    /// Converts the primary results into `arrow` `Batches`, typing the columns by their `ColumnType`, or their `DataType` if it is missing.
+    ///
+    /// This method does not consume the response, so it can be called multiple times.
+    #[cfg(feature = "arrow")]
+    pub fn record_batches(&self) -> Result<Vec<RecordBatch>> {
+        self.primary_results()?
+            .into_iter()
+            .map(|t| convert_table_v1(t.clone()))
+            .collect()
+    }
+
+    /// Consuming version for [record_batches](#method.record_batches).
+    #[cfg(feature = "arrow")]
+    pub fn into_record_batches(self) -> Result<impl Iterator<Item = Result<RecordBatch>>> {
+        Ok(self
+            .into_primary_results()?
+            .into_iter()
+            .map(convert_table_v1))
+    }
+
+    /// The indices of the primary results in the tables.
+    fn primary_result_ordinals(&self) -> Result<Vec<usize>> {
+        Ok(match self.table_of_contents()? {
+            Some(entries) => entries
+                .into_iter()
+                .filter(|e| e.kind == TableKindV1::QueryResult)
+                .filter_map(|e| usize::try_from(e.ordinal).ok())
+                .collect(),
+            None => (0..self.tables.len()).collect(),
+        })
+    }
 }
 
 #[async_convert::async_trait]
@@ -496,10 +1227,16 @@ impl TryFrom<HttpResponse> for KustoResponseDataSetV2 {
     type Error = Error;
 
     async fn try_from(response: HttpResponse) -> Result<Self> {
-        let (_status_code, _header_map, pinned_stream) = response.deconstruct();
+        let (_status_code, header_map, pinned_stream) = response.deconstruct();
         let data = pinned_stream.collect().await?;
         let tables: Vec<V2QueryResult> = serde_json::from_slice(&data)?;
-        Ok(Self { results: tables })
+        Ok(Self {
+            results: tables,
+            request_ids: RequestIds {
+                client_request_id: None,
+                activity_id: header_map.get_optional_string(&ACTIVITY_ID),
+            },
+        })
     }
 }
 
@@ -508,9 +1245,11 @@ impl TryFrom<HttpResponse> for KustoResponseDataSetV1 {
     type Error = Error;
 
     async fn try_from(response: HttpResponse) -> Result<Self> {
-        let (_status_code, _header_map, pinned_stream) = response.deconstruct();
+        let (_status_code, header_map, pinned_stream) = response.deconstruct();
         let data = pinned_stream.collect().await?;
-        Ok(serde_json::from_slice(&data)?)
+        let mut data_set: Self = serde_json::from_slice(&data)?;
+        data_set.request_ids.activity_id = header_map.get_optional_string(&ACTIVITY_ID);
+        Ok(data_set)
     }
 }
 
@@ -519,6 +1258,72 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
 
+    #[test]
+    fn parses_statistics() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/inputs/dataframe.json");
+        let data = std::fs::read_to_string(path).expect("Failed to read file");
+        let response = KustoResponseDataSetV2 {
+            results: serde_json::from_str(&data).expect("Failed to parse response"),
+            ..Default::default()
+        };
+
+        let statistics = response.statistics().expect("Failed to parse statistics");
+        let consumption = statistics
+            .resource_consumption
+            .as_ref()
+            .expect("No resource consumption");
+        assert_eq!(consumption.execution_time, 0.0156222);
+        assert_eq!(consumption.resource_usage.cache.memory.hits, 13);
+        assert_eq!(consumption.resource_usage.memory.peak_per_node, 16777312);
+        assert_eq!(
+            consumption.resource_usage.cpu.total_cpu,
+            Some(time::Duration::ZERO.into())
+        );
+        assert_eq!(consumption.dataset_statistics[0].table_row_count, 3);
+        assert_eq!(consumption.dataset_statistics[0].table_size, 191);
+
+        let visualization = statistics
+            .visualization(1)
+            .expect("Failed to parse visualization")
+            .expect("No visualization");
+        assert_eq!(visualization.visualization, None);
+        assert!(!visualization.accumulate);
+        assert_eq!(statistics.visualization(2).expect("Failed to parse"), None);
+        assert_eq!(statistics.cursor(1), None);
+    }
+
+    #[test]
+    fn declares_parameters_in_queries() {
+        let client = KustoClient::new(
+            crate::prelude::ConnectionString::with_default_auth(
+                "https://cluster.kusto.windows.net",
+            ),
+            crate::prelude::KustoClientOptions::default(),
+        )
+        .expect("Failed to create client");
+        let mut properties = ClientRequestProperties::default();
+        properties.add_i64_parameter("n".into(), 5);
+        let runner = |query: &str, kind| {
+            client.execute_with_options("db", query, kind, Some(properties.clone()))
+        };
+
+        let query = runner("T | take n", QueryKind::Query);
+        assert_eq!(query.query_text(), "T | take n");
+        assert_eq!(
+            query.with_parameter_declaration(true).query_text(),
+            "declare query_parameters(n:long);\nT | take n"
+        );
+
+        let declared = "DECLARE  query_parameters(n:long); T | take n";
+        assert!(declares_parameters(declared));
+        let query = runner(declared, QueryKind::Query).with_parameter_declaration(true);
+        assert_eq!(query.query_text(), declared);
+
+        let command =
+            runner(".show tables", QueryKind::Management).with_parameter_declaration(true);
+        assert_eq!(command.query_text(), ".show tables");
+    }
+
     #[test]
     fn load_response_data() {
         let data = r#"{
@@ -549,5 +1354,160 @@ mod tests {
         let parsed = serde_json::from_str::<KustoResponseDataSetV1>(&data)
             .expect("Failed to parse response");
         assert_eq!(parsed.table_count(), 4);
+
+        let contents = parsed
+            .table_of_contents()
+            .expect("Failed to parse the table of contents")
+            .expect("No table of contents");
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1].kind, TableKindV1::QueryProperties);
+        assert_eq!(contents[1].name, "@ExtendedProperties");
+
+        let results = parsed
+            .primary_results()
+            .expect("Failed to find primary results");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].table_name, "Table_0");
+
+        #[cfg(feature = "arrow")]
+        {
+            let batches: Vec<RecordBatch> = parsed
+                .into_record_batches()
+                .expect("Failed to find primary results")
+                .collect::<Result<_>>()
+                .expect("Failed to convert results");
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0].num_rows(), 2);
+            assert_eq!(
+                batches[0].schema().field(1).data_type(),
+                &arrow_schema::DataType::Utf8
+            );
+        }
+    }
+
+    #[test]
+    fn partial_failure_errors() {
+        let data = r#"[
+            {"FrameType": "DataSetHeader", "IsProgressive": false, "Version": "v2.0"},
+            {
+                "FrameType": "DataTable",
+                "TableId": 1,
+                "TableName": "QueryCompletionInformation",
+                "TableKind": "QueryCompletionInformation",
+                "Columns": [
+                    {"ColumnName": "Timestamp", "ColumnType": "datetime"},
+                    {"ColumnName": "ClientRequestId", "ColumnType": "string"},
+                    {"ColumnName": "ActivityId", "ColumnType": "guid"},
+                    {"ColumnName": "SubActivityId", "ColumnType": "guid"},
+                    {"ColumnName": "ParentActivityId", "ColumnType": "guid"},
+                    {"ColumnName": "Level", "ColumnType": "int"},
+                    {"ColumnName": "LevelName", "ColumnType": "string"},
+                    {"ColumnName": "StatusCode", "ColumnType": "int"},
+                    {"ColumnName": "StatusCodeName", "ColumnType": "string"},
+                    {"ColumnName": "EventType", "ColumnType": "int"},
+                    {"ColumnName": "EventTypeName", "ColumnType": "string"},
+                    {"ColumnName": "Payload", "ColumnType": "string"}
+                ],
+                "Rows": [
+                    ["2023-04-18T12:00:00Z", "KRS.execute;1", "a", "b", "c", 4, "Info", 0, "S_OK (0)", 4, "QueryInfo", "{\"Count\":1}"],
+                    ["2023-04-18T12:00:00Z", "KRS.execute;1", "a", "b", "c", 2, "Error", -2133196797, "E_QUERY_RESULT_SET_TOO_LARGE", 4, "QueryInfo",
+                     "{\"error\":{\"code\":\"LimitsExceeded\",\"message\":\"Request is invalid and cannot be executed.\"}}"]
+                ]
+            },
+            {
+                "FrameType": "DataSetCompletion",
+                "HasErrors": true,
+                "Cancelled": false,
+                "OneApiErrors": [{"error": {"code": "LimitsExceeded", "message": "Request is invalid and cannot be executed."}}]
+            }
+        ]"#;
+
+        let response = KustoResponseDataSetV2 {
+            results: serde_json::from_str(data).expect("Failed to parse"),
+            ..Default::default()
+        };
+
+        assert!(response.has_errors());
+        assert_eq!(response.query_completion_information().unwrap().len(), 2);
+
+        let errors = response.errors().expect("Failed to collect errors");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error.code, "LimitsExceeded");
+    }
+
+    fn progressive_frames(frames: &str) -> KustoResponseDataSetV2 {
+        KustoResponseDataSetV2 {
+            results: serde_json::from_str(frames).expect("Failed to parse"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn interleaved_progressive_tables() {
+        let response = progressive_frames(
+            r#"[
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}]},
+            {"FrameType": "TableHeader", "TableId": 2, "TableName": "b", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "y", "ColumnType": "long"}]},
+            {"FrameType": "TableFragment", "TableId": 2, "TableFragmentType": "DataAppend", "Rows": [[1]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[1], [2]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[3]]},
+            {"FrameType": "TableCompletion", "TableId": 2, "RowCount": 1},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 1}
+        ]"#,
+        );
+
+        let tables = response
+            .parsed_data_tables()
+            .collect::<Result<Vec<_>>>()
+            .expect("Failed to parse tables");
+
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].table_id, 2);
+        assert_eq!(tables[1].table_id, 1);
+        assert_eq!(tables[1].rows, vec![serde_json::json!([3])]);
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let cases = [
+            (
+                r#"[{"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": []}]"#,
+                ProtocolError::UnknownTable {
+                    table_id: 1,
+                    frame: "TableFragment",
+                },
+            ),
+            (
+                r#"[
+                    {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": []},
+                    {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 2}
+                ]"#,
+                ProtocolError::RowCountMismatch {
+                    table_id: 1,
+                    expected: 2,
+                    actual: 0,
+                },
+            ),
+            (
+                r#"[{"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": []}]"#,
+                ProtocolError::IncompleteTables { table_ids: vec![1] },
+            ),
+        ];
+
+        let request_ids = RequestIds {
+            client_request_id: Some("KRS.execute;1".to_string()),
+            activity_id: Some("2".to_string()),
+        };
+        for (frames, expected) in cases {
+            let mut response = progressive_frames(frames);
+            response.request_ids = request_ids.clone();
+            let results = response.parsed_data_tables().collect::<Vec<_>>();
+            assert_eq!(results.len(), 1);
+            assert!(
+                matches!(&results[0], Err(Error::ProtocolError(e, ids)) if *e == expected && *ids == request_ids),
+                "expected {expected:?}, got {:?}",
+                results[0]
+            );
+        }
     }
 }
@@ -0,0 +1,303 @@
+use crate::error::{Error, ProtocolError, Result};
+use crate::models::{Column, RowView, TableFragmentType, TableKind, V2QueryResult};
+use crate::row_deserializer::{from_row, RowDeserializerOptions};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A single row of a query result, along with the columns of its table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    columns: Arc<[Column]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// The columns of the table the row belongs to.
+    #[must_use]
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The values of the row, in the order of the columns.
+    #[must_use]
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Returns the value of the column with the given name, or [None] if there is no such column.
+    #[must_use]
+    pub fn get(&self, column_name: &str) -> Option<&Value> {
+        self.columns
+            .iter()
+            .position(|c| c.column_name == column_name)
+            .and_then(|i| self.values.get(i))
+    }
+
+    /// A view of the row, with typed accessors for its values.
+    #[must_use]
+    pub fn view(&self) -> RowView<'_> {
+        RowView::new(&self.columns, &self.values)
+    }
+
+    /// Consumes the row into its values.
+    #[must_use]
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
+    /// Deserializes the row into a struct, matching its fields to the columns by name with the default [RowDeserializerOptions].
+    pub fn deserialize<T: DeserializeOwned>(self) -> Result<T> {
+        self.deserialize_with(RowDeserializerOptions::default())
+    }
+
+    /// Deserializes the row into a struct, matching its fields to the columns by name as configured by the options.
+    pub fn deserialize_with<T: DeserializeOwned>(
+        self,
+        options: RowDeserializerOptions,
+    ) -> Result<T> {
+        from_row(
+            self.columns.iter().map(|c| c.column_name.as_str()),
+            self.values,
+            options,
+        )
+    }
+}
+
+/// The primary result table whose rows are being streamed.
+struct PrimaryTable {
+    table_id: i32,
+    columns: Arc<[Column]>,
+    /// Rows of the latest `DataReplace` fragment, held back as a later fragment may replace them again.
+    replaced: Option<Vec<Value>>,
+    /// Whether any rows of the table were already handed out, after which they can no longer be replaced.
+    streamed: bool,
+    row_count: usize,
+}
+
+/// Rows of the primary result that are final, i.e. will not be replaced by a later fragment.
+pub(crate) struct Fragment {
+    pub(crate) columns: Arc<[Column]>,
+    pub(crate) rows: Vec<Value>,
+}
+
+struct FragmentState<S> {
+    frames: S,
+    table: Option<PrimaryTable>,
+    /// Whether the first primary result table was found, all other tables are skipped.
+    found_primary: bool,
+    columns: Arc<[Column]>,
+    pending: Vec<Value>,
+}
+
+impl<S: Stream<Item = Result<V2QueryResult>> + Unpin> FragmentState<S> {
+    /// Handles a frame, queueing the rows it makes final in `pending`.
+    fn apply(&mut self, frame: V2QueryResult) -> Result<()> {
+        match frame {
+            V2QueryResult::DataTable(table)
+                if !self.found_primary && table.table_kind == TableKind::PrimaryResult =>
+            {
+                self.found_primary = true;
+                self.columns = table.columns.into();
+                self.pending = table.rows;
+            }
+            V2QueryResult::TableHeader(header)
+                if !self.found_primary && header.table_kind == TableKind::PrimaryResult =>
+            {
+                self.found_primary = true;
+                self.table = Some(PrimaryTable {
+                    table_id: header.table_id,
+                    columns: header.columns.into(),
+                    replaced: None,
+                    streamed: false,
+                    row_count: 0,
+                });
+            }
+            V2QueryResult::TableFragment(fragment) => {
+                let Some(table) = self
+                    .table
+                    .as_mut()
+                    .filter(|t| t.table_id == fragment.table_id)
+                else {
+                    return Ok(());
+                };
+
+                match fragment.table_fragment_type {
+                    TableFragmentType::DataAppend => {
+                        let mut rows = table.replaced.take().unwrap_or_default();
+                        table.row_count += fragment.rows.len();
+                        rows.extend(fragment.rows);
+                        table.streamed |= !rows.is_empty();
+                        self.columns = table.columns.clone();
+                        self.pending = rows;
+                    }
+                    TableFragmentType::DataReplace => {
+                        if table.streamed {
+                            return Err(ProtocolError::ReplaceAfterStreamedRows {
+                                table_id: table.table_id,
+                            }
+                            .into());
+                        }
+                        table.row_count = fragment.rows.len();
+                        table.replaced = Some(fragment.rows);
+                    }
+                }
+            }
+            V2QueryResult::TableCompletion(completion)
+                if self
+                    .table
+                    .as_ref()
+                    .map_or(false, |t| t.table_id == completion.table_id) =>
+            {
+                let table = self.table.take().expect("table was just checked");
+                if usize::try_from(completion.row_count) != Ok(table.row_count) {
+                    return Err(ProtocolError::RowCountMismatch {
+                        table_id: table.table_id,
+                        expected: completion.row_count,
+                        actual: table.row_count,
+                    }
+                    .into());
+                }
+                self.columns = table.columns;
+                self.pending = table.replaced.unwrap_or_default();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn next_fragment(&mut self) -> Option<Result<Fragment>> {
+        loop {
+            if !self.pending.is_empty() {
+                return Some(Ok(Fragment {
+                    columns: self.columns.clone(),
+                    rows: std::mem::take(&mut self.pending),
+                }));
+            }
+
+            match self.frames.next().await {
+                Some(Ok(frame)) => {
+                    if let Err(e) = self.apply(frame) {
+                        return Some(Err(e));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    return self.table.take().map(|t| {
+                        Err(ProtocolError::IncompleteTables {
+                            table_ids: vec![t.table_id],
+                        }
+                        .into())
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Streams the rows of the first primary result table in the frames, a fragment at a time.
+///
+/// Only a single fragment is held in memory at once. The frames after the primary result are still consumed,
+/// so that errors reported at the end of the response are surfaced. The stream ends after the first error.
+pub(crate) fn primary_fragments(
+    frames: impl Stream<Item = Result<V2QueryResult>> + Unpin,
+) -> impl Stream<Item = Result<Fragment>> {
+    let state = FragmentState {
+        frames,
+        table: None,
+        found_primary: false,
+        columns: Arc::new([]),
+        pending: Vec::new(),
+    };
+
+    stream::unfold(Some(state), |state| async move {
+        let mut state = state?;
+        let fragment = state.next_fragment().await?;
+        let state = fragment.is_ok().then_some(state);
+        Some((fragment, state))
+    })
+}
+
+/// Streams the rows of the first primary result table in the frames, one at a time.
+/// See [primary_fragments].
+pub(crate) fn primary_rows(
+    frames: impl Stream<Item = Result<V2QueryResult>> + Unpin,
+) -> impl Stream<Item = Result<Row>> {
+    primary_fragments(frames)
+        .map_ok(|fragment| {
+            let columns = fragment.columns;
+            stream::iter(fragment.rows.into_iter().map(move |values| match values {
+                Value::Array(values) => Ok(Row {
+                    columns: columns.clone(),
+                    values,
+                }),
+                _ => Err(Error::ConversionError("row is not an array".to_string())),
+            }))
+        })
+        .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn rows_of(frames: &str) -> Result<Vec<Vec<Value>>> {
+        let frames: Vec<V2QueryResult> = serde_json::from_str(frames).expect("Failed to parse");
+        primary_rows(stream::iter(frames.into_iter().map(Ok)))
+            .map_ok(Row::into_values)
+            .try_collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn streams_primary_result_rows() {
+        let rows = rows_of(
+            r#"[
+            {"FrameType": "DataSetHeader", "IsProgressive": true, "Version": "v2.0"},
+            {"FrameType": "TableHeader", "TableId": 0, "TableName": "props", "TableKind": "QueryProperties", "Columns": []},
+            {"FrameType": "TableFragment", "TableId": 0, "TableFragmentType": "DataAppend", "Rows": [[0]]},
+            {"FrameType": "TableCompletion", "TableId": 0, "RowCount": 1},
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": [{"ColumnName": "x", "ColumnType": "long"}]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[1]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[2], [3]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[4]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[5]]},
+            {"FrameType": "TableCompletion", "TableId": 1, "RowCount": 4},
+            {"FrameType": "DataSetCompletion", "HasErrors": false, "Cancelled": false}
+        ]"#,
+        )
+        .await
+        .expect("Failed to stream rows");
+
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::from(2)],
+                vec![Value::from(3)],
+                vec![Value::from(4)],
+                vec![Value::from(5)]
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replace_after_streamed_rows_fails() {
+        let result = rows_of(
+            r#"[
+            {"FrameType": "TableHeader", "TableId": 1, "TableName": "a", "TableKind": "PrimaryResult", "Columns": []},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataAppend", "Rows": [[1]]},
+            {"FrameType": "TableFragment", "TableId": 1, "TableFragmentType": "DataReplace", "Rows": [[2]]}
+        ]"#,
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ProtocolError(
+                ProtocolError::ReplaceAfterStreamedRows { table_id: 1 },
+                _
+            ))
+        ));
+    }
+}
@@ -16,11 +16,21 @@ pub use crate::connection_string::{
     ConnectionString, ConnectionStringAuth, DeviceCodeFunction, TokenCallbackFunction,
 };
 pub use crate::error::Error;
-pub use crate::models::{DataTable, V2QueryResult};
-pub use crate::operations::query::{KustoResponse, KustoResponseDataSetV1, KustoResponseDataSetV2};
+pub use crate::kql::{Kql, ToKql};
+pub use crate::models::{DataTable, RowView, V2QueryResult};
+pub use crate::operations::cancellation::CancellationHandle;
+#[cfg(feature = "arrow")]
+pub use crate::operations::query::RecordBatchStream;
+pub use crate::operations::query::{
+    KustoResponse, KustoResponseDataSetV1, KustoResponseDataSetV2, PartialFailureMode, RequestIds,
+    V2QueryStream,
+};
+pub use crate::operations::rows::Row;
 pub use crate::request_options::{
     ClientRequestProperties, ClientRequestPropertiesBuilder, Options, OptionsBuilder,
 };
+pub use crate::row_deserializer::RowDeserializerOptions;
+pub use crate::types::{FromKustoValue, KustoValue};
 
 // Token credentials are re-exported for user convenience
 pub use azure_identity::{
@@ -1,11 +1,15 @@
 //! Request options for the Azure Data Explorer Client.
 
-use crate::types::{KustoDateTime, KustoDuration};
+use crate::error::{InvalidArgumentError, Result};
+use crate::kql::{is_decimal_literal, is_identifier};
+use crate::models::ColumnType;
+use crate::types::{KustoDateTime, KustoDuration, KustoValue};
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::Number;
 use serde_with::skip_serializing_none;
 use std::borrow::Cow;
+use uuid::Uuid;
 
 /// Controls the hot or cold cache for the scope of the query.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -59,6 +63,10 @@ pub struct ClientRequestProperties {
     /// Parameters to pass to the query.
     pub parameters: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip)]
+    /// The types of the parameters, in the order they were added, used to declare them in the query.
+    /// Parameters added with [add_parameter](ClientRequestProperties::add_parameter), or whose names are not valid identifiers, are not declared.
+    pub parameter_declarations: Option<Vec<(String, ColumnType)>>,
+    #[serde(skip)]
     /// Client request id.
     pub client_request_id: Option<String>,
     #[serde(skip)]
@@ -67,21 +75,28 @@ pub struct ClientRequestProperties {
     #[serde(skip)]
     /// User name for tracing.
     pub user: Option<String>,
+    #[serde(skip)]
+    /// Client side timeout for the request.
+    /// When it elapses, a query is cancelled with `.cancel query` and the request fails with [Error::TimeoutError](crate::error::Error::TimeoutError).
+    pub client_timeout: Option<std::time::Duration>,
 }
 
 impl ClientRequestProperties {
     /// Add a query parameter with a string value.
     pub fn add_string_parameter(&mut self, name: Cow<str>, value: Cow<str>) {
+        self.declare_parameter(&name, ColumnType::String);
         self.add_parameter(name, serde_json::Value::String(value.into()));
     }
 
     /// Add a query parameter with an integer value.
     pub fn add_i64_parameter(&mut self, name: Cow<str>, value: i64) {
+        self.declare_parameter(&name, ColumnType::Long);
         self.add_parameter(name, serde_json::Value::Number(value.into()));
     }
 
     /// Add a query parameter with a float value.
     pub fn add_f64_parameter(&mut self, name: Cow<str>, value: f64) {
+        self.declare_parameter(&name, ColumnType::Real);
         self.add_parameter(
             name,
             Number::from_f64(value)
@@ -92,9 +107,118 @@ impl ClientRequestProperties {
 
     /// Add a query parameter with a boolean value.
     pub fn add_bool_parameter(&mut self, name: Cow<str>, value: bool) {
+        self.declare_parameter(&name, ColumnType::Bool);
         self.add_parameter(name, serde_json::Value::Bool(value));
     }
 
+    /// Add a query parameter with a datetime value.
+    pub fn add_datetime_parameter(&mut self, name: Cow<str>, value: KustoDateTime) -> Result<()> {
+        self.add_typed_parameter(name, ColumnType::Datetime, value)
+    }
+
+    /// Add a query parameter with a timespan value.
+    pub fn add_timespan_parameter(&mut self, name: Cow<str>, value: KustoDuration) -> Result<()> {
+        self.add_typed_parameter(name, ColumnType::Timespan, value)
+    }
+
+    /// Add a query parameter with a guid value.
+    pub fn add_guid_parameter(&mut self, name: Cow<str>, value: Uuid) -> Result<()> {
+        self.add_typed_parameter(name, ColumnType::Guid, value)
+    }
+
+    /// Add a query parameter with a dynamic value.
+    pub fn add_dynamic_parameter(
+        &mut self,
+        name: Cow<str>,
+        value: serde_json::Value,
+    ) -> Result<()> {
+        self.add_typed_parameter(name, ColumnType::Dynamic, value)
+    }
+
+    /// Add a query parameter with a decimal value, given as its decimal representation (e.g. `"1.50"`).
+    ///
+    /// Fails if the value is not a valid decimal number.
+    pub fn add_decimal_parameter(&mut self, name: Cow<str>, value: Cow<str>) -> Result<()> {
+        self.add_typed_parameter(name, ColumnType::Decimal, KustoValue::Decimal(value.into()))
+    }
+
+    /// Add a query parameter of the given type, serialized as a Kusto literal (e.g. `datetime(2023-01-02T03:04:05Z)`).
+    ///
+    /// Fails if the name is not a valid identifier, if the value is not of the given type,
+    /// or if it is a decimal that is not a valid number.
+    /// Nulls are accepted for any type, and sent as the null of that type.
+    pub fn add_typed_parameter(
+        &mut self,
+        name: Cow<str>,
+        column_type: ColumnType,
+        value: impl Into<KustoValue>,
+    ) -> Result<()> {
+        if !is_identifier(&name) {
+            return Err(InvalidArgumentError::InvalidIdentifier(name.into()).into());
+        }
+
+        let value = value.into();
+        if let KustoValue::Decimal(d) = &value {
+            if !is_decimal_literal(d) {
+                return Err(InvalidArgumentError::InvalidDecimal(d.clone()).into());
+            }
+        }
+        let literal = match value {
+            // Strings are sent as they are, so the null of strings is the empty string itself.
+            KustoValue::Null if column_type == ColumnType::String => String::new(),
+            KustoValue::Null => KustoValue::typed_null_literal(&column_type),
+            KustoValue::String(s) if column_type == ColumnType::String => s,
+            value => match value.column_type() {
+                Some(actual) if actual != column_type => {
+                    return Err(InvalidArgumentError::TypeMismatch {
+                        name: name.into(),
+                        expected: column_type,
+                        actual,
+                    }
+                    .into())
+                }
+                _ => value.to_string(),
+            },
+        };
+
+        self.declare_parameter(&name, column_type);
+        self.add_parameter(name, serde_json::Value::String(literal));
+        Ok(())
+    }
+
+    /// The `declare query_parameters` statement for the typed parameters, terminated by a semicolon, if there are any.
+    /// Declarations whose names are not valid identifiers are skipped.
+    #[must_use]
+    pub fn parameter_declaration(&self) -> Option<String> {
+        let declarations = self
+            .parameter_declarations
+            .as_ref()?
+            .iter()
+            .filter(|(name, _)| is_identifier(name))
+            .map(|(name, column_type)| format!("{name}:{column_type}"))
+            .collect::<Vec<_>>();
+        if declarations.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "declare query_parameters({});",
+            declarations.join(", ")
+        ))
+    }
+
+    /// Records the type of a parameter, replacing any previous declaration with the same name.
+    /// Names that are not valid identifiers are not declared, as they cannot be used in the query.
+    fn declare_parameter(&mut self, name: &str, column_type: ColumnType) {
+        if !is_identifier(name) {
+            return;
+        }
+        let declarations = self.parameter_declarations.get_or_insert_with(Vec::new);
+        match declarations.iter_mut().find(|(n, _)| n == name) {
+            Some(declaration) => declaration.1 = column_type,
+            None => declarations.push((name.to_string(), column_type)),
+        }
+    }
+
     /// Add a query parameter with a generic value.
     pub fn add_parameter(&mut self, name: Cow<str>, value: serde_json::Value) {
         if self.parameters.is_none() {
@@ -242,3 +366,97 @@ pub struct Options {
     #[serde(flatten)]
     pub additional: HashMap<String, String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn typed_parameters() {
+        let mut properties = ClientRequestProperties::default();
+        properties.add_string_parameter("name".into(), "a \"b\"".into());
+        properties
+            .add_datetime_parameter("from".into(), datetime!(2023-01-02 03:04:05 UTC).into())
+            .expect("Failed to add datetime");
+        properties
+            .add_timespan_parameter("span".into(), time::Duration::minutes(90).into())
+            .expect("Failed to add timespan");
+        properties
+            .add_typed_parameter("id".into(), ColumnType::Guid, None::<Uuid>)
+            .expect("Failed to add null guid");
+        properties
+            .add_dynamic_parameter("tags".into(), serde_json::json!(["x"]))
+            .expect("Failed to add dynamic");
+        properties.add_i64_parameter("from".into(), 1);
+
+        let parameters = properties.parameters.as_ref().expect("No parameters");
+        assert_eq!(parameters["name"], "a \"b\"");
+        assert_eq!(parameters["span"], "time(01:30:00.0000000)");
+        assert_eq!(parameters["id"], "guid(null)");
+        assert_eq!(parameters["tags"], r#"dynamic(["x"])"#);
+        assert_eq!(parameters["from"], 1);
+        assert_eq!(
+            properties.parameter_declaration().as_deref(),
+            Some("declare query_parameters(name:string, from:long, span:timespan, id:guid, tags:dynamic);")
+        );
+    }
+
+    #[test]
+    fn typed_parameters_are_validated() {
+        let mut properties = ClientRequestProperties::default();
+        assert!(properties
+            .add_typed_parameter("n".into(), ColumnType::Long, "5")
+            .is_err());
+        assert!(properties
+            .add_typed_parameter("a b".into(), ColumnType::Long, 5_i64)
+            .is_err());
+        assert!(properties
+            .add_decimal_parameter("1a".into(), "1.5".into())
+            .is_err());
+        assert!(properties
+            .add_decimal_parameter("d".into(), "1) | take 1; (2".into())
+            .is_err());
+        assert!(properties.parameters.is_none());
+        assert_eq!(properties.parameter_declaration(), None);
+
+        properties
+            .add_datetime_parameter("from".into(), datetime!(2023-01-02 03:04:05 UTC).into())
+            .expect("Failed to add datetime");
+        assert_eq!(
+            properties.parameters.expect("No parameters")["from"],
+            "datetime(2023-01-02T03:04:05Z)"
+        );
+    }
+
+    #[test]
+    fn null_string_parameters_are_empty() {
+        let mut properties = ClientRequestProperties::default();
+        properties
+            .add_typed_parameter("s".into(), ColumnType::String, KustoValue::Null)
+            .expect("Failed to add null string");
+        assert_eq!(properties.parameters.expect("No parameters")["s"], "");
+    }
+
+    #[test]
+    fn invalid_parameter_names_are_not_declared() {
+        let name = "x:string); .drop table T; //";
+        let mut properties = ClientRequestProperties::default();
+        properties.add_string_parameter(name.into(), "a".into());
+        properties.add_i64_parameter(name.into(), 1);
+        properties.add_f64_parameter(name.into(), 1.5);
+        properties.add_bool_parameter(name.into(), true);
+        assert_eq!(properties.parameter_declaration(), None);
+
+        properties.add_bool_parameter("b".into(), true);
+        properties
+            .parameter_declarations
+            .as_mut()
+            .expect("No declarations")
+            .push((name.to_string(), ColumnType::String));
+        assert_eq!(
+            properties.parameter_declaration().as_deref(),
+            Some("declare query_parameters(b:bool);")
+        );
+    }
+}
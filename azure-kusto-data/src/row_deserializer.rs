@@ -0,0 +1,295 @@
+//! Deserialization of result rows into structs, matching struct fields to columns by name.
+
+use crate::error::Result;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, Error as _, IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserializer};
+use serde_json::Value;
+
+/// Options for matching the fields of a struct to the columns of a row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowDeserializerOptions {
+    /// Fail if a column has no matching field, or a field has no matching column.
+    /// Otherwise extra columns are ignored, and missing fields are handled by serde as usual (e.g. `Option` fields are `None`).
+    pub strict: bool,
+    /// Match column names to field names regardless of ASCII case.
+    pub case_insensitive: bool,
+}
+
+impl RowDeserializerOptions {
+    /// Sets whether to fail on missing or extra columns.
+    #[must_use]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Sets whether to match column names regardless of ASCII case.
+    #[must_use]
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+}
+
+/// A serde [Deserializer] presenting a row as a map from column names to values.
+///
+/// Structs are deserialized by matching their fields to the column names, maps get an entry for each column,
+/// and tuples and sequences are deserialized from the values by position.
+/// The values are either owned [Value]s or borrowed `&Value`s.
+pub struct RowDeserializer<'a, V = Value> {
+    column_names: Vec<&'a str>,
+    values: Vec<V>,
+    options: RowDeserializerOptions,
+}
+
+impl<'a, V> RowDeserializer<'a, V> {
+    /// Creates a deserializer for the values of a row, in the order of the column names.
+    pub fn new(
+        column_names: impl IntoIterator<Item = &'a str>,
+        values: Vec<V>,
+        options: RowDeserializerOptions,
+    ) -> Self {
+        Self {
+            column_names: column_names.into_iter().collect(),
+            values,
+            options,
+        }
+    }
+
+    fn check_len(&self) -> std::result::Result<(), serde_json::Error> {
+        if self.column_names.len() == self.values.len() {
+            Ok(())
+        } else {
+            Err(serde_json::Error::custom(format!(
+                "row has {} values for {} columns",
+                self.values.len(),
+                self.column_names.len()
+            )))
+        }
+    }
+}
+
+// The values are `I`, as `V` is taken by the visitors of the `Deserializer` methods.
+impl<'de, 'a, I: IntoDeserializer<'de, serde_json::Error>> Deserializer<'de>
+    for RowDeserializer<'a, I>
+{
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.check_len()?;
+        let entries = self
+            .column_names
+            .into_iter()
+            .map(str::to_string)
+            .zip(self.values);
+        let mut map = MapDeserializer::new(entries);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.check_len()?;
+        let options = self.options;
+        let keys = self
+            .column_names
+            .iter()
+            .map(|&column| {
+                let field = fields.iter().find(|&&f| {
+                    f == column || (options.case_insensitive && f.eq_ignore_ascii_case(column))
+                });
+                match field {
+                    Some(field) => Ok(Some(*field)),
+                    None if options.strict => Err(serde_json::Error::custom(format!(
+                        "column `{column}` has no matching field"
+                    ))),
+                    None => Ok(None),
+                }
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        if options.strict {
+            if let Some(missing) = fields.iter().find(|f| !keys.contains(&Some(**f))) {
+                return Err(serde_json::Error::custom(format!(
+                    "field `{missing}` has no matching column"
+                )));
+            }
+        }
+
+        // Columns without a matching field are dropped, so that they can't clash with a renamed field.
+        let entries = keys
+            .into_iter()
+            .zip(self.values)
+            .filter_map(|(key, value)| key.map(|k| (k, value)));
+        let mut map = MapDeserializer::new(entries);
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let mut seq = SeqDeserializer::new(self.values.into_iter());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct enum identifier ignored_any
+    }
+}
+
+/// Deserializes the values of a row, in the order of the column names, into `T`. See [RowDeserializer].
+pub fn from_row<'a, 'de, T: DeserializeOwned>(
+    column_names: impl IntoIterator<Item = &'a str>,
+    values: Vec<impl IntoDeserializer<'de, serde_json::Error>>,
+    options: RowDeserializerOptions,
+) -> Result<T> {
+    Ok(T::deserialize(RowDeserializer::new(
+        column_names,
+        values,
+        options,
+    ))?)
+}
+
+/// Deserializes the rows of a table, each holding the values in the order of the column names, into `T`s.
+pub(crate) fn from_rows<'a, 'de, T, R, V>(
+    column_names: impl IntoIterator<Item = &'a str>,
+    rows: impl IntoIterator<Item = Result<R>>,
+    options: RowDeserializerOptions,
+) -> Result<Vec<T>>
+where
+    T: DeserializeOwned,
+    R: IntoIterator<Item = V>,
+    V: IntoDeserializer<'de, serde_json::Error>,
+{
+    let column_names: Vec<&str> = column_names.into_iter().collect();
+    rows.into_iter()
+        .map(|row| {
+            from_row(
+                column_names.iter().copied(),
+                row?.into_iter().collect(),
+                options,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Person {
+        name: String,
+        age: u32,
+        #[serde(rename = "Nickname")]
+        nickname: Option<String>,
+    }
+
+    fn values() -> Vec<Value> {
+        vec![Value::from(42), Value::from("foo"), Value::from(true)]
+    }
+
+    #[test]
+    fn matches_fields_by_name() {
+        let person: Person = from_row(
+            ["age", "name", "extra"],
+            values(),
+            RowDeserializerOptions::default(),
+        )
+        .expect("Failed to deserialize");
+
+        assert_eq!(
+            person,
+            Person {
+                name: "foo".to_string(),
+                age: 42,
+                nickname: None
+            }
+        );
+    }
+
+    #[test]
+    fn matches_case_insensitive() {
+        let options = RowDeserializerOptions::default();
+        assert!(from_row::<Person>(["Age", "NAME"], values()[..2].to_vec(), options).is_err());
+
+        let person: Person = from_row(
+            ["Age", "NAME", "nickname"],
+            vec![Value::from(42), Value::from("foo"), Value::from("bar")],
+            options.with_case_insensitive(true),
+        )
+        .expect("Failed to deserialize");
+        assert_eq!(person.age, 42);
+        assert_eq!(person.nickname.as_deref(), Some("bar"));
+    }
+
+    #[test]
+    fn strict_fails_on_extra_or_missing_columns() {
+        let options = RowDeserializerOptions::default().with_strict(true);
+
+        assert!(from_row::<Person>(["age", "name", "extra"], values(), options).is_err());
+        assert!(from_row::<Person>(["age", "name"], values()[..2].to_vec(), options).is_err());
+
+        let person: Person = from_row(
+            ["age", "name", "Nickname"],
+            vec![Value::from(42), Value::from("foo"), Value::Null],
+            options,
+        )
+        .expect("Failed to deserialize");
+        assert_eq!(person.nickname, None);
+    }
+
+    #[test]
+    fn deserializes_maps_and_tuples() {
+        let map: HashMap<String, Value> =
+            from_row(["a", "b", "c"], values(), RowDeserializerOptions::default())
+                .expect("Failed to deserialize map");
+        assert_eq!(map["b"], Value::from("foo"));
+
+        let tuple: (u32, String, bool) =
+            from_row(["a", "b", "c"], values(), RowDeserializerOptions::default())
+                .expect("Failed to deserialize tuple");
+        assert_eq!(tuple, (42, "foo".to_string(), true));
+    }
+}
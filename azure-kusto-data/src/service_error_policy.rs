@@ -0,0 +1,66 @@
+use crate::error::KustoServiceError;
+use azure_core::headers::CLIENT_REQUEST_ID;
+use azure_core::{BytesStream, Context, Policy, PolicyResult, Request, Response};
+use std::sync::{Arc, Mutex};
+
+/// Holds the last [KustoServiceError] seen by [ServiceErrorPolicy] for a request.
+///
+/// The retry policy turns failed responses into opaque errors, so the body has to be parsed
+/// before it reaches it. Inserting a slot into the request [Context] lets the caller retrieve the parsed error afterwards.
+#[derive(Debug, Default)]
+pub(crate) struct ServiceErrorSlot(Mutex<Option<KustoServiceError>>);
+
+impl ServiceErrorSlot {
+    /// Takes the last error stored in the slot, if any.
+    pub(crate) fn take(&self) -> Option<KustoServiceError> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .take()
+    }
+
+    fn set(&self, error: KustoServiceError) {
+        *self
+            .0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(error);
+    }
+}
+
+/// Policy that parses the body of failed responses into a [KustoServiceError], storing it in the [ServiceErrorSlot] of the request.
+/// The response is passed on unchanged, so retries behave as usual.
+#[derive(Debug, Default)]
+pub struct ServiceErrorPolicy;
+
+#[async_trait::async_trait]
+impl Policy for ServiceErrorPolicy {
+    async fn send(
+        &self,
+        ctx: &Context,
+        request: &mut Request,
+        next: &[Arc<dyn Policy>],
+    ) -> PolicyResult {
+        let response = next[0].send(ctx, request, &next[1..]).await?;
+
+        let slot = match ctx.get::<ServiceErrorSlot>() {
+            Some(slot) if !response.status().is_success() => slot,
+            _ => return Ok(response),
+        };
+
+        let (status, headers, body) = response.deconstruct();
+        let body = body.collect().await?;
+
+        slot.set(KustoServiceError::from_response(
+            status,
+            &headers,
+            &body,
+            request.headers().get_optional_string(&CLIENT_REQUEST_ID),
+        ));
+
+        Ok(Response::new(
+            status,
+            headers,
+            Box::pin(BytesStream::new(body)),
+        ))
+    }
+}
@@ -0,0 +1,392 @@
+//! Statistics of a query, parsed from the `QueryCompletionInformation` and `QueryProperties` tables of a V2 response.
+//!
+//! See [KustoResponseDataSetV2::statistics](crate::operations::query::KustoResponseDataSetV2::statistics).
+
+use crate::error::Result;
+use crate::models::QueryCompletionInformation;
+use crate::types::{KustoDateTime, KustoDuration};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// The name of the `QueryCompletionInformation` event holding the resource consumption of the query.
+const RESOURCE_CONSUMPTION_EVENT: &str = "QueryResourceConsumption";
+
+/// Statistics of a query.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct QueryStatistics {
+    /// The resources used by the query, if reported.
+    pub resource_consumption: Option<QueryResourceConsumption>,
+    /// The properties of the results, from the `@ExtendedProperties` table.
+    pub properties: Vec<QueryProperty>,
+}
+
+impl QueryStatistics {
+    /// Collects the statistics from the completion events and the properties of a response.
+    pub(crate) fn new(
+        completion_information: &[QueryCompletionInformation],
+        properties: Vec<QueryProperty>,
+    ) -> Result<Self> {
+        let resource_consumption = completion_information
+            .iter()
+            .find(|event| event.event_type_name == RESOURCE_CONSUMPTION_EVENT)
+            .map(|event| serde_json::from_str(&event.payload))
+            .transpose()?;
+        Ok(Self {
+            resource_consumption,
+            properties,
+        })
+    }
+
+    /// The visualization requested by the `render` operator of the query, for the table with the given id.
+    pub fn visualization(&self, table_id: i32) -> Result<Option<Visualization>> {
+        self.property(table_id, "Visualization")
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()
+            .map_err(Into::into)
+    }
+
+    /// The cursor of the database when the query ran, for the table with the given id, if requested with the `cursor_current()` function.
+    #[must_use]
+    pub fn cursor(&self, table_id: i32) -> Option<&str> {
+        self.property(table_id, "Cursor")
+            .and_then(|value| value.get("Cursor"))
+            .and_then(Value::as_str)
+    }
+
+    /// The value of a property of the table with the given id.
+    #[must_use]
+    pub fn property(&self, table_id: i32, key: &str) -> Option<&Value> {
+        self.properties
+            .iter()
+            .find(|p| p.table_id == table_id && p.key == key)
+            .map(|p| &p.value)
+    }
+}
+
+/// A row of the `@ExtendedProperties` table, holding a property of a result table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct QueryProperty {
+    /// Id of the table the property applies to.
+    pub table_id: i32,
+    /// Name of the property, e.g. `Visualization` or `Cursor`.
+    pub key: String,
+    /// Value of the property. Values sent as JSON text are parsed.
+    #[serde(deserialize_with = "json_or_text")]
+    pub value: Value,
+}
+
+/// Deserializes a dynamic value, parsing it if it was sent as JSON text.
+fn json_or_text<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Value, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => serde_json::from_str(&s).unwrap_or(Value::String(s)),
+        value => value,
+    })
+}
+
+/// The visualization requested by the `render` operator.
+/// See [the docs](https://learn.microsoft.com/en-us/azure/data-explorer/kusto/query/render-operator).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Visualization {
+    /// The kind of visualization, e.g. `timechart` or `table`.
+    pub visualization: Option<String>,
+    /// Title of the visualization.
+    pub title: Option<String>,
+    /// Column used for the x-axis.
+    pub x_column: Option<String>,
+    /// Columns whose values define the series.
+    pub series: Option<String>,
+    /// Columns used for the y-axis.
+    pub y_columns: Option<String>,
+    /// Title of the x-axis.
+    pub x_title: Option<String>,
+    /// Title of the y-axis.
+    pub y_title: Option<String>,
+    /// Scale of the x-axis, `linear` or `log`.
+    pub x_axis: Option<String>,
+    /// Scale of the y-axis, `linear` or `log`.
+    pub y_axis: Option<String>,
+    /// Whether to show a legend, `visible` or `hidden`.
+    pub legend: Option<String>,
+    /// How multiple y-axes are displayed.
+    pub y_split: Option<String>,
+    /// Whether values are accumulated.
+    pub accumulate: bool,
+    /// Whether the results are sorted.
+    pub is_query_sorted: bool,
+    /// Sub-kind of the visualization, e.g. `stacked`.
+    pub kind: Option<String>,
+}
+
+/// The resources used by a query, from the `QueryResourceConsumption` event.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct QueryResourceConsumption {
+    /// Duration of the query on the service, in seconds.
+    #[serde(rename = "ExecutionTime")]
+    pub execution_time: f64,
+    /// CPU, memory, cache and network usage.
+    pub resource_usage: ResourceUsage,
+    /// How much of the queried data was scanned.
+    pub input_dataset_statistics: InputDatasetStatistics,
+    /// Sizes of the result tables.
+    pub dataset_statistics: Vec<DatasetStatistics>,
+}
+
+/// CPU, memory, cache and network usage of a query.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct ResourceUsage {
+    /// Cache hits and misses.
+    pub cache: CacheUsage,
+    /// CPU time.
+    pub cpu: CpuUsage,
+    /// Memory usage.
+    pub memory: MemoryUsage,
+    /// Network usage.
+    pub network: NetworkUsage,
+}
+
+/// Cache hits and misses of a query.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct CacheUsage {
+    /// Memory cache.
+    pub memory: CacheHits,
+    /// Disk cache.
+    pub disk: CacheHits,
+    /// Shard cache, in bytes.
+    pub shards: ShardCacheUsage,
+}
+
+/// Hits and misses of a cache.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct CacheHits {
+    /// Number of hits.
+    pub hits: u64,
+    /// Number of misses.
+    pub misses: u64,
+    /// Total number of accesses.
+    pub total: u64,
+}
+
+/// Hits and misses of the shard cache, for hot and cold data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ShardCacheUsage {
+    /// Hot data.
+    pub hot: ShardCacheBytes,
+    /// Cold data.
+    pub cold: ShardCacheBytes,
+    /// Bytes read without going through the cache.
+    pub bypassbytes: u64,
+}
+
+/// Bytes read from the shard cache.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ShardCacheBytes {
+    /// Bytes found in the cache.
+    pub hitbytes: u64,
+    /// Bytes not found in the cache.
+    pub missbytes: u64,
+    /// Bytes retrieved from storage.
+    pub retrievebytes: u64,
+}
+
+/// CPU time of a query. Times that the service reports in an unexpected format are [None].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct CpuUsage {
+    /// User mode CPU time.
+    #[serde(deserialize_with = "lenient_duration")]
+    pub user: Option<KustoDuration>,
+    /// Kernel mode CPU time.
+    #[serde(deserialize_with = "lenient_duration")]
+    pub kernel: Option<KustoDuration>,
+    /// Total CPU time.
+    #[serde(
+        rename = "total cpu",
+        alias = "totalcpu",
+        deserialize_with = "lenient_duration"
+    )]
+    pub total_cpu: Option<KustoDuration>,
+}
+
+/// Deserializes a duration, ignoring spaces (e.g. in `00: 00: 01`), and returning [None] for invalid values,
+/// including values that are not strings.
+fn lenient_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<KustoDuration>, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(s) => s.replace(' ', "").parse().ok(),
+        _ => None,
+    })
+}
+
+/// Memory usage of a query.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct MemoryUsage {
+    /// Peak memory used on a single node, in bytes.
+    pub peak_per_node: u64,
+}
+
+/// Network usage of a query.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct NetworkUsage {
+    /// Bytes sent between the nodes of the cluster.
+    pub inter_cluster_total_bytes: u64,
+    /// Bytes sent to or received from other clusters.
+    pub cross_cluster_total_bytes: u64,
+}
+
+/// How much of the queried data was scanned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct InputDatasetStatistics {
+    /// Extents (data shards).
+    pub extents: ExtentStatistics,
+    /// Rows.
+    pub rows: RowStatistics,
+    /// Shard queries.
+    pub shards: ShardQueryStatistics,
+}
+
+/// How many extents were scanned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ExtentStatistics {
+    /// Extents of the queried tables.
+    pub total: u64,
+    /// Extents scanned by the query.
+    pub scanned: u64,
+    /// Earliest ingestion time of the scanned extents.
+    pub scanned_min_datetime: Option<KustoDateTime>,
+    /// Latest ingestion time of the scanned extents.
+    pub scanned_max_datetime: Option<KustoDateTime>,
+}
+
+/// How many rows were scanned.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct RowStatistics {
+    /// Rows of the queried tables.
+    pub total: u64,
+    /// Rows scanned by the query.
+    pub scanned: u64,
+}
+
+/// How shard queries were run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ShardQueryStatistics {
+    /// Shard queries run by the generic engine.
+    pub queries_generic: u64,
+    /// Shard queries run by specialized engines.
+    pub queries_specialized: u64,
+}
+
+/// The size of a result table.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct DatasetStatistics {
+    /// Number of rows.
+    pub table_row_count: u64,
+    /// Size in bytes.
+    pub table_size: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn statistics(properties: Value) -> QueryStatistics {
+        QueryStatistics {
+            resource_consumption: None,
+            properties: serde_json::from_value(properties).expect("Failed to parse properties"),
+        }
+    }
+
+    #[test]
+    fn properties_sent_as_text_are_parsed() {
+        let statistics = statistics(json!([
+            {"TableId": 0, "Key": "Visualization", "Value": "{\"Visualization\":\"timechart\",\"Accumulate\":true}"},
+            {"TableId": 1, "Key": "Visualization", "Value": {"Visualization": "table"}},
+            {"TableId": 2, "Key": "Note", "Value": "not json"}
+        ]));
+
+        let visualization = statistics
+            .visualization(0)
+            .expect("Failed to parse visualization")
+            .expect("No visualization");
+        assert_eq!(visualization.visualization.as_deref(), Some("timechart"));
+        assert!(visualization.accumulate);
+        assert_eq!(
+            statistics
+                .visualization(1)
+                .expect("Failed to parse visualization")
+                .and_then(|v| v.visualization)
+                .as_deref(),
+            Some("table")
+        );
+        assert_eq!(statistics.property(2, "Note"), Some(&json!("not json")));
+        assert!(statistics
+            .visualization(3)
+            .expect("Failed to parse visualization")
+            .is_none());
+    }
+
+    #[test]
+    fn cursor() {
+        let statistics = statistics(json!([
+            {"TableId": 0, "Key": "Cursor", "Value": "{\"Cursor\":\"638400000000000000\"}"},
+            {"TableId": 1, "Key": "Cursor", "Value": {"Cursor": 1}}
+        ]));
+
+        assert_eq!(statistics.cursor(0), Some("638400000000000000"));
+        assert_eq!(statistics.cursor(1), None);
+        assert_eq!(statistics.cursor(2), None);
+    }
+
+    #[test]
+    fn cpu_usage() {
+        let second = Some(KustoDuration::from(time::Duration::seconds(1)));
+
+        let usage: CpuUsage = serde_json::from_value(json!({
+            "user": "00: 00: 01",
+            "kernel": "00:00:00",
+            "total cpu": "00:00:01"
+        }))
+        .expect("Failed to parse cpu usage");
+        assert_eq!(usage.user, second);
+        assert_eq!(
+            usage.kernel,
+            Some(KustoDuration::from(time::Duration::ZERO))
+        );
+        assert_eq!(usage.total_cpu, second);
+
+        let usage: CpuUsage = serde_json::from_value(json!({"totalcpu": "00:00:01"}))
+            .expect("Failed to parse cpu usage");
+        assert_eq!(usage.total_cpu, second);
+        assert_eq!(usage.user, None);
+    }
+
+    #[test]
+    fn invalid_cpu_usage_is_none() {
+        let consumption: QueryResourceConsumption = serde_json::from_value(json!({
+            "ExecutionTime": 0.5,
+            "resource_usage": {"cpu": {"user": 1, "kernel": "soon", "total cpu": null}}
+        }))
+        .expect("Failed to parse resource consumption");
+
+        assert_eq!(consumption.execution_time, 0.5);
+        assert_eq!(consumption.resource_usage.cpu, CpuUsage::default());
+    }
+}
@@ -10,7 +10,11 @@ use std::str::FromStr;
 use time::{Duration, OffsetDateTime};
 
 use crate::error::{Error, InvalidArgumentError};
+use crate::kql;
+use crate::models::ColumnType;
+use serde_json::Value;
 use time::format_description::well_known::Rfc3339;
+use uuid::Uuid;
 
 /// Represents a datetime field for kusto, for serialization and deserialization.
 #[derive(PartialEq, Eq, Copy, Clone, DeserializeFromStr, SerializeDisplay)]
@@ -145,6 +149,299 @@ impl Debug for KustoDuration {
     }
 }
 
+/// A single value of a result, typed according to the [ColumnType] of its column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KustoValue {
+    /// A null value, which any column type can hold.
+    Null,
+    /// A `bool` value.
+    Bool(bool),
+    /// An `int` value.
+    Int(i32),
+    /// A `long` value.
+    Long(i64),
+    /// A `real` value.
+    Real(f64),
+    /// A `decimal` value, in its textual representation to preserve its precision.
+    Decimal(String),
+    /// A `string` value.
+    String(String),
+    /// A `datetime` value.
+    Datetime(KustoDateTime),
+    /// A `timespan` value.
+    Timespan(KustoDuration),
+    /// A `guid` value.
+    Guid(Uuid),
+    /// A `dynamic` value.
+    Dynamic(Value),
+}
+
+impl KustoValue {
+    /// Converts a value of a response, as encoded in JSON, into a value of the given column type.
+    pub fn from_json(value: Value, column_type: &ColumnType) -> Result<Self, Error> {
+        let invalid = |value: &Value| {
+            Error::ConversionError(format!("{value} is not a valid {column_type:?}"))
+        };
+        Ok(match (column_type, value) {
+            (_, Value::Null) => Self::Null,
+            (ColumnType::Bool, Value::Bool(b)) => Self::Bool(b),
+            (ColumnType::Bool, Value::Number(n)) if n.as_u64() == Some(0) => Self::Bool(false),
+            (ColumnType::Bool, Value::Number(n)) if n.as_u64() == Some(1) => Self::Bool(true),
+            (ColumnType::Int, Value::Number(n)) => Self::Int(
+                n.as_i64()
+                    .and_then(|n| i32::try_from(n).ok())
+                    .ok_or_else(|| invalid(&Value::Number(n)))?,
+            ),
+            (ColumnType::Long, Value::Number(n)) => {
+                Self::Long(n.as_i64().ok_or_else(|| invalid(&Value::Number(n)))?)
+            }
+            (ColumnType::Real, Value::Number(n)) => {
+                Self::Real(n.as_f64().ok_or_else(|| invalid(&Value::Number(n)))?)
+            }
+            (ColumnType::Real, Value::String(s)) => match s.as_str() {
+                "NaN" => Self::Real(f64::NAN),
+                "Infinity" => Self::Real(f64::INFINITY),
+                "-Infinity" => Self::Real(f64::NEG_INFINITY),
+                _ => return Err(invalid(&Value::String(s))),
+            },
+            (ColumnType::Decimal, Value::String(s)) => Self::Decimal(s),
+            (ColumnType::Decimal, Value::Number(n)) => Self::Decimal(n.to_string()),
+            (ColumnType::String, Value::String(s)) => Self::String(s),
+            (ColumnType::Datetime, Value::String(s)) => Self::Datetime(s.parse()?),
+            (ColumnType::Timespan, Value::String(s)) => Self::Timespan(s.parse()?),
+            (ColumnType::Guid, Value::String(s)) => {
+                Self::Guid(Uuid::parse_str(&s).map_err(|_| invalid(&Value::String(s.clone())))?)
+            }
+            (ColumnType::Dynamic, value) => Self::Dynamic(value),
+            (_, value) => return Err(invalid(&value)),
+        })
+    }
+
+    /// The column type of the value, or [None] for [KustoValue::Null].
+    #[must_use]
+    pub fn column_type(&self) -> Option<ColumnType> {
+        Some(match self {
+            Self::Null => return None,
+            Self::Bool(_) => ColumnType::Bool,
+            Self::Int(_) => ColumnType::Int,
+            Self::Long(_) => ColumnType::Long,
+            Self::Real(_) => ColumnType::Real,
+            Self::Decimal(_) => ColumnType::Decimal,
+            Self::String(_) => ColumnType::String,
+            Self::Datetime(_) => ColumnType::Datetime,
+            Self::Timespan(_) => ColumnType::Timespan,
+            Self::Guid(_) => ColumnType::Guid,
+            Self::Dynamic(_) => ColumnType::Dynamic,
+        })
+    }
+
+    /// The KQL literal of a null of the given type, e.g. `long(null)`.
+    /// Strings cannot be null in KQL, so the null of strings is the empty string.
+    #[must_use]
+    pub fn typed_null_literal(column_type: &ColumnType) -> String {
+        match column_type {
+            ColumnType::String => "\"\"".to_string(),
+            column_type => format!("{column_type}(null)"),
+        }
+    }
+
+    /// Whether the value is [KustoValue::Null].
+    #[must_use]
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    /// Converts the value into JSON, in the same encoding as values of responses.
+    /// Decimals are encoded as strings to preserve their precision, as are non finite reals.
+    #[must_use]
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Null => Value::Null,
+            Self::Bool(b) => Value::Bool(*b),
+            Self::Int(i) => Value::from(*i),
+            Self::Long(l) => Value::from(*l),
+            Self::Real(r) if r.is_nan() => Value::from("NaN"),
+            Self::Real(r) if r.is_infinite() && r.is_sign_positive() => Value::from("Infinity"),
+            Self::Real(r) if r.is_infinite() => Value::from("-Infinity"),
+            Self::Real(r) => Value::from(*r),
+            Self::Decimal(s) | Self::String(s) => Value::from(s.as_str()),
+            Self::Datetime(d) => Value::from(d.to_string()),
+            Self::Timespan(t) => Value::from(t.to_string()),
+            Self::Guid(g) => Value::from(g.to_string()),
+            Self::Dynamic(v) => v.clone(),
+        }
+    }
+}
+
+/// Formats the value as a KQL literal, e.g. `datetime(2023-01-02T03:04:05Z)` or `"escaped \"string\""`.
+/// Nulls are formatted as `dynamic(null)`, use [KustoValue::typed_null_literal] for nulls of other types.
+/// Decimals that are not valid numbers are formatted with `todecimal()`, so that any value is a safe literal. See [crate::kql].
+impl Display for KustoValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Null => f.write_str("dynamic(null)"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Int(i) => write!(f, "int({i})"),
+            Self::Long(l) => write!(f, "long({l})"),
+            Self::Real(r) if r.is_nan() => f.write_str("real(nan)"),
+            Self::Real(r) if r.is_infinite() && r.is_sign_positive() => f.write_str("real(+inf)"),
+            Self::Real(r) if r.is_infinite() => f.write_str("real(-inf)"),
+            Self::Real(r) => write!(f, "real({r:?})"),
+            Self::Decimal(d) if kql::is_decimal_literal(d) => write!(f, "decimal({d})"),
+            Self::Decimal(d) => write!(f, "todecimal({})", kql::string(d)),
+            Self::String(s) => write!(f, "{}", kql::string(s)),
+            Self::Datetime(d) => write!(f, "datetime({d})"),
+            Self::Timespan(t) => write!(f, "time({t})"),
+            Self::Guid(g) => write!(f, "guid({g})"),
+            Self::Dynamic(v) => write!(f, "dynamic({v})"),
+        }
+    }
+}
+
+impl<T: Into<KustoValue>> From<Option<T>> for KustoValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+/// Conversion of a [KustoValue] into a Rust type, used by typed accessors such as [DataTable::get](crate::models::DataTable::get).
+///
+/// Null values can only be converted into `Option`s and [KustoValue] itself.
+pub trait FromKustoValue: Sized {
+    /// Converts the value, failing if it is of another type.
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error>;
+}
+
+impl FromKustoValue for KustoValue {
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+        Ok(value)
+    }
+}
+
+impl<T: FromKustoValue> FromKustoValue for Option<T> {
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+        match value {
+            KustoValue::Null => Ok(None),
+            value => T::from_kusto_value(value).map(Some),
+        }
+    }
+}
+
+/// Conversion of a value of a narrower Kusto type, failing if it can't be represented exactly.
+trait Widen<T> {
+    fn widen(self) -> Option<T>;
+}
+
+impl Widen<i64> for i32 {
+    fn widen(self) -> Option<i64> {
+        Some(self.into())
+    }
+}
+
+impl Widen<f64> for i32 {
+    fn widen(self) -> Option<f64> {
+        Some(self.into())
+    }
+}
+
+/// Longs of more than 53 significant bits are rounded by the cast, and are rejected.
+impl Widen<f64> for i64 {
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn widen(self) -> Option<f64> {
+        let widened = self as f64;
+        // Compared as i128, as the cast back into an i64 saturates at i64::MAX, which itself rounds up to 2^63.
+        (widened as i128 == i128::from(self)).then_some(widened)
+    }
+}
+
+macro_rules! kusto_value_conversions {
+    ($($type:ty => $variant:ident $(| $widened:ident)*),* $(,)?) => {
+        $(
+            impl From<$type> for KustoValue {
+                fn from(value: $type) -> Self {
+                    Self::$variant(value)
+                }
+            }
+
+            impl FromKustoValue for $type {
+                fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+                    match value {
+                        KustoValue::$variant(v) => Ok(v),
+                        $(KustoValue::$widened(v) => v.widen().ok_or_else(|| {
+                            Error::ConversionError(format!(
+                                "{:?} can't be converted into a {} without loss of precision",
+                                KustoValue::$widened(v),
+                                stringify!($variant)
+                            ))
+                        }),)*
+                        value => Err(Error::ConversionError(format!(
+                            "{value:?} is not a {}",
+                            stringify!($variant)
+                        ))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+kusto_value_conversions! {
+    bool => Bool,
+    i32 => Int,
+    i64 => Long | Int,
+    f64 => Real | Int | Long,
+    KustoDateTime => Datetime,
+    KustoDuration => Timespan,
+    Uuid => Guid,
+    Value => Dynamic,
+}
+
+impl From<String> for KustoValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+/// Decimals are converted into their textual representation.
+impl FromKustoValue for String {
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+        match value {
+            KustoValue::String(s) | KustoValue::Decimal(s) => Ok(s),
+            value => Err(Error::ConversionError(format!("{value:?} is not a String"))),
+        }
+    }
+}
+
+impl FromKustoValue for OffsetDateTime {
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+        KustoDateTime::from_kusto_value(value).map(|d| d.0)
+    }
+}
+
+impl FromKustoValue for Duration {
+    fn from_kusto_value(value: KustoValue) -> Result<Self, Error> {
+        KustoDuration::from_kusto_value(value).map(|d| d.0)
+    }
+}
+
+impl From<&str> for KustoValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<OffsetDateTime> for KustoValue {
+    fn from(value: OffsetDateTime) -> Self {
+        Self::Datetime(value.into())
+    }
+}
+
+impl From<Duration> for KustoValue {
+    fn from(value: Duration) -> Self {
+        Self::Timespan(value.into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -189,4 +486,75 @@ mod tests {
             assert_eq!(format!("{:?}", parsed), duration);
         }
     }
+
+    #[test]
+    fn kusto_value_from_json() {
+        let value =
+            |v: Value, t: ColumnType| KustoValue::from_json(v, &t).expect("Failed to convert");
+
+        assert_eq!(value(Value::Null, ColumnType::Long), KustoValue::Null);
+        assert_eq!(value(Value::from(7), ColumnType::Int), KustoValue::Int(7));
+        assert_eq!(
+            value(Value::from("1.5"), ColumnType::Decimal),
+            KustoValue::Decimal("1.5".to_string())
+        );
+        assert_eq!(
+            value(Value::from("01:00:00"), ColumnType::Timespan),
+            KustoValue::Timespan(Duration::hours(1).into())
+        );
+        assert!(
+            matches!(value(Value::from("NaN"), ColumnType::Real), KustoValue::Real(r) if r.is_nan())
+        );
+        assert!(KustoValue::from_json(Value::from(1_i64 << 40), &ColumnType::Int).is_err());
+        assert!(KustoValue::from_json(Value::from("x"), &ColumnType::Guid).is_err());
+    }
+
+    #[test]
+    fn from_kusto_value() {
+        assert_eq!(i64::from_kusto_value(KustoValue::Int(3)).unwrap(), 3);
+        assert_eq!(
+            Option::<i64>::from_kusto_value(KustoValue::Null).unwrap(),
+            None
+        );
+        assert!(i64::from_kusto_value(KustoValue::Null).is_err());
+        assert!(String::from_kusto_value(KustoValue::Long(1)).is_err());
+        assert_eq!(
+            f64::from_kusto_value(KustoValue::Long(1 << 53)).unwrap(),
+            9_007_199_254_740_992.0
+        );
+        assert!(f64::from_kusto_value(KustoValue::Long((1 << 53) + 1)).is_err());
+        assert!(f64::from_kusto_value(KustoValue::Long(i64::MAX)).is_err());
+        assert_eq!(
+            f64::from_kusto_value(KustoValue::Long(i64::MIN)).unwrap(),
+            -9.223_372_036_854_776e18
+        );
+        assert_eq!(
+            KustoValue::from(Some("a")),
+            KustoValue::String("a".to_string())
+        );
+        assert_eq!(KustoValue::from(None::<i32>), KustoValue::Null);
+    }
+
+    #[test]
+    fn kusto_value_literals() {
+        assert_eq!(KustoValue::from(5).to_string(), "int(5)");
+        assert_eq!(KustoValue::from(1.0).to_string(), "real(1.0)");
+        assert_eq!(
+            KustoValue::from(f64::NEG_INFINITY).to_string(),
+            "real(-inf)"
+        );
+        assert_eq!(
+            KustoValue::from("a\"\\\n\u{1}").to_string(),
+            r#""a\"\\\n\u0001""#
+        );
+        assert_eq!(
+            KustoValue::from(serde_json::json!({"a": 1})).to_string(),
+            r#"dynamic({"a":1})"#
+        );
+        assert_eq!(KustoValue::Null.to_string(), "dynamic(null)");
+        assert_eq!(
+            KustoValue::typed_null_literal(&ColumnType::Long),
+            "long(null)"
+        );
+    }
 }
@@ -61,7 +61,11 @@ async fn create_query_delete_table() {
         .await
         .expect("Failed to run query");
 
-    let results = response.into_primary_results().next().expect("No results");
+    let results = response
+        .into_primary_results()
+        .next()
+        .expect("No results")
+        .expect("Failed to parse results");
 
     let rows = results.rows;
 
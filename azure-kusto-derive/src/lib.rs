@@ -0,0 +1,213 @@
+#![warn(missing_docs)]
+
+//! # Derive macros for Azure Data Explorer
+//! Provides `#[derive(KustoRow)]`, implementing `azure_kusto_data::kusto_row::KustoRow` for structs with named fields.
+//!
+//! Each field maps to a column, named after the field and typed after the type of the field.
+//! Fields can be configured with the `kusto` attribute:
+//! - `#[kusto(rename = "Name")]` sets the name of the column.
+//! - `#[kusto(column_type = "decimal")]` sets the type of the column, which the field is converted into when encoding rows.
+//!   It is required for types other than `bool`, `i32`, `i64`, `f64`, `String`, datetimes, durations, `Uuid` and `serde_json::Value`.
+//! - `#[kusto(optional)]` allows the column to be missing from results, leaving the field at its default.
+//!
+//! `Option` fields have the column type of their inner type, and are `None` for nulls.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, LitStr, PathArguments,
+    Result, Type,
+};
+
+/// Derives `KustoRow` for a struct with named fields. See the [crate] documentation for the supported attributes.
+#[proc_macro_derive(KustoRow, attributes(kusto))]
+pub fn derive_kusto_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The column a field maps to.
+struct FieldColumn {
+    ident: syn::Ident,
+    name: String,
+    column_type: TokenStream2,
+    optional: bool,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "KustoRow can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                input,
+                "KustoRow can only be derived for structs",
+            ))
+        }
+    };
+
+    let columns = fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.clone().expect("named fields have identifiers");
+            let mut name = ident.to_string().trim_start_matches("r#").to_string();
+            let mut column_type = None;
+            let mut optional = false;
+
+            for attr in field.attrs.iter().filter(|a| a.path().is_ident("kusto")) {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        name = meta.value()?.parse::<LitStr>()?.value();
+                    } else if meta.path.is_ident("column_type") {
+                        let lit = meta.value()?.parse::<LitStr>()?;
+                        column_type = Some(parse_column_type(&lit.value(), lit.span())?);
+                    } else if meta.path.is_ident("optional") {
+                        optional = true;
+                    } else {
+                        return Err(meta.error("unknown kusto attribute"));
+                    }
+                    Ok(())
+                })?;
+            }
+
+            let column_type = match column_type {
+                Some(column_type) => column_type,
+                None => infer_column_type(&field.ty)?,
+            };
+            Ok(FieldColumn {
+                ident,
+                name,
+                column_type,
+                optional,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let column_defs = columns.iter().map(|c| {
+        let name = &c.name;
+        let column_type = &c.column_type;
+        quote! {
+            ::azure_kusto_data::models::Column {
+                column_name: ::std::string::ToString::to_string(#name),
+                column_type: ::azure_kusto_data::models::ColumnType::#column_type,
+            }
+        }
+    });
+    let optional = columns.iter().map(|c| c.optional);
+    let field_values = columns.iter().map(|c| {
+        let ident = &c.ident;
+        let name = &c.name;
+        let convert = if c.optional {
+            quote!(::azure_kusto_data::kusto_row::optional_field_value)
+        } else {
+            quote!(::azure_kusto_data::kusto_row::field_value)
+        };
+        quote! {
+            #ident: #convert(::std::iter::Iterator::next(&mut values).flatten(), #name)?
+        }
+    });
+    let to_values = columns.iter().map(|c| {
+        let ident = &c.ident;
+        let column_type = &c.column_type;
+        quote! {
+            ::azure_kusto_data::kusto_row::column_value(
+                ::std::clone::Clone::clone(&self.#ident),
+                &::azure_kusto_data::models::ColumnType::#column_type,
+            )
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::azure_kusto_data::kusto_row::KustoRow for #ident #ty_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<::azure_kusto_data::models::Column> {
+                ::std::vec![#(#column_defs),*]
+            }
+
+            fn is_optional(index: usize) -> bool {
+                const OPTIONAL: &[bool] = &[#(#optional),*];
+                OPTIONAL.get(index).copied().unwrap_or(false)
+            }
+
+            fn from_values(
+                values: ::std::vec::Vec<::std::option::Option<::azure_kusto_data::types::KustoValue>>,
+            ) -> ::azure_kusto_data::error::Result<Self> {
+                let mut values = ::std::iter::IntoIterator::into_iter(values);
+                ::std::result::Result::Ok(Self {
+                    #(#field_values),*
+                })
+            }
+
+            fn to_values(&self) -> ::std::vec::Vec<::azure_kusto_data::types::KustoValue> {
+                ::std::vec![#(#to_values),*]
+            }
+        }
+    })
+}
+
+/// Parses the name of a column type, accepting the same names as the service.
+fn parse_column_type(name: &str, span: Span) -> Result<TokenStream2> {
+    Ok(match name.to_ascii_lowercase().as_str() {
+        "bool" | "boolean" => quote!(Bool),
+        "datetime" | "date" => quote!(Datetime),
+        "dynamic" => quote!(Dynamic),
+        "guid" | "uuid" => quote!(Guid),
+        "int" => quote!(Int),
+        "long" => quote!(Long),
+        "real" | "double" => quote!(Real),
+        "string" => quote!(String),
+        "timespan" | "time" => quote!(Timespan),
+        "decimal" => quote!(Decimal),
+        _ => return Err(Error::new(span, format!("unknown column type `{name}`"))),
+    })
+}
+
+/// Infers the column type of a field from its type, looking through `Option`.
+fn infer_column_type(ty: &Type) -> Result<TokenStream2> {
+    let segment = match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    };
+    let Some(segment) = segment else {
+        return Err(cannot_infer(ty));
+    };
+
+    if segment.ident == "Option" {
+        if let PathArguments::AngleBracketed(args) = &segment.arguments {
+            if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                return infer_column_type(inner);
+            }
+        }
+    }
+
+    Ok(match segment.ident.to_string().as_str() {
+        "bool" => quote!(Bool),
+        "i32" => quote!(Int),
+        "i64" => quote!(Long),
+        "f64" => quote!(Real),
+        "String" => quote!(String),
+        "KustoDateTime" | "OffsetDateTime" => quote!(Datetime),
+        "KustoDuration" | "Duration" => quote!(Timespan),
+        "Uuid" => quote!(Guid),
+        "Value" => quote!(Dynamic),
+        _ => return Err(cannot_infer(ty)),
+    })
+}
+
+fn cannot_infer(ty: &Type) -> Error {
+    Error::new_spanned(
+        ty,
+        "cannot infer the column type of this field, set it with #[kusto(column_type = \"...\")]",
+    )
+}
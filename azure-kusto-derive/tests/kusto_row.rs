@@ -0,0 +1,145 @@
+use azure_kusto_data::kusto_row::{to_csv_row, to_json_row, KustoRow};
+use azure_kusto_data::models::{Column, ColumnType, DataTable};
+use azure_kusto_data::types::{KustoDuration, KustoValue};
+use time::macros::datetime;
+use time::OffsetDateTime;
+
+#[derive(KustoRow, Debug, PartialEq)]
+struct Event {
+    #[kusto(rename = "Timestamp")]
+    timestamp: OffsetDateTime,
+    name: String,
+    #[kusto(column_type = "decimal")]
+    amount: String,
+    took: Option<KustoDuration>,
+    #[kusto(optional)]
+    count: Option<i64>,
+}
+
+fn column(name: &str, column_type: ColumnType) -> Column {
+    Column {
+        column_name: name.to_string(),
+        column_type,
+    }
+}
+
+#[test]
+fn generates_schema() {
+    assert_eq!(
+        Event::columns(),
+        vec![
+            column("Timestamp", ColumnType::Datetime),
+            column("name", ColumnType::String),
+            column("amount", ColumnType::Decimal),
+            column("took", ColumnType::Timespan),
+            column("count", ColumnType::Long),
+        ]
+    );
+    assert!(!Event::is_optional(0));
+    assert!(Event::is_optional(4));
+}
+
+#[test]
+fn decodes_data_table_rows() {
+    let table: DataTable = serde_json::from_str(
+        r#"{
+        "TableId": 1, "TableName": "t", "TableKind": "PrimaryResult",
+        "Columns": [
+            {"ColumnName": "amount", "ColumnType": "decimal"},
+            {"ColumnName": "name", "ColumnType": "string"},
+            {"ColumnName": "took", "ColumnType": "timespan"},
+            {"ColumnName": "Timestamp", "ColumnType": "datetime"}
+        ],
+        "Rows": [["1.50", "a", "00:00:01", "2023-01-02T03:04:05Z"], ["2", "b", null, "2023-01-02T03:04:05Z"]]
+    }"#,
+    )
+    .expect("Failed to parse table");
+
+    let events = table.decode_rows::<Event>().expect("Failed to decode rows");
+
+    assert_eq!(
+        events[0],
+        Event {
+            timestamp: datetime!(2023-01-02 03:04:05 UTC),
+            name: "a".to_string(),
+            amount: "1.50".to_string(),
+            took: Some(time::Duration::seconds(1).into()),
+            count: None,
+        }
+    );
+    assert_eq!(events[1].took, None);
+}
+
+#[test]
+fn rejects_mismatched_columns() {
+    let table: DataTable = serde_json::from_str(
+        r#"{
+        "TableId": 1, "TableName": "t", "TableKind": "PrimaryResult",
+        "Columns": [
+            {"ColumnName": "amount", "ColumnType": "real"},
+            {"ColumnName": "name", "ColumnType": "string"},
+            {"ColumnName": "took", "ColumnType": "timespan"},
+            {"ColumnName": "Timestamp", "ColumnType": "datetime"}
+        ],
+        "Rows": []
+    }"#,
+    )
+    .expect("Failed to parse table");
+
+    assert!(table.decode_rows::<Event>().is_err());
+}
+
+#[test]
+fn encodes_rows() {
+    let event = Event {
+        timestamp: datetime!(2023-01-02 03:04:05 UTC),
+        name: "a,b".to_string(),
+        amount: "1.50".to_string(),
+        took: None,
+        count: Some(3),
+    };
+
+    assert_eq!(to_csv_row(&event), r#"2023-01-02T03:04:05Z,"a,b",1.50,,3"#);
+    assert_eq!(
+        to_json_row(&event),
+        serde_json::json!({
+            "Timestamp": "2023-01-02T03:04:05Z",
+            "name": "a,b",
+            "amount": "1.50",
+            "took": null,
+            "count": 3
+        })
+    );
+}
+
+#[derive(KustoRow)]
+struct Declared {
+    #[kusto(column_type = "guid")]
+    id: String,
+    #[kusto(column_type = "decimal")]
+    price: f64,
+    #[kusto(column_type = "long")]
+    count: Option<i32>,
+}
+
+#[test]
+fn encodes_fields_as_declared_column_types() {
+    let row = Declared {
+        id: "8f5bd6a4-6c2f-4e3b-9d8b-3e5a2a1f4c10".to_string(),
+        price: 1.5,
+        count: Some(2),
+    };
+
+    assert_eq!(
+        row.to_values(),
+        vec![
+            KustoValue::Guid(
+                "8f5bd6a4-6c2f-4e3b-9d8b-3e5a2a1f4c10"
+                    .parse()
+                    .expect("Failed to parse guid")
+            ),
+            KustoValue::Decimal("1.5".to_string()),
+            KustoValue::Long(2),
+        ]
+    );
+}
@@ -39,6 +39,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         data_format: DataFormat::Parquet,
         // Assume the server side default for flush_immediately
         flush_immediately: None,
+        // Map the fields of the Parquet file to the columns of the table by name
+        ingestion_mapping: None,
+        // Use the defaults for the other properties, such as tags
+        ..Default::default()
     };
 
     // Define the blob to ingest from
@@ -53,7 +57,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let blob_descriptor = BlobDescriptor::new(blob_uri, blob_size, None)
         .with_blob_auth(BlobAuth::SystemAssignedManagedIdentity);
 
-    let _ = queued_ingest_client
+    queued_ingest_client
         .ingest_from_blob(blob_descriptor, ingestion_properties)
         .await?;
 
@@ -1,3 +1,4 @@
+use azure_kusto_data::ingestion_mapping::IngestionMappingKind;
 use serde::Serialize;
 
 /// All data formats supported by Kusto.
@@ -25,6 +26,44 @@ pub enum DataFormat {
     W3CLOGFILE,
 }
 
+impl DataFormat {
+    /// The kind of ingestion mapping that can be used with data in this format
+    pub fn ingestion_mapping_kind(&self) -> IngestionMappingKind {
+        match self {
+            DataFormat::ApacheAvro => IngestionMappingKind::ApacheAvro,
+            DataFormat::Avro => IngestionMappingKind::Avro,
+            DataFormat::JSON | DataFormat::MultiJSON | DataFormat::SingleJSON => {
+                IngestionMappingKind::Json
+            }
+            DataFormat::ORC => IngestionMappingKind::Orc,
+            DataFormat::Parquet => IngestionMappingKind::Parquet,
+            DataFormat::SStream => IngestionMappingKind::SStream,
+            DataFormat::W3CLOGFILE => IngestionMappingKind::W3CLogFile,
+            DataFormat::CSV
+            | DataFormat::PSV
+            | DataFormat::RAW
+            | DataFormat::SCSV
+            | DataFormat::SOHsv
+            | DataFormat::TSV
+            | DataFormat::TSVe
+            | DataFormat::TXT => IngestionMappingKind::Csv,
+        }
+    }
+
+    /// Whether data in this format benefits from being compressed before it is uploaded.
+    /// Binary formats such as Parquet or Avro are compressed internally, so they are uploaded as is.
+    pub fn compressible(&self) -> bool {
+        !matches!(
+            self,
+            DataFormat::ApacheAvro
+                | DataFormat::Avro
+                | DataFormat::ORC
+                | DataFormat::Parquet
+                | DataFormat::SStream
+        )
+    }
+}
+
 // Unit tests
 #[cfg(test)]
 mod tests {
@@ -34,4 +73,28 @@ mod tests {
     fn data_format_default() {
         assert_eq!(DataFormat::default(), DataFormat::CSV);
     }
+
+    #[test]
+    fn data_format_ingestion_mapping_kind() {
+        assert_eq!(
+            DataFormat::TSV.ingestion_mapping_kind(),
+            IngestionMappingKind::Csv
+        );
+        assert_eq!(
+            DataFormat::MultiJSON.ingestion_mapping_kind(),
+            IngestionMappingKind::Json
+        );
+        assert_eq!(
+            DataFormat::Parquet.ingestion_mapping_kind(),
+            IngestionMappingKind::Parquet
+        );
+    }
+
+    #[test]
+    fn data_format_compressible() {
+        assert!(DataFormat::CSV.compressible());
+        assert!(DataFormat::MultiJSON.compressible());
+        assert!(!DataFormat::Parquet.compressible());
+        assert!(!DataFormat::ApacheAvro.compressible());
+    }
 }
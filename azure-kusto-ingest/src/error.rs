@@ -7,10 +7,22 @@ pub enum Error {
     #[error("Error obtaining ingestion resources: {0}")]
     ResourceManagerError(#[from] super::resource_manager::ResourceManagerError),
 
+    /// Error raised when the ingestion properties are inconsistent
+    #[error("Invalid ingestion properties: {0}")]
+    IngestionPropertiesError(#[from] super::ingestion_properties::IngestionPropertiesError),
+
+    /// Error converting the data to ingest into a format supported by Kusto
+    #[error("Error converting data to ingest: {0}")]
+    ConversionError(#[from] azure_kusto_data::error::Error),
+
     /// Error relating to (de-)serialization of JSON data
     #[error("Error in JSON serialization/deserialization: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    /// Error reading the data to ingest
+    #[error("Error reading data to ingest: {0}")]
+    IoError(#[from] std::io::Error),
+
     /// Error occurring within core azure crates
     #[error("Error in azure-core: {0}")]
     AzureError(#[from] azure_core::error::Error),
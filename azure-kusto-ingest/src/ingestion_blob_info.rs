@@ -1,9 +1,13 @@
+use azure_kusto_data::ingestion_mapping::IngestionMappingKind;
 use serde::Serialize;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 use crate::{
-    data_format::DataFormat, descriptors::BlobDescriptor,
-    ingestion_properties::IngestionProperties,
+    data_format::DataFormat,
+    descriptors::BlobDescriptor,
+    error::Result,
+    ingestion_properties::{IngestionMapping, IngestionProperties},
     resource_manager::authorization_context::KustoIdentityToken,
 };
 
@@ -60,13 +64,50 @@ impl QueuedIngestionMessage {
         blob_descriptor: &BlobDescriptor,
         ingestion_properties: &IngestionProperties,
         authorization_context: KustoIdentityToken,
-    ) -> Self {
-        let additional_properties = AdditionalProperties {
+    ) -> Result<Self> {
+        let tags = ingestion_properties.tags();
+        let ingest_if_not_exists = &ingestion_properties.ingest_if_not_exists;
+        let mut additional_properties = AdditionalProperties {
             authorization_context,
             data_format: ingestion_properties.data_format.clone(),
+            ingestion_mapping: None,
+            ingestion_mapping_type: None,
+            ingestion_mapping_reference: None,
+            // Lists and the validation policy are passed as JSON strings
+            tags: (!tags.is_empty())
+                .then(|| serde_json::to_string(&tags))
+                .transpose()?,
+            ingest_if_not_exists: (!ingest_if_not_exists.is_empty())
+                .then(|| serde_json::to_string(ingest_if_not_exists))
+                .transpose()?,
+            ignore_first_record: ingestion_properties.ignore_first_record,
+            creation_time: ingestion_properties.creation_time,
+            validation_policy: ingestion_properties
+                .validation_policy
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            zip_pattern: ingestion_properties.zip_pattern.clone(),
+            ignore_size_limit: ingestion_properties.ignore_size_limit,
+            extend_schema: ingestion_properties.extend_schema.clone(),
+            recreate_schema: ingestion_properties.recreate_schema.clone(),
+            ignore_last_record_empty: ingestion_properties.ignore_last_record_empty,
+            additional_properties: ingestion_properties.additional_properties.clone(),
         };
+        match &ingestion_properties.ingestion_mapping {
+            Some(IngestionMapping::Inline { kind, columns }) => {
+                // The mapping is passed as a JSON string
+                additional_properties.ingestion_mapping = Some(serde_json::to_string(columns)?);
+                additional_properties.ingestion_mapping_type = Some(*kind);
+            }
+            Some(IngestionMapping::Reference { kind, name }) => {
+                additional_properties.ingestion_mapping_reference = Some(name.clone());
+                additional_properties.ingestion_mapping_type = Some(*kind);
+            }
+            None => {}
+        }
 
-        Self {
+        Ok(Self {
             id: blob_descriptor.source_id,
             blob_path: blob_descriptor.uri(),
             raw_data_size: blob_descriptor.size,
@@ -76,7 +117,7 @@ impl QueuedIngestionMessage {
             flush_immediately: ingestion_properties.flush_immediately,
             source_message_creation_time: OffsetDateTime::now_utc(),
             additional_properties,
-        }
+        })
     }
 }
 
@@ -89,11 +130,62 @@ struct AdditionalProperties {
     authorization_context: KustoIdentityToken,
     #[serde(rename = "format")]
     data_format: DataFormat,
+    /// Mapping sent along with the data, as a JSON array of column mappings
+    #[serde(rename = "ingestionMapping", skip_serializing_if = "Option::is_none")]
+    ingestion_mapping: Option<String>,
+    /// Kind of the mapping, whether inline or referenced
+    #[serde(
+        rename = "ingestionMappingType",
+        skip_serializing_if = "Option::is_none"
+    )]
+    ingestion_mapping_type: Option<IngestionMappingKind>,
+    /// Name of a mapping created on the table beforehand
+    #[serde(
+        rename = "ingestionMappingReference",
+        skip_serializing_if = "Option::is_none"
+    )]
+    ingestion_mapping_reference: Option<String>,
+    /// Tags of the data, as a JSON array
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<String>,
+    /// `ingest-by:` tags that prevent the ingestion if present in the table, as a JSON array
+    #[serde(rename = "ingestIfNotExists", skip_serializing_if = "Option::is_none")]
+    ingest_if_not_exists: Option<String>,
+    #[serde(rename = "ignoreFirstRecord", skip_serializing_if = "Option::is_none")]
+    ignore_first_record: Option<bool>,
+    #[serde(
+        rename = "creationTime",
+        with = "kusto_ingest_iso8601_format::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    creation_time: Option<OffsetDateTime>,
+    /// Validation policy, as a JSON object
+    #[serde(rename = "validationPolicy", skip_serializing_if = "Option::is_none")]
+    validation_policy: Option<String>,
+    #[serde(rename = "zipPattern", skip_serializing_if = "Option::is_none")]
+    zip_pattern: Option<String>,
+    #[serde(rename = "ignoreSizeLimit", skip_serializing_if = "Option::is_none")]
+    ignore_size_limit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extend_schema: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recreate_schema: Option<String>,
+    #[serde(
+        rename = "ignoreLastRecordEmpty",
+        skip_serializing_if = "Option::is_none"
+    )]
+    ignore_last_record_empty: Option<bool>,
+    /// Free-form properties, whose names do not clash with the others as checked by [IngestionProperties::validate]
+    #[serde(flatten)]
+    additional_properties: BTreeMap<String, String>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ingestion_properties::{
+        ValidationImplications, ValidationOptions, ValidationPolicy,
+    };
 
     #[test]
     fn time_custom_iso8601_serialization() {
@@ -116,4 +208,100 @@ mod tests {
             "{\"customised_time_format\":\"2009-02-13T23:31:30.123456789Z\"}"
         );
     }
+
+    fn additional_properties(ingestion_properties: &IngestionProperties) -> serde_json::Value {
+        let message = QueuedIngestionMessage::new(
+            &BlobDescriptor::new("https://account.blob.core.windows.net/c/b", None, None),
+            ingestion_properties,
+            "token".to_string(),
+        )
+        .unwrap();
+        serde_json::to_value(message).unwrap()["AdditionalProperties"].clone()
+    }
+
+    fn json_properties(ingestion_mapping: IngestionMapping) -> IngestionProperties {
+        IngestionProperties {
+            database_name: "db".to_string(),
+            table_name: "table".to_string(),
+            data_format: DataFormat::JSON,
+            ingestion_mapping: Some(ingestion_mapping),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inline_ingestion_mapping_serialization() {
+        let mapping = IngestionMapping::inline(
+            IngestionMappingKind::Json,
+            vec![azure_kusto_data::ingestion_mapping::ColumnMapping::with_path("a", "$.b")],
+        );
+
+        assert_eq!(
+            additional_properties(&json_properties(mapping)),
+            serde_json::json!({
+                "authorizationContext": "token",
+                "format": "json",
+                "ingestionMapping": r#"[{"column":"a","Properties":{"Path":"$.b"}}]"#,
+                "ingestionMappingType": "Json",
+            })
+        );
+    }
+
+    #[test]
+    fn additional_properties_serialization() {
+        let mut ingestion_properties = IngestionProperties {
+            data_format: DataFormat::CSV,
+            additional_tags: vec!["a".to_string()],
+            ingest_by_tags: vec!["2024-01-01".to_string()],
+            drop_by_tags: vec!["b".to_string()],
+            ingest_if_not_exists: vec!["2024-01-01".to_string()],
+            ignore_first_record: Some(true),
+            creation_time: Some(time::macros::datetime!(2024-01-01 0:00 UTC)),
+            validation_policy: Some(ValidationPolicy {
+                validation_options: ValidationOptions::ValidateCsvInputConstantColumns,
+                validation_implications: ValidationImplications::Fail,
+            }),
+            zip_pattern: Some(".*\\.csv".to_string()),
+            ignore_size_limit: Some(false),
+            extend_schema: Some("(c:string)".to_string()),
+            ignore_last_record_empty: Some(true),
+            ..Default::default()
+        };
+        ingestion_properties
+            .additional_properties
+            .insert("persistDetails".to_string(), "true".to_string());
+
+        assert_eq!(
+            additional_properties(&ingestion_properties),
+            serde_json::json!({
+                "authorizationContext": "token",
+                "format": "csv",
+                "tags": r#"["a","ingest-by:2024-01-01","drop-by:b"]"#,
+                "ingestIfNotExists": r#"["2024-01-01"]"#,
+                "ignoreFirstRecord": true,
+                "creationTime": "2024-01-01T00:00:00.000000000Z",
+                "validationPolicy": r#"{"ValidationOptions":1,"ValidationImplications":0}"#,
+                "zipPattern": ".*\\.csv",
+                "ignoreSizeLimit": false,
+                "extend_schema": "(c:string)",
+                "ignoreLastRecordEmpty": true,
+                "persistDetails": "true",
+            })
+        );
+    }
+
+    #[test]
+    fn ingestion_mapping_reference_serialization() {
+        let mapping = IngestionMapping::reference(IngestionMappingKind::Json, "mapping");
+
+        assert_eq!(
+            additional_properties(&json_properties(mapping)),
+            serde_json::json!({
+                "authorizationContext": "token",
+                "format": "json",
+                "ingestionMappingReference": "mapping",
+                "ingestionMappingType": "Json",
+            })
+        );
+    }
 }
@@ -1,5 +1,31 @@
+use std::collections::BTreeMap;
+
+use azure_kusto_data::ingestion_mapping::{ColumnMapping, IngestionMappingKind};
+use serde::Serialize;
+use time::OffsetDateTime;
+
 use crate::data_format::DataFormat;
 
+/// Names of the properties of the ingestion message set from the typed fields of [IngestionProperties],
+/// which cannot be overridden by [IngestionProperties::additional_properties]
+pub(crate) const RESERVED_PROPERTIES: &[&str] = &[
+    "authorizationContext",
+    "format",
+    "ingestionMapping",
+    "ingestionMappingType",
+    "ingestionMappingReference",
+    "tags",
+    "ingestIfNotExists",
+    "ignoreFirstRecord",
+    "creationTime",
+    "validationPolicy",
+    "zipPattern",
+    "ignoreSizeLimit",
+    "extend_schema",
+    "recreate_schema",
+    "ignoreLastRecordEmpty",
+];
+
 /// Properties of ingestion that can be used when ingesting data into Kusto allowing for customisation of the ingestion process
 #[derive(Clone, Debug, Default)]
 pub struct IngestionProperties {
@@ -15,4 +41,253 @@ pub struct IngestionProperties {
     pub data_format: DataFormat,
     /// If set to `true`, any aggregation will be skipped. Default is `false`
     pub flush_immediately: Option<bool>,
+    /// How the fields of the data map to the columns of the table.
+    /// When not provided, columns are mapped by name, or by position for the CSV-like formats
+    pub ingestion_mapping: Option<IngestionMapping>,
+    /// Tags to associate with the ingested data
+    pub additional_tags: Vec<String>,
+    /// Tags to associate with the ingested data as `ingest-by:` tags, which can be checked with [ingest_if_not_exists](Self::ingest_if_not_exists)
+    pub ingest_by_tags: Vec<String>,
+    /// Tags to associate with the ingested data as `drop-by:` tags, which allow dropping the data with `.drop extents`
+    pub drop_by_tags: Vec<String>,
+    /// Skips the ingestion if the table already has data tagged with any of these `ingest-by:` tags,
+    /// making ingestion idempotent
+    pub ingest_if_not_exists: Vec<String>,
+    /// If set to `true`, the first record of each file is ignored, e.g. the header of a CSV file
+    pub ignore_first_record: Option<bool>,
+    /// Overrides the creation time of the ingested data, which is otherwise the time of ingestion.
+    /// Useful when backfilling historical data, so that retention and caching policies apply as expected
+    pub creation_time: Option<OffsetDateTime>,
+    /// How the data is validated during ingestion
+    pub validation_policy: Option<ValidationPolicy>,
+    /// Regular expression selecting the files of a ZIP archive to ingest, all files are ingested when not provided
+    pub zip_pattern: Option<String>,
+    /// If set to `true`, data larger than the size limit of a single ingestion is ingested anyway. Default is `false`
+    pub ignore_size_limit: Option<bool>,
+    /// Columns to add to the table if they do not exist, e.g. `(NewColumn:string)`
+    pub extend_schema: Option<String>,
+    /// Schema to recreate the table with, e.g. `(a:string, b:int)`
+    pub recreate_schema: Option<String>,
+    /// If set to `true`, the last record is ignored when it is empty. Default is `true` for the CSV-like formats
+    pub ignore_last_record_empty: Option<bool>,
+    /// Other properties to pass to the ingestion service, which must not be any of those set by the other fields
+    pub additional_properties: BTreeMap<String, String>,
+}
+
+impl IngestionProperties {
+    /// Checks that the properties are consistent, e.g. that the ingestion mapping can be used with the data format
+    pub fn validate(&self) -> Result<(), IngestionPropertiesError> {
+        if let Some(mapping) = &self.ingestion_mapping {
+            mapping.validate(&self.data_format)?;
+        }
+        if let Some(name) = self
+            .additional_properties
+            .keys()
+            .find(|name| RESERVED_PROPERTIES.contains(&name.as_str()))
+        {
+            return Err(IngestionPropertiesError::ReservedProperty(name.clone()));
+        }
+        Ok(())
+    }
+
+    /// All the tags of the data: the additional tags followed by the `ingest-by:` and `drop-by:` tags
+    pub(crate) fn tags(&self) -> Vec<String> {
+        let ingest_by = self.ingest_by_tags.iter().map(|t| format!("ingest-by:{t}"));
+        let drop_by = self.drop_by_tags.iter().map(|t| format!("drop-by:{t}"));
+        self.additional_tags
+            .iter()
+            .cloned()
+            .chain(ingest_by)
+            .chain(drop_by)
+            .collect()
+    }
+}
+
+/// How data is validated during ingestion.
+/// See [the docs](https://learn.microsoft.com/en-us/azure/data-explorer/ingestion-properties)
+#[derive(Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase")]
+pub struct ValidationPolicy {
+    /// What is validated
+    pub validation_options: ValidationOptions,
+    /// What happens when the validation fails
+    pub validation_implications: ValidationImplications,
+}
+
+/// What is validated during ingestion
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidationOptions {
+    /// No validation
+    #[default]
+    DoNotValidate = 0,
+    /// Checks that all the records of CSV data have the same number of fields
+    ValidateCsvInputConstantColumns = 1,
+    /// Checks that CSV data has an even number of fields
+    ValidateCsvInputColumnLevelOnly = 2,
+}
+
+/// What happens when the validation of ingested data fails
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ValidationImplications {
+    /// The whole ingestion fails
+    Fail = 0,
+    /// The errors are ignored, and the valid data is ingested
+    #[default]
+    BestEffort = 1,
+}
+
+/// The service expects the validation options and implications as numbers
+impl Serialize for ValidationOptions {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+impl Serialize for ValidationImplications {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// Error raised when [IngestionProperties] are inconsistent
+#[derive(Debug, thiserror::Error)]
+pub enum IngestionPropertiesError {
+    #[error("A {mapping_kind} ingestion mapping cannot be used with the {data_format:?} format, which requires a {expected} mapping")]
+    MappingKindMismatch {
+        mapping_kind: IngestionMappingKind,
+        data_format: DataFormat,
+        expected: IngestionMappingKind,
+    },
+
+    #[error("Ingestion mapping has no columns")]
+    EmptyMapping,
+
+    #[error("Ingestion mapping reference has no name")]
+    EmptyMappingReference,
+
+    #[error("Additional property {0} is set by the typed fields of the ingestion properties")]
+    ReservedProperty(String),
+}
+
+/// An ingestion mapping, describing how the fields of the data map to the columns of the table.
+/// See [the docs](https://learn.microsoft.com/en-us/azure/data-explorer/kusto/management/mappings)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IngestionMapping {
+    /// A mapping sent along with the data.
+    /// Column mappings can be generated with [column_mappings](azure_kusto_data::ingestion_mapping::column_mappings)
+    Inline {
+        kind: IngestionMappingKind,
+        columns: Vec<ColumnMapping>,
+    },
+    /// A mapping created on the table beforehand, referenced by name,
+    /// e.g. with [create_ingestion_mapping](azure_kusto_data::management::Management::create_ingestion_mapping)
+    Reference {
+        kind: IngestionMappingKind,
+        name: String,
+    },
+}
+
+impl IngestionMapping {
+    /// Creates a mapping sent along with the data
+    pub fn inline(kind: IngestionMappingKind, columns: Vec<ColumnMapping>) -> Self {
+        Self::Inline { kind, columns }
+    }
+
+    /// Creates a reference to a mapping created on the table beforehand
+    pub fn reference(kind: IngestionMappingKind, name: impl Into<String>) -> Self {
+        Self::Reference {
+            kind,
+            name: name.into(),
+        }
+    }
+
+    /// The kind of the mapping, which must match the format of the data
+    pub fn kind(&self) -> IngestionMappingKind {
+        match self {
+            Self::Inline { kind, .. } | Self::Reference { kind, .. } => *kind,
+        }
+    }
+
+    /// Checks that the mapping is not empty, and that its kind matches the format of the data
+    pub fn validate(&self, data_format: &DataFormat) -> Result<(), IngestionPropertiesError> {
+        match self {
+            Self::Inline { columns, .. } if columns.is_empty() => {
+                return Err(IngestionPropertiesError::EmptyMapping)
+            }
+            Self::Reference { name, .. } if name.is_empty() => {
+                return Err(IngestionPropertiesError::EmptyMappingReference)
+            }
+            _ => {}
+        }
+
+        let expected = data_format.ingestion_mapping_kind();
+        if self.kind() != expected {
+            return Err(IngestionPropertiesError::MappingKindMismatch {
+                mapping_kind: self.kind(),
+                data_format: data_format.clone(),
+                expected,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_mapping_kind() {
+        let csv = IngestionMapping::inline(
+            IngestionMappingKind::Csv,
+            vec![ColumnMapping::with_ordinal("a", 0)],
+        );
+        assert!(csv.validate(&DataFormat::TSV).is_ok());
+        assert!(matches!(
+            csv.validate(&DataFormat::Parquet),
+            Err(IngestionPropertiesError::MappingKindMismatch {
+                mapping_kind: IngestionMappingKind::Csv,
+                expected: IngestionMappingKind::Parquet,
+                ..
+            })
+        ));
+
+        let json = IngestionMapping::reference(IngestionMappingKind::Json, "mapping");
+        let properties = IngestionProperties {
+            data_format: DataFormat::MultiJSON,
+            ingestion_mapping: Some(json),
+            ..Default::default()
+        };
+        assert!(properties.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_reserved_properties() {
+        let mut properties = IngestionProperties::default();
+        properties
+            .additional_properties
+            .insert("persistDetails".to_string(), "true".to_string());
+        assert!(properties.validate().is_ok());
+
+        properties
+            .additional_properties
+            .insert("tags".to_string(), "[]".to_string());
+        assert!(matches!(
+            properties.validate(),
+            Err(IngestionPropertiesError::ReservedProperty(name)) if name == "tags"
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_mappings() {
+        assert!(matches!(
+            IngestionMapping::inline(IngestionMappingKind::Json, vec![])
+                .validate(&DataFormat::JSON),
+            Err(IngestionPropertiesError::EmptyMapping)
+        ));
+        assert!(matches!(
+            IngestionMapping::reference(IngestionMappingKind::Json, "").validate(&DataFormat::JSON),
+            Err(IngestionPropertiesError::EmptyMappingReference)
+        ));
+    }
 }
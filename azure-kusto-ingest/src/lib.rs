@@ -6,3 +6,4 @@ pub(crate) mod ingestion_blob_info;
 pub mod ingestion_properties;
 pub mod queued_ingest;
 pub(crate) mod resource_manager;
+pub(crate) mod upload;
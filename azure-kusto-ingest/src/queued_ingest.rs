@@ -1,14 +1,28 @@
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
 
 use crate::error::Result;
+#[cfg(feature = "arrow")]
+use arrow_array::RecordBatch;
 use azure_core::base64;
+#[cfg(feature = "arrow")]
+use azure_kusto_data::arrow::write_json_lines;
 use azure_kusto_data::prelude::KustoClient;
+use futures::{Stream, TryStreamExt};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt};
 
 use crate::client_options::QueuedIngestClientOptions;
+use crate::data_format::DataFormat;
 use crate::descriptors::BlobDescriptor;
 use crate::ingestion_blob_info::QueuedIngestionMessage;
 use crate::ingestion_properties::IngestionProperties;
 use crate::resource_manager::ResourceManager;
+use crate::upload::StreamingUpload;
+
+/// Size of the chunks read from files and readers passed to [QueuedIngestClient]
+const READ_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Client for ingesting data into Kusto using the queued flavour of ingestion
 #[derive(Clone)]
@@ -42,13 +56,23 @@ impl QueuedIngestClient {
         &self,
         blob_descriptor: BlobDescriptor,
         ingestion_properties: IngestionProperties,
+    ) -> Result<()> {
+        ingestion_properties.validate()?;
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+
+    /// Queues the ingestion of a blob, whose ingestion properties were validated already
+    async fn enqueue(
+        &self,
+        blob_descriptor: &BlobDescriptor,
+        ingestion_properties: &IngestionProperties,
     ) -> Result<()> {
         let queue_client = self.resource_manager.random_ingestion_queue().await?;
 
         let auth_context = self.resource_manager.authorization_context().await?;
 
         let message =
-            QueuedIngestionMessage::new(&blob_descriptor, &ingestion_properties, auth_context);
+            QueuedIngestionMessage::new(blob_descriptor, ingestion_properties, auth_context)?;
 
         let message = serde_json::to_string(&message)?;
 
@@ -59,4 +83,151 @@ impl QueuedIngestClient {
 
         Ok(())
     }
+
+    /// Ingest a local file into Kusto, by uploading it to one of the temporary storage containers of the ingestion service
+    ///
+    /// Files are gzip compressed as they are read and uploaded in blocks, unless they are compressed already (`.gz` or `.zip`),
+    /// or their format is compressed internally (see [DataFormat::compressible](crate::data_format::DataFormat::compressible))
+    pub async fn ingest_from_file(
+        &self,
+        path: impl AsRef<Path>,
+        ingestion_properties: IngestionProperties,
+    ) -> Result<()> {
+        ingestion_properties.validate()?;
+        let path = path.as_ref();
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} is not a file name", path.display()),
+                )
+            })?;
+        let file = tokio::fs::File::open(path).await?;
+
+        let container = self
+            .resource_manager
+            .random_temp_storage_container()
+            .await?;
+        let upload = StreamingUpload::named(&container, &ingestion_properties, file_name);
+        let blob_descriptor = upload_reader(upload, file).await?;
+
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+
+    /// Ingest data from a stream of chunks into Kusto, by uploading it to one of the temporary storage containers of the ingestion service
+    ///
+    /// The data is compressed as it is read, unless its format is compressed internally (see [DataFormat::compressible](crate::data_format::DataFormat::compressible)),
+    /// and uploaded in blocks, so it does not have to be held in memory in its entirety
+    pub async fn ingest_from_stream<S, B>(
+        &self,
+        mut stream: S,
+        ingestion_properties: IngestionProperties,
+    ) -> Result<()>
+    where
+        S: Stream<Item = io::Result<B>> + Unpin,
+        B: AsRef<[u8]>,
+    {
+        ingestion_properties.validate()?;
+        let container = self
+            .resource_manager
+            .random_temp_storage_container()
+            .await?;
+        let mut upload = StreamingUpload::new(&container, &ingestion_properties);
+        while let Some(chunk) = stream.try_next().await? {
+            upload.write(chunk.as_ref()).await?;
+        }
+        let blob_descriptor = upload.finish().await?;
+
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+
+    /// Ingest data from a reader into Kusto, by uploading it to one of the temporary storage containers of the ingestion service.
+    /// See [ingest_from_stream](Self::ingest_from_stream)
+    pub async fn ingest_from_reader<R>(
+        &self,
+        reader: R,
+        ingestion_properties: IngestionProperties,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        ingestion_properties.validate()?;
+        let container = self
+            .resource_manager
+            .random_temp_storage_container()
+            .await?;
+        let upload = StreamingUpload::new(&container, &ingestion_properties);
+        let blob_descriptor = upload_reader(upload, reader).await?;
+
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+
+    /// Ingest rows into Kusto, serialized as JSON and ingested with the [DataFormat::MultiJSON] format, which replaces the format of the ingestion properties
+    ///
+    /// Fields are mapped to the columns of the table by name, unless an ingestion mapping is provided
+    pub async fn ingest_from_rows<T: Serialize>(
+        &self,
+        rows: impl IntoIterator<Item = T>,
+        mut ingestion_properties: IngestionProperties,
+    ) -> Result<()> {
+        ingestion_properties.data_format = DataFormat::MultiJSON;
+        ingestion_properties.validate()?;
+        let container = self
+            .resource_manager
+            .random_temp_storage_container()
+            .await?;
+        let mut upload = StreamingUpload::new(&container, &ingestion_properties);
+        for row in rows {
+            upload.write_json_line(&row).await?;
+        }
+        let blob_descriptor = upload.finish().await?;
+
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+
+    /// Ingest Arrow record batches into Kusto, written as by [write_json_lines] and ingested with the [DataFormat::MultiJSON] format,
+    /// which replaces the format of the ingestion properties
+    ///
+    /// Fields are mapped to the columns of the table by name, unless an ingestion mapping is provided
+    #[cfg(feature = "arrow")]
+    pub async fn ingest_from_record_batches(
+        &self,
+        batches: &[RecordBatch],
+        mut ingestion_properties: IngestionProperties,
+    ) -> Result<()> {
+        ingestion_properties.data_format = DataFormat::MultiJSON;
+        ingestion_properties.validate()?;
+        let container = self
+            .resource_manager
+            .random_temp_storage_container()
+            .await?;
+        let mut upload = StreamingUpload::new(&container, &ingestion_properties);
+        let mut lines = Vec::new();
+        for batch in batches {
+            lines.clear();
+            lines = write_json_lines(batch, lines)?;
+            upload.write(&lines).await?;
+        }
+        let blob_descriptor = upload.finish().await?;
+
+        self.enqueue(&blob_descriptor, &ingestion_properties).await
+    }
+}
+
+/// Uploads all the data of a reader, returning the descriptor of the blob to ingest from
+async fn upload_reader(
+    mut upload: StreamingUpload,
+    mut reader: impl AsyncRead + Unpin,
+) -> Result<BlobDescriptor> {
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        upload.write(&buffer[..read]).await?;
+    }
+    upload.finish().await
 }
@@ -4,7 +4,6 @@ pub mod authorization_context;
 pub mod cache;
 pub mod ingest_client_resources;
 pub mod resource_uri;
-pub mod utils;
 
 use azure_kusto_data::prelude::KustoClient;
 
@@ -14,7 +13,7 @@ use crate::client_options::QueuedIngestClientOptions;
 
 use self::{
     authorization_context::{AuthorizationContext, KustoIdentityToken},
-    ingest_client_resources::IngestClientResources,
+    ingest_client_resources::{IngestClientResources, TempStorageContainer},
 };
 
 use rand::{seq::SliceRandom, thread_rng};
@@ -61,14 +60,22 @@ impl ResourceManager {
     /// Returns a [QueueClient] to ingest to.
     /// This is a random selection from the list of ingestion queues
     pub async fn random_ingestion_queue(&self) -> Result<QueueClient> {
-        let ingestion_queues = self.ingestion_queues().await?;
+        choose_random(self.ingestion_queues().await?)
+    }
 
-        let mut rng = thread_rng();
-        let selected_queue = ingestion_queues
-            .choose(&mut rng)
-            .ok_or(ResourceManagerError::NoResourcesFound)?;
+    /// Returns the latest [TempStorageContainer]s that data can be uploaded to before ingesting it
+    async fn temp_storage_containers(&self) -> Result<Vec<TempStorageContainer>> {
+        Ok(self
+            .ingest_client_resources
+            .get()
+            .await?
+            .temp_storage_containers)
+    }
 
-        Ok(selected_queue.clone())
+    /// Returns a [TempStorageContainer] to upload data to.
+    /// This is a random selection from the list of temporary storage containers
+    pub async fn random_temp_storage_container(&self) -> Result<TempStorageContainer> {
+        choose_random(self.temp_storage_containers().await?)
     }
 
     /// Returns the latest [KustoIdentityToken] to be added as an authorization context to ingestion messages
@@ -79,3 +86,12 @@ impl ResourceManager {
             .map_err(ResourceManagerError::AuthorizationContextError)
     }
 }
+
+/// Selects a random resource, spreading the load across them
+fn choose_random<T: Clone>(resources: Vec<T>) -> Result<T> {
+    let mut rng = thread_rng();
+    resources
+        .choose(&mut rng)
+        .cloned()
+        .ok_or(ResourceManagerError::NoResourcesFound)
+}
@@ -2,7 +2,6 @@ use azure_kusto_data::prelude::KustoClient;
 use serde_json::Value;
 
 use super::cache::ThreadSafeCachedValue;
-use super::utils::get_column_index;
 use super::RESOURCE_REFRESH_PERIOD;
 
 pub(crate) type KustoIdentityToken = String;
@@ -66,7 +65,7 @@ impl AuthorizationContext {
         };
 
         // Check that a column in this table actually exists called `AuthorizationContext`
-        let index = get_column_index(table, AUTHORIZATION_CONTEXT).ok_or(
+        let index = table.column_index(AUTHORIZATION_CONTEXT).ok_or(
             KustoIdentityTokenError::ColumnNotFound(AUTHORIZATION_CONTEXT.into()),
         )?;
 
@@ -149,7 +149,7 @@ mod thread_safe_cached_value_tests {
             // Include an incrementing counter in the token to track how many times the token has been refreshed
             let mut call_count = self.get_token_call_count.lock().unwrap();
             *call_count += 1;
-            Ok(call_count.clone())
+            Ok(*call_count)
         }
     }
 
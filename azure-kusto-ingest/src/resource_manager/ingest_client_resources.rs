@@ -3,7 +3,7 @@ use crate::client_options::QueuedIngestClientOptions;
 use super::{
     cache::ThreadSafeCachedValue,
     resource_uri::{ClientFromResourceUri, ResourceUri},
-    utils, RESOURCE_REFRESH_PERIOD,
+    RESOURCE_REFRESH_PERIOD,
 };
 
 use azure_core::ClientOptions;
@@ -36,9 +36,11 @@ pub enum IngestionResourceError {
 type Result<T> = std::result::Result<T, IngestionResourceError>;
 
 fn get_column_index(table: &TableV1, column_name: &str) -> Result<usize> {
-    utils::get_column_index(table, column_name).ok_or(IngestionResourceError::ColumnNotFoundError {
-        column_name: column_name.to_string(),
-    })
+    table
+        .column_index(column_name)
+        .ok_or(IngestionResourceError::ColumnNotFoundError {
+            column_name: column_name.to_string(),
+        })
 }
 
 /// Helper to get a resource URI from a table, erroring if there are no resources of the given name
@@ -76,11 +78,29 @@ where
         .collect()
 }
 
+/// A container that data can be uploaded to before being ingested
+#[derive(Debug, Clone)]
+pub struct TempStorageContainer {
+    pub client: ContainerClient,
+    /// SAS token granting the ingestion service access to the blobs of the container
+    pub sas_token: String,
+}
+
+impl ClientFromResourceUri for TempStorageContainer {
+    fn create_client(resource_uri: ResourceUri, client_options: ClientOptions) -> Self {
+        let sas_token = resource_uri.sas_token_query.clone();
+        Self {
+            client: ContainerClient::create_client(resource_uri, client_options),
+            sas_token,
+        }
+    }
+}
+
 /// Storage of the clients required for ingestion
 #[derive(Debug, Clone)]
 pub struct InnerIngestClientResources {
     pub ingestion_queues: Vec<QueueClient>,
-    pub temp_storage_containers: Vec<ContainerClient>,
+    pub temp_storage_containers: Vec<TempStorageContainer>,
 }
 
 impl TryFrom<(&TableV1, &QueuedIngestClientOptions)> for InnerIngestClientResources {
@@ -133,8 +153,9 @@ impl IngestClientResources {
             .await?;
 
         let new_resources = results
-            .tables
-            .first()
+            .primary_results()?
+            .into_iter()
+            .next()
             .ok_or(IngestionResourceError::NoTablesFound)?;
 
         InnerIngestClientResources::try_from((new_resources, &self.client_options))
@@ -35,6 +35,8 @@ pub(crate) struct ResourceUri {
     pub(crate) object_name: String,
     pub(crate) account_name: String,
     pub(crate) sas_token: StorageCredentials,
+    /// The SAS token as it appears in the URI, to authenticate the URIs of objects within the resource
+    pub(crate) sas_token_query: String,
 }
 
 impl TryFrom<&str> for ResourceUri {
@@ -80,6 +82,7 @@ impl TryFrom<&str> for ResourceUri {
             .query()
             .ok_or(ResourceUriError::MissingSasToken)?;
 
+        let sas_token_query = sas_token.to_string();
         let sas_token = StorageCredentials::sas_token(sas_token)?;
 
         Ok(Self {
@@ -87,6 +90,7 @@ impl TryFrom<&str> for ResourceUri {
             object_name: object_name.to_string(),
             account_name: account_name.to_string(),
             sas_token,
+            sas_token_query,
         })
     }
 }
@@ -142,6 +146,7 @@ mod tests {
             "https://storageaccountname.blob.core.windows.com"
         );
         assert_eq!(resource_uri.object_name, "containerobjectname");
+        assert_eq!(resource_uri.sas_token_query, "sas=token");
 
         let storage_credential_inner = std::sync::Arc::into_inner(resource_uri.sas_token.0)
             .unwrap()
@@ -241,6 +246,7 @@ mod tests {
             object_name: "queuename".to_string(),
             account_name: "mystorageaccount".to_string(),
             sas_token: StorageCredentials::sas_token("sas=token").unwrap(),
+            sas_token_query: "sas=token".to_string(),
         };
 
         let client_options = ClientOptions::default();
@@ -256,6 +262,7 @@ mod tests {
             object_name: "containername".to_string(),
             account_name: "mystorageaccount".to_string(),
             sas_token: StorageCredentials::sas_token("sas=token").unwrap(),
+            sas_token_query: "sas=token".to_string(),
         };
 
         let client_options = ClientOptions::default();
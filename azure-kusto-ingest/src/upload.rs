@@ -0,0 +1,430 @@
+//! Uploading of local data to the temporary storage containers of the ingestion service, so it can be ingested from there
+
+use std::io::Write;
+
+use azure_storage_blobs::prelude::{BlobBlockType, BlobClient, BlockId, BlockList};
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::descriptors::{BlobAuth, BlobDescriptor};
+use crate::error::Result;
+use crate::ingestion_properties::IngestionProperties;
+use crate::resource_manager::ingest_client_resources::TempStorageContainer;
+
+/// Size of the blocks that streamed data is uploaded in
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Where streamed data is buffered until it fills a block
+enum BlockBuffer {
+    Compressed(GzEncoder<Vec<u8>>),
+    Raw(Vec<u8>),
+}
+
+/// How the size of the uncompressed data is determined once all the data has been written
+enum RawDataSize {
+    /// The data is uploaded uncompressed, or compressed on the fly, so its size is the size of the data written
+    Written,
+    /// The data is gzip compressed already, holding its size in the last bytes written
+    GzipTrailer(Vec<u8>),
+    /// The data is compressed already, in a format whose uncompressed size is not read
+    Unknown,
+}
+
+/// Upload of data as it is produced, compressing it on the fly and uploading it in blocks,
+/// which are committed as a single blob once all the data has been written
+pub(crate) struct StreamingUpload {
+    blob_client: BlobClient,
+    sas_token: String,
+    source_id: Uuid,
+    buffer: BlockBuffer,
+    blocks: Vec<BlobBlockType>,
+    /// Size of the data written so far, before any compression
+    written: u64,
+    raw_data_size: RawDataSize,
+    block_size: usize,
+}
+
+impl StreamingUpload {
+    /// Starts an upload to the container, compressing the data unless its format is not
+    /// [compressible](crate::data_format::DataFormat::compressible)
+    pub(crate) fn new(
+        container: &TempStorageContainer,
+        ingestion_properties: &IngestionProperties,
+    ) -> Self {
+        Self::named(container, ingestion_properties, "stream")
+    }
+
+    /// Starts an upload of data named `name`, e.g. the name of the file it is read from.
+    ///
+    /// Data whose name ends with `.gz` or `.zip` is considered compressed already and is uploaded as is,
+    /// otherwise it is compressed as by [new](Self::new)
+    pub(crate) fn named(
+        container: &TempStorageContainer,
+        ingestion_properties: &IngestionProperties,
+        name: &str,
+    ) -> Self {
+        let source_id = Uuid::new_v4();
+        let extension = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+        let (name, buffer, raw_data_size) = match extension.as_deref() {
+            Some("gz") => (
+                name.to_string(),
+                BlockBuffer::Raw(Vec::new()),
+                RawDataSize::GzipTrailer(Vec::new()),
+            ),
+            // The uncompressed size of a zip archive is only known after reading all of its entries
+            Some("zip") => (
+                name.to_string(),
+                BlockBuffer::Raw(Vec::new()),
+                RawDataSize::Unknown,
+            ),
+            _ if ingestion_properties.data_format.compressible() => (
+                format!("{name}.gz"),
+                BlockBuffer::Compressed(GzEncoder::new(Vec::new(), Compression::default())),
+                RawDataSize::Written,
+            ),
+            _ => (
+                name.to_string(),
+                BlockBuffer::Raw(Vec::new()),
+                RawDataSize::Written,
+            ),
+        };
+
+        Self {
+            blob_client: container.client.blob_client(blob_name(
+                ingestion_properties,
+                source_id,
+                &name,
+            )),
+            sas_token: container.sas_token.clone(),
+            source_id,
+            buffer,
+            blocks: vec![],
+            written: 0,
+            raw_data_size,
+            block_size: BLOCK_SIZE,
+        }
+    }
+
+    /// Adds data to the blob, uploading a block whenever enough data is buffered
+    pub(crate) async fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.written += data.len() as u64;
+        if let RawDataSize::GzipTrailer(trailer) = &mut self.raw_data_size {
+            trailer.extend_from_slice(&data[data.len().saturating_sub(GZIP_TRAILER_SIZE)..]);
+            let excess = trailer.len().saturating_sub(GZIP_TRAILER_SIZE);
+            trailer.drain(..excess);
+        }
+        let buffer = match &mut self.buffer {
+            BlockBuffer::Compressed(encoder) => {
+                encoder.write_all(data)?;
+                encoder.get_mut()
+            }
+            BlockBuffer::Raw(buffer) => {
+                buffer.extend_from_slice(data);
+                buffer
+            }
+        };
+
+        if buffer.len() >= self.block_size {
+            let block = std::mem::take(buffer);
+            put_block(&self.blob_client, &mut self.blocks, block).await?;
+        }
+        Ok(())
+    }
+
+    /// Adds a row to the blob as a line of JSON, as ingested with the `multijson` format
+    pub(crate) async fn write_json_line(&mut self, row: &impl Serialize) -> Result<()> {
+        let mut line = serde_json::to_vec(row)?;
+        line.push(b'\n');
+        self.write(&line).await
+    }
+
+    /// Uploads the remaining data and commits the blocks, returning the descriptor of the blob to ingest from
+    pub(crate) async fn finish(mut self) -> Result<BlobDescriptor> {
+        let block = match self.buffer {
+            BlockBuffer::Compressed(encoder) => encoder.finish()?,
+            BlockBuffer::Raw(buffer) => buffer,
+        };
+        if !block.is_empty() || self.blocks.is_empty() {
+            put_block(&self.blob_client, &mut self.blocks, block).await?;
+        }
+        self.blob_client
+            .put_block_list(BlockList {
+                blocks: self.blocks,
+            })
+            .await?;
+
+        let raw_data_size = match self.raw_data_size {
+            RawDataSize::Written => Some(self.written),
+            RawDataSize::GzipTrailer(trailer) => gzip_uncompressed_size(self.written, &trailer),
+            RawDataSize::Unknown => None,
+        };
+        Ok(BlobDescriptor::new(
+            self.blob_client.url()?.to_string(),
+            raw_data_size,
+            Some(self.source_id),
+        )
+        .with_blob_auth(BlobAuth::SASToken(self.sas_token)))
+    }
+}
+
+/// Uploads a block of a blob, keeping track of its id to commit it later
+async fn put_block(
+    blob_client: &BlobClient,
+    blocks: &mut Vec<BlobBlockType>,
+    data: Vec<u8>,
+) -> Result<()> {
+    // All the block ids of a blob must have the same length
+    let block_id = BlockId::new(format!("{:010}", blocks.len()));
+    blob_client.put_block(block_id.clone(), data).await?;
+    blocks.push(BlobBlockType::new_uncommitted(block_id));
+    Ok(())
+}
+
+/// The name of a blob to upload, unique to the upload and recognizable by the database and table it is ingested into
+fn blob_name(ingestion_properties: &IngestionProperties, source_id: Uuid, name: &str) -> String {
+    format!(
+        "{}__{}__{}__{}",
+        ingestion_properties.database_name, ingestion_properties.table_name, source_id, name
+    )
+}
+
+/// Size of the trailer of a gzip stream holding the size of the uncompressed data
+const GZIP_TRAILER_SIZE: usize = 4;
+
+/// Deflate compresses data by a factor of 1032 at most, which bounds the size of the uncompressed data
+const MAX_DEFLATE_RATIO: u64 = 1032;
+
+/// Reads the size of the uncompressed data from the trailer of a gzip stream of `compressed_size` bytes.
+///
+/// The trailer holds the size modulo 2^32, so it is only trusted if the stream is too small
+/// to hold 4GiB or more of uncompressed data, and [None] is returned otherwise
+fn gzip_uncompressed_size(compressed_size: u64, trailer: &[u8]) -> Option<u64> {
+    if compressed_size.saturating_mul(MAX_DEFLATE_RATIO) > u64::from(u32::MAX) {
+        return None;
+    }
+    let trailer: [u8; GZIP_TRAILER_SIZE] = trailer.try_into().ok()?;
+    Some(u32::from_le_bytes(trailer).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    use azure_core::Url;
+    use azure_core::{
+        headers::Headers, BytesStream, ClientOptions, HttpClient, Request, Response, StatusCode,
+        TransportOptions,
+    };
+    use azure_storage::{CloudLocation, StorageCredentials};
+    use azure_storage_blobs::prelude::ClientBuilder;
+    use flate2::read::GzDecoder;
+
+    use super::*;
+    use crate::data_format::DataFormat;
+
+    fn ingestion_properties(data_format: DataFormat) -> IngestionProperties {
+        IngestionProperties {
+            database_name: "db".to_string(),
+            table_name: "table".to_string(),
+            data_format,
+            ..Default::default()
+        }
+    }
+
+    /// Stand-in for a storage account, recording the requests putting blobs and blocks into it
+    #[derive(Debug, Default)]
+    struct MockStorage {
+        blobs: Mutex<Vec<(Url, Vec<u8>)>>,
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for MockStorage {
+        async fn execute_request(&self, request: &Request) -> azure_core::Result<Response> {
+            let body = match request.body() {
+                azure_core::Body::Bytes(bytes) => bytes.to_vec(),
+                _ => panic!("Unexpected streamed body"),
+            };
+            self.blobs
+                .lock()
+                .unwrap()
+                .push((request.url().clone(), body));
+
+            let mut headers = Headers::new();
+            headers.insert("etag", "\"0x8D0000000000000\"");
+            headers.insert("last-modified", "Mon, 01 Jan 2024 00:00:00 GMT");
+            headers.insert("date", "Mon, 01 Jan 2024 00:00:00 GMT");
+            headers.insert("x-ms-request-id", Uuid::nil().to_string());
+            headers.insert("x-ms-request-server-encrypted", "true");
+            Ok(Response::new(
+                StatusCode::Created,
+                headers,
+                Box::pin(BytesStream::new_empty()),
+            ))
+        }
+    }
+
+    fn temp_storage_container(storage: Arc<MockStorage>) -> TempStorageContainer {
+        let client = ClientBuilder::with_location(
+            CloudLocation::Custom {
+                uri: "https://account.blob.core.windows.net".to_string(),
+                account: "account".to_string(),
+            },
+            StorageCredentials::sas_token("sig=secret").unwrap(),
+        )
+        .client_options(ClientOptions::new(TransportOptions::new(storage)))
+        .container_client("container");
+
+        TempStorageContainer {
+            client,
+            sas_token: "sig=secret".to_string(),
+        }
+    }
+
+    /// Uploads data named `name` in chunks of `chunk_size` bytes, returning its descriptor and the blocks put into the storage
+    async fn upload_named(
+        name: &str,
+        data: &[u8],
+        chunk_size: usize,
+        data_format: DataFormat,
+    ) -> (BlobDescriptor, Vec<(Url, Vec<u8>)>) {
+        let storage = Arc::new(MockStorage::default());
+        let container = temp_storage_container(storage.clone());
+        let mut upload =
+            StreamingUpload::named(&container, &ingestion_properties(data_format), name);
+        for chunk in data.chunks(chunk_size) {
+            upload.write(chunk).await.unwrap();
+        }
+        let descriptor = upload.finish().await.unwrap();
+
+        let mut requests = std::mem::take(&mut *storage.blobs.lock().unwrap());
+        let (list, _) = requests.pop().unwrap();
+        assert!(list.query().unwrap().contains("comp=blocklist"));
+        (descriptor, requests)
+    }
+
+    #[tokio::test]
+    async fn compresses_compressible_formats() {
+        let data = b"a,b\n1,2\n";
+        let (descriptor, blocks) = upload_named("data.csv", data, 3, DataFormat::CSV).await;
+
+        assert_eq!(
+            descriptor.uri(),
+            format!(
+                "https://account.blob.core.windows.net/container/db__table__{}__data.csv.gz?sig=secret",
+                descriptor.source_id
+            )
+        );
+        assert_eq!(descriptor.size, Some(data.len() as u64));
+
+        let mut decompressed = vec![];
+        GzDecoder::new(blocks[0].1.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[tokio::test]
+    async fn keeps_compressed_data() {
+        let (descriptor, blocks) =
+            upload_named("data.parquet", b"PAR1", 4, DataFormat::Parquet).await;
+        assert!(descriptor.uri().ends_with("__data.parquet?sig=secret"));
+        assert_eq!(blocks[0].1, b"PAR1");
+        assert_eq!(descriptor.size, Some(4));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&[0; 1000]).unwrap();
+        let compressed = encoder.finish().unwrap();
+        // Written in chunks smaller than the trailer, so that it spans several writes
+        let (descriptor, blocks) =
+            upload_named("data.csv.GZ", &compressed, 3, DataFormat::CSV).await;
+        assert!(descriptor.uri().ends_with("__data.csv.GZ?sig=secret"));
+        assert_eq!(blocks[0].1, compressed);
+        assert_eq!(descriptor.size, Some(1000));
+
+        let (descriptor, _) = upload_named("data.zip", &[1, 2], 2, DataFormat::CSV).await;
+        assert_eq!(descriptor.size, None);
+    }
+
+    #[test]
+    fn distrusts_gzip_trailer_of_large_streams() {
+        let trailer = 1000u32.to_le_bytes();
+        assert_eq!(gzip_uncompressed_size(100, &trailer), Some(1000));
+        // Large enough to hold 4GiB of uncompressed data, after which the trailer wraps around
+        assert_eq!(gzip_uncompressed_size(5 * 1024 * 1024, &trailer), None);
+        assert_eq!(gzip_uncompressed_size(2, &trailer[..2]), None);
+    }
+
+    #[tokio::test]
+    async fn uploads_json_lines() {
+        #[derive(Serialize)]
+        struct Row {
+            id: i64,
+            name: &'static str,
+        }
+
+        let storage = Arc::new(MockStorage::default());
+        let container = temp_storage_container(storage.clone());
+
+        let mut upload =
+            StreamingUpload::new(&container, &ingestion_properties(DataFormat::MultiJSON));
+        for row in [Row { id: 1, name: "a" }, Row { id: 2, name: "b" }] {
+            upload.write_json_line(&row).await.unwrap();
+        }
+        upload.finish().await.unwrap();
+
+        let requests = storage.blobs.lock().unwrap();
+        let mut decompressed = String::new();
+        GzDecoder::new(requests[0].1.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(
+            decompressed,
+            "{\"id\":1,\"name\":\"a\"}\n{\"id\":2,\"name\":\"b\"}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn uploads_streams_in_blocks() {
+        let storage = Arc::new(MockStorage::default());
+        let container = temp_storage_container(storage.clone());
+        let properties = ingestion_properties(DataFormat::CSV);
+
+        let mut upload = StreamingUpload::new(&container, &properties);
+        upload.block_size = 16 * 1024;
+        let source_id = upload.source_id;
+        // Rows of pseudo-random numbers, so that the compressed data spans several blocks
+        let data: Vec<u8> = (0u64..20_000)
+            .flat_map(|i| format!("{i},{}\n", i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).into_bytes())
+            .collect();
+        for chunk in data.chunks(1000) {
+            upload.write(chunk).await.unwrap();
+        }
+        let descriptor = upload.finish().await.unwrap();
+
+        let name = format!("db__table__{source_id}__stream.gz");
+        assert_eq!(
+            descriptor.uri(),
+            format!("https://account.blob.core.windows.net/container/{name}?sig=secret")
+        );
+        assert_eq!(descriptor.size, Some(data.len() as u64));
+
+        let requests = storage.blobs.lock().unwrap();
+        let (list, blocks) = requests.split_last().unwrap();
+        assert!(blocks.len() > 1);
+        assert!(list.0.query().unwrap().contains("comp=blocklist"));
+        let mut compressed = vec![];
+        for (url, block) in blocks {
+            assert_eq!(url.path(), format!("/container/{name}"));
+            assert!(url.query().unwrap().contains("comp=block&"));
+            compressed.extend_from_slice(block);
+        }
+
+        let mut decompressed = vec![];
+        GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+}